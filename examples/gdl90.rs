@@ -15,6 +15,11 @@ fn main() {
                     Gdl90Kind::Heartbeat(heartbeat) => {
                         println!("{:?}", heartbeat);
                     },
+                    Gdl90Kind::Uplink(uplink) => {
+                        for apdu in uplink.apdus() {
+                            println!("{:?}", apdu);
+                        }
+                    },
                     Gdl90Kind::Ownship(traffic) | Gdl90Kind::Traffic(traffic) => {
                         println!("{:?} ({}): {}, {}, {}, {}",
                             traffic.address(),
@@ -25,6 +30,18 @@ fn main() {
                             traffic.heading()
                         );
                     },
+                    Gdl90Kind::BasicReport(report) | Gdl90Kind::LongReport(report) => {
+                        if let Some(traffic) = report.traffic() {
+                            println!("{:?} ({}): {}, {}, {}, {}",
+                                traffic.address(),
+                                traffic.callsign(),
+                                traffic.latitude(),
+                                traffic.longitude(),
+                                traffic.altitude(),
+                                traffic.heading()
+                            );
+                        }
+                    },
                     Gdl90Kind::GeoAltitude(altitude) => {
                         println!("{:?}", altitude);
                     },
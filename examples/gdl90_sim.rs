@@ -0,0 +1,164 @@
+extern crate friar;
+
+use friar::coordinate::Coordinate;
+use friar::earth::Earth;
+use friar::gdl90::{Gdl90ForeFlightAhrs, Gdl90GeoAltitude, Gdl90Heartbeat, Gdl90Kind, Gdl90Traffic};
+use friar::spheroid::Spheroid;
+use std::env;
+use std::net::UdpSocket;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const FEET: f64 = 0.3048;
+const KNOTS: f64 = 1852.0 / 3600.0;
+const NAUTICAL_MILE: f64 = 1852.0;
+
+/// Seconds before the scenario starts over
+const LOOP_TIME: f64 = 600.0;
+
+/// A simulated aircraft, with altitude in feet, speed in knots and vertical speed in feet per
+/// minute
+struct Aircraft<'r> {
+    address: u32,
+    callsign: &'static str,
+    coordinate: Coordinate<'r, Earth>,
+    track: f64,
+    speed: f64,
+    vertical_speed: f64,
+}
+
+impl<'r> Aircraft<'r> {
+    fn report(&self) -> Gdl90Traffic {
+        let mut traffic = Gdl90Traffic {
+            address: self.address,
+            // Airborne, with a true track
+            misc: 0b1001,
+            integrity: 8,
+            accuracy: 9,
+            // Light aircraft
+            category: 1,
+            ..Default::default()
+        };
+        traffic.set_latitude(self.coordinate.latitude);
+        traffic.set_longitude(self.coordinate.longitude);
+        traffic.set_altitude(self.coordinate.elevation / FEET);
+        traffic.set_h_velocity(self.speed);
+        traffic.set_v_velocity(self.vertical_speed);
+        traffic.set_heading(self.track);
+        traffic.set_callsign(self.callsign);
+        traffic
+    }
+}
+
+/// Broadcast a scenario over UDP to port 4000 on localhost: an ownship flying west, one aircraft
+/// orbiting it and another crossing ahead of it
+///
+/// Usage: gdl90_sim [latitude longitude]
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (latitude, longitude) = if args.len() >= 2 {
+        (
+            args[0].parse::<f64>().expect("invalid latitude"),
+            args[1].parse::<f64>().expect("invalid longitude"),
+        )
+    } else {
+        (39.639720, -104.854705) // Cherry Creek Reservoir
+    };
+
+    let socket = UdpSocket::bind(("0.0.0.0", 0)).unwrap();
+    let earth = Earth;
+    let start = earth.coordinate(latitude, longitude, 9500.0 * FEET);
+    let ownship_track = 270.0;
+    let ownship_speed = 120.0;
+
+    let started = Instant::now();
+    let mut next_report = 0.0;
+    loop {
+        let time = started.elapsed().as_secs_f64() % LOOP_TIME;
+
+        let ownship = Aircraft {
+            address: 0xABCDEF,
+            callsign: "N12345",
+            coordinate: start.offset(ownship_speed * KNOTS * time, ownship_track, 0.0),
+            track: ownship_track,
+            speed: ownship_speed,
+            vertical_speed: 0.0,
+        };
+
+        // Every second, all reports are sent together, as batched by some receivers
+        if time >= next_report || time + 1.0 < next_report {
+            next_report = time.floor() + 1.0;
+
+            // Two nautical miles from the ownship and 500 feet above, taking two minutes for each
+            // orbit
+            let bearing = (time * 3.0) % 360.0;
+            let mut orbit = ownship.coordinate.offset(2.0 * NAUTICAL_MILE, bearing, 0.0);
+            orbit.elevation += 500.0 * FEET;
+            let orbiting = Aircraft {
+                address: 0xA00001,
+                callsign: "ORBIT1",
+                coordinate: orbit,
+                track: (bearing + 90.0) % 360.0,
+                speed: 2.0 * std::f64::consts::PI * 2.0 * 3600.0 / 120.0,
+                vertical_speed: 0.0,
+            };
+
+            // Crossing the track of the ownship five nautical miles from where it started,
+            // descending through its altitude
+            let crossing_track = (ownship_track + 90.0) % 360.0;
+            let crossing_speed = 150.0;
+            let crossing_vertical_speed = -500.0;
+            let crossing_start = start
+                .offset(5.0 * NAUTICAL_MILE, ownship_track, 0.0)
+                .offset(3.0 * NAUTICAL_MILE, (ownship_track + 270.0) % 360.0, 0.0);
+            let mut crossing_coordinate = crossing_start.offset(crossing_speed * KNOTS * time, crossing_track, 0.0);
+            crossing_coordinate.elevation += (1000.0 + crossing_vertical_speed * time / 60.0) * FEET;
+            let crossing = Aircraft {
+                address: 0xA00002,
+                callsign: "CROSS2",
+                coordinate: crossing_coordinate,
+                track: crossing_track,
+                speed: crossing_speed,
+                vertical_speed: crossing_vertical_speed,
+            };
+
+            let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs()) % 86400;
+            let mut heartbeat = Gdl90Heartbeat {
+                // GPS position valid and initialized, then UTC timing valid
+                status: [0x81, 0x01],
+                timestamp: 0,
+                counts: [0, 0],
+            };
+            heartbeat.set_seconds(seconds as u32);
+            let geo_altitude = Gdl90GeoAltitude {
+                altitude: (ownship.coordinate.elevation / FEET / 5.0).round() as i16,
+                // Vertical figure of merit of 10 meters
+                metrics: 10,
+            };
+
+            let mut datagram = Vec::new();
+            datagram.extend(Gdl90Kind::Heartbeat(heartbeat).encode());
+            datagram.extend(Gdl90Kind::Ownship(ownship.report()).encode());
+            datagram.extend(Gdl90Kind::GeoAltitude(geo_altitude).encode());
+            datagram.extend(Gdl90Kind::Traffic(orbiting.report()).encode());
+            datagram.extend(Gdl90Kind::Traffic(crossing.report()).encode());
+            if let Err(err) = socket.send_to(&datagram, ("127.0.0.1", 4000)) {
+                println!("failed to send reports: {}", err);
+            }
+        }
+
+        // Attitude five times each second
+        let ahrs = Gdl90ForeFlightAhrs {
+            roll: 0,
+            pitch: 0,
+            heading: (ownship.track * 10.0).round() as u16,
+            indicated_airspeed: ownship.speed.round() as u16,
+            true_airspeed: ownship.speed.round() as u16,
+        };
+        if let Err(err) = socket.send_to(&Gdl90Kind::ForeFlightAhrs(ahrs).encode(), ("127.0.0.1", 4000)) {
+            println!("failed to send attitude: {}", err);
+        }
+
+        thread::sleep(Duration::from_millis(200));
+    }
+}
@@ -0,0 +1,64 @@
+extern crate friar;
+
+use friar::earth::Earth;
+use friar::hgt::{HgtCache, HgtMosaic, HgtResolution, HgtTileId};
+use friar::mesh::{Mesh, MeshFrame, MeshOptions};
+use std::env;
+use std::fs::File;
+use std::io::BufWriter;
+use std::process;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() < 5 {
+        eprintln!("mesh <south> <west> <north> <east> <output.obj|.stl|.glb> [lod] [exaggeration] [ecef]");
+        process::exit(1);
+    }
+
+    let number = |i: usize| -> f64 {
+        args[i].parse().unwrap_or_else(|err| {
+            eprintln!("invalid number {:?}: {}", args[i], err);
+            process::exit(1);
+        })
+    };
+
+    let mut options = MeshOptions::new((number(0), number(1), number(2), number(3)));
+    let output = &args[4];
+    if args.len() > 5 {
+        options.lod = number(5) as u32;
+    }
+    if args.len() > 6 {
+        options.exaggeration = number(6);
+    }
+    if args.get(7).is_some_and(|arg| arg == "ecef") {
+        options.frame = MeshFrame::Ecef;
+    }
+    if output.ends_with(".stl") {
+        options.base = Some(100.0);
+    }
+
+    let resolution = HgtResolution::Three;
+    let cache = HgtCache::new("cache");
+    let mut mosaic = HgtMosaic::new(resolution);
+    let (south, west, north, east) = options.bounds;
+    for tile in HgtTileId::covering(south, west, north, east) {
+        match cache.get(tile.south(), tile.west(), resolution) {
+            Ok(file) => {
+                mosaic.insert(file).unwrap();
+            },
+            Err(err) => eprintln!("{}: {}", tile, err),
+        }
+    }
+
+    let mesh = Mesh::new(&mosaic, &Earth, &options);
+    println!("{} vertices, {} triangles", mesh.positions.len(), mesh.triangles.len());
+
+    let mut w = BufWriter::new(File::create(output).unwrap());
+    if output.ends_with(".stl") {
+        mesh.write_stl(&mut w).unwrap();
+    } else if output.ends_with(".glb") {
+        mesh.write_gltf(&mut w).unwrap();
+    } else {
+        mesh.write_obj(&mut w).unwrap();
+    }
+}
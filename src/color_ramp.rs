@@ -0,0 +1,142 @@
+use std::fs;
+use std::cmp::Ordering;
+use std::path::Path;
+
+use crate::{Error, Result};
+
+/// Colors for elevations, interpolated between stops
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorRamp {
+    pub name: String,
+    /// Elevations in meters and their colors, in increasing order of elevation
+    pub stops: Vec<(f64, (u8, u8, u8))>,
+}
+
+impl ColorRamp {
+    /// Create a ramp from stops in any order
+    pub fn new<S: Into<String>>(name: S, mut stops: Vec<(f64, (u8, u8, u8))>) -> Self {
+        stops.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+        Self {
+            name: name.into(),
+            stops,
+        }
+    }
+
+    /// Hypsometric tints, from blue below sea level through green lowlands and brown mountains to
+    /// white peaks
+    pub fn hypsometric() -> Self {
+        Self::new("hypsometric", vec![
+            (-100.0, (0x20, 0x60, 0xA0)),
+            (0.0, (0x70, 0xA8, 0x6C)),
+            (500.0, (0xA8, 0xC6, 0x82)),
+            (1000.0, (0xE6, 0xDC, 0x9A)),
+            (1500.0, (0xD9, 0xB8, 0x78)),
+            (2000.0, (0xC0, 0x94, 0x60)),
+            (3000.0, (0x9C, 0x74, 0x50)),
+            (4000.0, (0xD8, 0xD0, 0xC8)),
+            (5000.0, (0xFF, 0xFF, 0xFF)),
+        ])
+    }
+
+    /// Plain ground with snow above 3500 meters
+    pub fn snow_line() -> Self {
+        Self::new("snow line", vec![
+            (3300.0, (0x7A, 0x79, 0x4C)),
+            (3500.0, (0xF0, 0xF0, 0xF0)),
+        ])
+    }
+
+    /// Black at sea level to white at 4500 meters
+    pub fn grayscale() -> Self {
+        Self::new("grayscale", vec![
+            (0.0, (0x00, 0x00, 0x00)),
+            (4500.0, (0xFF, 0xFF, 0xFF)),
+        ])
+    }
+
+    /// A single color at every elevation
+    pub fn solid<S: Into<String>>(name: S, color: (u8, u8, u8)) -> Self {
+        Self::new(name, vec![(0.0, color)])
+    }
+
+    /// All built-in ramps
+    pub fn builtin() -> Vec<Self> {
+        vec![
+            Self::hypsometric(),
+            Self::snow_line(),
+            Self::grayscale(),
+        ]
+    }
+
+    /// Parse a gradient in the text format of gdaldem color-relief, with a line per stop of an
+    /// elevation and red, green and blue from 0 to 255, separated by spaces, tabs or commas
+    ///
+    /// Any alpha component is ignored, as are empty lines, lines starting with # and the nv line
+    /// for missing values.
+    pub fn parse<S: Into<String>>(name: S, text: &str) -> Result<Self> {
+        let mut stops = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || Error::Format(
+                format!("ColorRamp: invalid stop {:?} on line {}", line, i + 1)
+            );
+
+            let fields: Vec<&str> = line
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|field| ! field.is_empty())
+                .collect();
+            if fields.len() < 4 || fields.len() > 5 {
+                return Err(invalid());
+            }
+            if fields[0].eq_ignore_ascii_case("nv") {
+                continue;
+            }
+
+            let elevation: f64 = fields[0].parse().map_err(|_| invalid())?;
+            let r: u8 = fields[1].parse().map_err(|_| invalid())?;
+            let g: u8 = fields[2].parse().map_err(|_| invalid())?;
+            let b: u8 = fields[3].parse().map_err(|_| invalid())?;
+            stops.push((elevation, (r, g, b)));
+        }
+
+        if stops.is_empty() {
+            return Err(Error::Format("ColorRamp: no stops".to_string()));
+        }
+
+        Ok(Self::new(name, stops))
+    }
+
+    /// Read a gradient file as described in parse, named after the file
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let name = path.file_stem().map_or_else(
+            || path.display().to_string(),
+            |stem| stem.to_string_lossy().into_owned()
+        );
+        let text = fs::read_to_string(path)?;
+        Self::parse(name, &text)
+    }
+
+    /// Interpolate the color of an elevation, using the first or last stop outside of the ramp
+    pub fn color(&self, elevation: f64) -> (u8, u8, u8) {
+        let mut prev: Option<&(f64, (u8, u8, u8))> = None;
+        for stop in self.stops.iter() {
+            if elevation <= stop.0 {
+                return match prev {
+                    Some(prev) => {
+                        let t = (elevation - prev.0) / (stop.0 - prev.0);
+                        let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+                        (mix((prev.1).0, (stop.1).0), mix((prev.1).1, (stop.1).1), mix((prev.1).2, (stop.1).2))
+                    },
+                    None => stop.1,
+                };
+            }
+            prev = Some(stop);
+        }
+        prev.map_or((0x80, 0x80, 0x80), |stop| stop.1)
+    }
+}
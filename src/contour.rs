@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::coordinate::Coordinate;
+use crate::hgt::HgtSampler;
+use crate::spheroid::Spheroid;
+
+/// A line of constant elevation
+///
+/// Points are ordered so that higher ground is on the left.
+pub struct Contour<'r, R: Spheroid + 'r> {
+    /// Elevation of the line in meters
+    pub elevation: f64,
+    /// Points along the line, each at the elevation of the line
+    pub points: Vec<Coordinate<'r, R>>,
+    /// True if the last point connects back to the first
+    pub closed: bool,
+}
+
+/// Identifies where a contour crosses the edge between two neighbouring samples, by the row and
+/// column of the south or west sample and whether the edge runs north
+type EdgeKey = (i64, i64, bool);
+
+/// Part of a contour crossing one cell, as the start and end edge with their positions in samples
+type Segment = ((EdgeKey, (f64, f64)), (EdgeKey, (f64, f64)));
+
+impl<'r, R: Spheroid> Contour<'r, R> {
+    /// Trace contours at every multiple of interval meters through the samples inside bounds, given
+    /// as (south, west, north, east) in degrees, using marching squares
+    ///
+    /// Cells with a missing sample at any corner are skipped, so lines end at voids and at the
+    /// edge of the bounds.
+    pub fn generate<S: HgtSampler + ?Sized>(sampler: &S, reference: &'r R, bounds: (f64, f64, f64, f64), interval: f64) -> Vec<Self> {
+        let (south, west, north, east) = bounds;
+        let (origin_lat, origin_lon) = sampler.origin();
+        let (dlat, dlon) = sampler.spacing();
+
+        let first_row = ((south - origin_lat) / dlat - 1e-6).ceil() as i64;
+        let last_row = ((north - origin_lat) / dlat + 1e-6).floor() as i64;
+        let first_col = ((west - origin_lon) / dlon - 1e-6).ceil() as i64;
+        let last_col = ((east - origin_lon) / dlon + 1e-6).floor() as i64;
+        if interval <= 0.0 || last_row <= first_row || last_col <= first_col {
+            return Vec::new();
+        }
+
+        // Segments for each level
+        let mut levels: HashMap<i64, Vec<Segment>> = HashMap::new();
+
+        // Heights of the previous and current row of samples, to avoid sampling each twice
+        let cols = (last_col - first_col + 1) as usize;
+        let mut below: Vec<Option<f64>> = (0..cols).map(|j| sampler.height(first_row, first_col + j as i64)).collect();
+        for row in first_row..last_row {
+            let above: Vec<Option<f64>> = (0..cols).map(|j| sampler.height(row + 1, first_col + j as i64)).collect();
+            for j in 0..cols - 1 {
+                let col = first_col + j as i64;
+
+                // Corners counter-clockwise from the southwest, and edges starting at each corner
+                let h = match (below[j], below[j + 1], above[j + 1], above[j]) {
+                    (Some(a), Some(b), Some(c), Some(d)) => [a, b, c, d],
+                    _ => continue,
+                };
+                let corner = |k: usize| -> (f64, f64) {
+                    match k {
+                        0 => (row as f64, col as f64),
+                        1 => (row as f64, (col + 1) as f64),
+                        2 => ((row + 1) as f64, (col + 1) as f64),
+                        _ => ((row + 1) as f64, col as f64),
+                    }
+                };
+                let edge_key = |e: usize| -> EdgeKey {
+                    match e {
+                        0 => (row, col, false),
+                        1 => (row, col + 1, true),
+                        2 => (row + 1, col, false),
+                        _ => (row, col, true),
+                    }
+                };
+
+                let low = h.iter().cloned().fold(f64::MAX, f64::min);
+                let high = h.iter().cloned().fold(f64::MIN, f64::max);
+                // Levels are raised slightly so no sample is exactly on one, which would leave
+                // lines passing through corners
+                let epsilon = interval * 1e-6;
+                let first_level = ((low - epsilon) / interval).ceil() as i64;
+                let last_level = ((high - epsilon) / interval).floor() as i64;
+                for level in first_level..=last_level {
+                    let elevation = level as f64 * interval + epsilon;
+                    let inside = [h[0] >= elevation, h[1] >= elevation, h[2] >= elevation, h[3] >= elevation];
+
+                    let crossing = |e: usize| -> (f64, f64) {
+                        let (a, b) = (e, (e + 1) % 4);
+                        let t = (elevation - h[a]) / (h[b] - h[a]);
+                        let (pa, pb) = (corner(a), corner(b));
+                        (pa.0 + (pb.0 - pa.0) * t, pa.1 + (pb.1 - pa.1) * t)
+                    };
+
+                    let edges: Vec<usize> = (0..4).filter(|&e| inside[e] != inside[(e + 1) % 4]).collect();
+                    let pairs: Vec<(usize, usize)> = match edges.len() {
+                        2 => vec![(edges[0], edges[1])],
+                        4 => {
+                            // Saddle, resolved by the average of the corners
+                            let center_inside = (h[0] + h[1] + h[2] + h[3]) / 4.0 >= elevation;
+                            (0..4)
+                                .filter(|&k| inside[k] != center_inside)
+                                .map(|k| ((k + 3) % 4, k))
+                                .collect()
+                        },
+                        _ => continue,
+                    };
+
+                    for (e1, e2) in pairs {
+                        let (p1, p2) = (crossing(e1), crossing(e2));
+
+                        // Find a corner on a known side of the segment
+                        let reference_corner = if (e1 + 1) % 4 == e2 {
+                            e2
+                        } else if (e2 + 1) % 4 == e1 {
+                            e1
+                        } else {
+                            (0..4).find(|&k| inside[k]).unwrap_or(0)
+                        };
+                        let c = corner(reference_corner);
+
+                        // Positive when the corner is to the left, with x east and y north
+                        let cross = (p2.1 - p1.1) * (c.0 - p1.0) - (p2.0 - p1.0) * (c.1 - p1.1);
+                        let segment = if (cross > 0.0) == inside[reference_corner] {
+                            ((edge_key(e1), p1), (edge_key(e2), p2))
+                        } else {
+                            ((edge_key(e2), p2), (edge_key(e1), p1))
+                        };
+                        levels.entry(level).or_default().push(segment);
+                    }
+                }
+            }
+            below = above;
+        }
+
+        let mut level_keys: Vec<i64> = levels.keys().cloned().collect();
+        level_keys.sort();
+
+        let mut contours = Vec::new();
+        for level in level_keys {
+            let elevation = level as f64 * interval;
+            let segments = &levels[&level];
+
+            let mut by_start: HashMap<EdgeKey, usize> = HashMap::with_capacity(segments.len());
+            let mut has_previous = vec![false; segments.len()];
+            for (i, segment) in segments.iter().enumerate() {
+                by_start.insert((segment.0).0, i);
+            }
+            for segment in segments.iter() {
+                if let Some(&next) = by_start.get(&(segment.1).0) {
+                    has_previous[next] = true;
+                }
+            }
+
+            let mut used = vec![false; segments.len()];
+            let trace = |start: usize, used: &mut Vec<bool>| -> Self {
+                let position = |p: (f64, f64)| reference.coordinate(
+                    origin_lat + p.0 * dlat,
+                    origin_lon + p.1 * dlon,
+                    elevation
+                );
+
+                let mut points = vec![position((segments[start].0).1)];
+                let mut closed = false;
+                let mut i = start;
+                loop {
+                    used[i] = true;
+                    points.push(position((segments[i].1).1));
+                    match by_start.get(&(segments[i].1).0) {
+                        Some(&next) if next == start => {
+                            closed = true;
+                            points.pop();
+                            break;
+                        },
+                        Some(&next) if ! used[next] => i = next,
+                        _ => break,
+                    }
+                }
+
+                Self {
+                    elevation,
+                    points,
+                    closed,
+                }
+            };
+
+            // Open lines first, from their start, then the remaining loops
+            for i in 0..segments.len() {
+                if ! used[i] && ! has_previous[i] {
+                    contours.push(trace(i, &mut used));
+                }
+            }
+            for i in 0..segments.len() {
+                if ! used[i] {
+                    contours.push(trace(i, &mut used));
+                }
+            }
+        }
+
+        contours
+    }
+
+    /// Write contours as a GeoJSON FeatureCollection of LineStrings with an elevation property
+    pub fn write_geojson<W: Write>(contours: &[Self], w: &mut W) -> io::Result<()> {
+        write!(w, "{{\"type\":\"FeatureCollection\",\"features\":[")?;
+        for (i, contour) in contours.iter().enumerate() {
+            if i > 0 {
+                write!(w, ",")?;
+            }
+            write!(
+                w,
+                "{{\"type\":\"Feature\",\"properties\":{{\"elevation\":{}}},\"geometry\":{{\"type\":\"LineString\",\"coordinates\":[",
+                contour.elevation
+            )?;
+            let first = if contour.closed { contour.points.first() } else { None };
+            for (j, point) in contour.points.iter().chain(first).enumerate() {
+                if j > 0 {
+                    write!(w, ",")?;
+                }
+                write!(w, "[{:.7},{:.7}]", point.longitude, point.latitude)?;
+            }
+            write!(w, "]}}}}")?;
+        }
+        writeln!(w, "]}}")
+    }
+}
@@ -0,0 +1,109 @@
+use std::{error, fmt, io, result};
+
+pub type Result<T> = result::Result<T, Error>;
+
+/// Errors returned by this library, separated by source so callers can tell a missing tile from a
+/// failed download or a corrupt file
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing a file or socket failed
+    Io(io::Error),
+    /// Downloading failed
+    Network(reqwest::Error),
+    /// A zip archive could not be read or written
+    Archive(zip::result::ZipError),
+    /// A CSV file could not be parsed
+    Csv(csv::Error),
+    /// An OpenStreetMap PBF file could not be read
+    OsmPbf(osmpbfreader::Error),
+    /// Elevation data, or a file used with it such as a raster, mask or color ramp, is malformed
+    Format(String),
+    /// A message received from a simulator or receiver could not be decoded
+    Decode(String),
+    /// Data failed a checksum or frame check sequence, so it was corrupted in storage or transit
+    Checksum(String),
+    /// An argument is outside of the values that are supported
+    OutOfRange(String),
+    /// Data does not exist, such as a tile over the ocean
+    NotFound(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "{}", err),
+            Error::Network(err) => write!(f, "network: {}", err),
+            Error::Archive(err) => write!(f, "archive: {}", err),
+            Error::Csv(err) => write!(f, "CSV: {}", err),
+            Error::OsmPbf(err) => write!(f, "OSM PBF: {}", err),
+            Error::Format(message)
+            | Error::Decode(message)
+            | Error::Checksum(message)
+            | Error::OutOfRange(message)
+            | Error::NotFound(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Network(err) => Some(err),
+            Error::Archive(err) => Some(err),
+            Error::Csv(err) => Some(err),
+            Error::OsmPbf(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::Network(err)
+    }
+}
+
+impl From<zip::result::ZipError> for Error {
+    fn from(err: zip::result::ZipError) -> Self {
+        match err {
+            zip::result::ZipError::Io(io_err) => Error::Io(io_err),
+            _ => Error::Archive(err),
+        }
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(err: csv::Error) -> Self {
+        Error::Csv(err)
+    }
+}
+
+impl From<osmpbfreader::Error> for Error {
+    fn from(err: osmpbfreader::Error) -> Self {
+        match err {
+            osmpbfreader::Error::Io(io_err) => Error::Io(io_err),
+            _ => Error::OsmPbf(err),
+        }
+    }
+}
+
+/// Allows errors to pass through functions returning `io::Result`, such as writers
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        let kind = match err {
+            Error::Io(io_err) => return io_err,
+            Error::Format(_) | Error::Decode(_) | Error::Checksum(_) => io::ErrorKind::InvalidData,
+            Error::OutOfRange(_) => io::ErrorKind::InvalidInput,
+            Error::NotFound(_) => io::ErrorKind::NotFound,
+            _ => io::ErrorKind::Other,
+        };
+        io::Error::new(kind, err)
+    }
+}
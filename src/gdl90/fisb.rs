@@ -0,0 +1,616 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Characters of the six bit DLAC alphabet. ETX (0) ends text, tab (28) is followed by a count of
+/// spaces, and RS (29) separates records.
+const DLAC: &[u8; 64] = b"\x03ABCDEFGHIJKLMNOPQRSTUVWXYZ\x1A\t\x1E\n| !\"#$%&'()*+,-./0123456789:;<=>?";
+
+/// Decode DLAC text, which packs four characters into every three bytes
+pub fn dlac_decode(data: &[u8]) -> String {
+    let mut text = String::new();
+    let mut tab = false;
+    for i in 0..data.len() * 4 / 3 {
+        let bit = i * 6;
+        let byte = bit / 8;
+        let pair = ((data[byte] as u16) << 8) | (data.get(byte + 1).cloned().unwrap_or(0) as u16);
+        let ch = ((pair >> (10 - bit % 8)) & 0x3F) as usize;
+
+        if tab {
+            for _ in 0..ch {
+                text.push(' ');
+            }
+            tab = false;
+        } else if ch == 28 {
+            tab = true;
+        } else {
+            text.push(DLAC[ch] as char);
+        }
+    }
+    text
+}
+
+/// Time of an APDU or record, which may leave out the date or seconds
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FisbTime {
+    pub month: Option<u8>,
+    pub day: Option<u8>,
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: Option<u8>,
+}
+
+/// A report from a generic text product, such as a METAR, TAF or PIREP
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FisbTextReport {
+    /// Type of report, such as METAR, SPECI, TAF, TAF.AMD, PIREP or WINDS
+    pub kind: String,
+    /// Station or location identifier
+    pub location: String,
+    /// Time as sent, such as 121853Z
+    pub time: String,
+    /// Text of the report
+    pub text: String,
+}
+
+impl FisbTextReport {
+    /// Split decoded text into reports, each starting with its type, location and time
+    fn parse_all(text: &str) -> Vec<Self> {
+        let text = text.split('\x03').next().unwrap_or("");
+        let mut reports = Vec::new();
+        for record in text.split('\x1E') {
+            let record = record.trim_matches(|c: char| c.is_whitespace() || c == '\x1A');
+            if record.is_empty() {
+                continue;
+            }
+
+            let mut parts = record.splitn(4, ' ');
+            reports.push(Self {
+                kind: parts.next().unwrap_or("").to_string(),
+                location: parts.next().unwrap_or("").to_string(),
+                time: parts.next().unwrap_or("").to_string(),
+                text: parts.next().unwrap_or("").to_string(),
+            });
+        }
+        reports
+    }
+}
+
+/// Width in degrees of longitude of NEXRAD blocks below 60 degrees of latitude
+const NEXRAD_BLOCK_WIDTH: f64 = 48.0 / 60.0;
+/// Width of blocks from 60 degrees of latitude, which only use even block numbers
+const NEXRAD_WIDE_BLOCK_WIDTH: f64 = 96.0 / 60.0;
+/// Height in degrees of latitude of NEXRAD blocks
+const NEXRAD_BLOCK_HEIGHT: f64 = 4.0 / 60.0;
+/// First block number at 60 degrees of latitude
+const NEXRAD_BLOCK_THRESHOLD: u32 = 405000;
+/// Blocks around each ring of latitude
+const NEXRAD_BLOCKS_PER_RING: u32 = 450;
+/// Blocks around each ring of latitude from 60 degrees
+const NEXRAD_WIDE_BLOCKS_PER_RING: u32 = 225;
+
+/// Columns of bins in a NEXRAD block
+pub const NEXRAD_BLOCK_COLUMNS: usize = 32;
+/// Rows of bins in a NEXRAD block
+pub const NEXRAD_BLOCK_ROWS: usize = 4;
+
+/// Precipitation reflectivity over a block of 32 by 4 bins
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FisbNexradBlock {
+    pub block: u32,
+    /// Block is in the southern hemisphere
+    pub south: bool,
+    /// Scale factor of the block size, 0 for 1, 1 for 5 and 2 for 9
+    pub scale: u8,
+    /// Intensity of each bin from 0 to 7, in rows from north to south of columns from west to
+    /// east. Empty blocks are all zero.
+    pub bins: [u8; NEXRAD_BLOCK_COLUMNS * NEXRAD_BLOCK_ROWS],
+}
+
+impl FisbNexradBlock {
+    fn new(block: u32, south: bool, scale: u8) -> Self {
+        Self {
+            block,
+            south,
+            scale,
+            bins: [0; NEXRAD_BLOCK_COLUMNS * NEXRAD_BLOCK_ROWS],
+        }
+    }
+
+    /// Area covered as (north latitude, west longitude, height, width) in degrees
+    pub fn bounds(&self) -> (f64, f64, f64, f64) {
+        let scale = match self.scale {
+            1 => 5.0,
+            2 => 9.0,
+            _ => 1.0,
+        };
+
+        let block = if self.block >= NEXRAD_BLOCK_THRESHOLD { self.block & !1 } else { self.block };
+        let latitude = NEXRAD_BLOCK_HEIGHT * (block / NEXRAD_BLOCKS_PER_RING) as f64;
+        let mut west = (block % NEXRAD_BLOCKS_PER_RING) as f64 * NEXRAD_BLOCK_WIDTH;
+        if west > 180.0 {
+            west -= 360.0;
+        }
+        let width = if block >= NEXRAD_BLOCK_THRESHOLD { NEXRAD_WIDE_BLOCK_WIDTH } else { NEXRAD_BLOCK_WIDTH };
+
+        let north = if self.south { -latitude } else { latitude + NEXRAD_BLOCK_HEIGHT };
+        (north, west, NEXRAD_BLOCK_HEIGHT * scale, width * scale)
+    }
+
+    /// Intensity of the bin at a row counted southward and a column counted eastward
+    pub fn bin(&self, row: usize, col: usize) -> u8 {
+        self.bins[row * NEXRAD_BLOCK_COLUMNS + col]
+    }
+}
+
+/// Decode NEXRAD blocks, either one block of run-length encoded bins or a set of empty blocks
+fn nexrad_blocks(data: &[u8]) -> Option<Vec<FisbNexradBlock>> {
+    if data.len() < 4 {
+        return None;
+    }
+
+    let run_length = data[0] & 0x80 != 0;
+    let south = data[0] & 0x40 != 0;
+    let scale = (data[0] & 0x30) >> 4;
+    let block = ((data[0] as u32 & 0x0F) << 16) | ((data[1] as u32) << 8) | (data[2] as u32);
+
+    if run_length {
+        let mut nexrad = FisbNexradBlock::new(block, south, scale);
+        let mut i = 0;
+        for &byte in data[3..].iter() {
+            let intensity = byte & 0x07;
+            let run = (byte >> 3) as usize + 1;
+            for _ in 0..run {
+                *nexrad.bins.get_mut(i)? = intensity;
+                i += 1;
+            }
+        }
+        Some(vec![nexrad])
+    } else {
+        // The first block is empty, and a bitmap marks other empty blocks that follow it, wrapping
+        // around its ring of latitude. The low nibble of the first bitmap byte is the number of
+        // bitmap bytes, including itself.
+        let (ring_start, ring_size) = if block >= NEXRAD_BLOCK_THRESHOLD {
+            (block - (block - NEXRAD_BLOCK_THRESHOLD) % NEXRAD_WIDE_BLOCKS_PER_RING, NEXRAD_WIDE_BLOCKS_PER_RING)
+        } else {
+            (block - block % NEXRAD_BLOCKS_PER_RING, NEXRAD_BLOCKS_PER_RING)
+        };
+        let following = |offset: u32| ring_start + (block - ring_start + offset) % ring_size;
+
+        let mut blocks = vec![FisbNexradBlock::new(block, south, scale)];
+        for bit in 4..8 {
+            if data[3] & (1 << bit) != 0 {
+                blocks.push(FisbNexradBlock::new(following(bit - 3), south, scale));
+            }
+        }
+        let count = (data[3] & 0x0F).max(1) as usize;
+        for (i, &byte) in data.get(4..3 + count)?.iter().enumerate() {
+            for bit in 0..8 {
+                if byte & (1 << bit) != 0 {
+                    blocks.push(FisbNexradBlock::new(following(5 + 8 * i as u32 + bit), south, scale));
+                }
+            }
+        }
+        Some(blocks)
+    }
+}
+
+/// A point of a graphic overlay
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FisbVertex {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Altitude in feet
+    pub altitude: f64,
+}
+
+/// Shape of a graphic overlay
+#[derive(Clone, Debug, PartialEq)]
+pub enum FisbGeometry {
+    /// Polygon with altitudes above mean sea level
+    PolygonMsl(Vec<FisbVertex>),
+    /// Polygon with altitudes above ground level
+    PolygonAgl(Vec<FisbVertex>),
+    /// Point with altitude above ground level
+    PointAgl(FisbVertex),
+    /// Geometry option not decoded, with its raw vertex data
+    Other(u8, Vec<u8>),
+}
+
+/// Shape and validity of an area, such as a TFR, SUA or AIRMET
+#[derive(Clone, Debug, PartialEq)]
+pub struct FisbGraphic {
+    pub report_number: u16,
+    pub report_year: u8,
+    /// Report is active rather than cancelled
+    pub active: bool,
+    /// Identifies parts of one report, from 1
+    pub overlay: u8,
+    /// Numeric or alphanumeric label of the object
+    pub label: String,
+    pub object_type: u8,
+    pub object_status: u8,
+    /// Time the report starts to apply, if not immediately
+    pub start: Option<FisbTime>,
+    /// Time the report stops applying, if not until further notice
+    pub end: Option<FisbTime>,
+    pub geometry: FisbGeometry,
+}
+
+/// A record of a text with graphics product, such as a NOTAM with the text and shape of a TFR
+#[derive(Clone, Debug, PartialEq)]
+pub enum FisbRecord {
+    Text {
+        report_number: u16,
+        report_year: u8,
+        active: bool,
+        text: String,
+    },
+    Graphic(FisbGraphic),
+}
+
+/// Read a time of four bytes, as month, day, hours and minutes
+fn record_time(data: &[u8]) -> Option<FisbTime> {
+    let data = data.get(..4)?;
+    Some(FisbTime {
+        month: Some(data[0]),
+        day: Some(data[1]),
+        hours: data[2],
+        minutes: data[3],
+        seconds: None,
+    })
+}
+
+/// Read the common start of text and graphic records, returning the report number, year and
+/// whether it is active
+fn record_report(data: &[u8]) -> (u16, u8, bool) {
+    (
+        ((data[2] as u16) << 6) | ((data[3] as u16) >> 2),
+        ((data[3] & 0x03) << 5) | (data[4] >> 3),
+        data[4] & 0x04 != 0,
+    )
+}
+
+/// Decode extended range vertices of 19 bit longitude, 19 bit latitude and 10 bit altitude in
+/// hundreds of feet
+fn extended_vertices(data: &[u8], count: usize) -> Option<Vec<FisbVertex>> {
+    let mut vertices = Vec::with_capacity(count);
+    for chunk in data.get(..count * 6)?.chunks(6) {
+        let raw_lon = ((chunk[0] as u32) << 11) | ((chunk[1] as u32) << 3) | ((chunk[2] as u32) >> 5);
+        let raw_lat = ((chunk[2] as u32 & 0x1F) << 14) | ((chunk[3] as u32) << 6) | ((chunk[4] as u32) >> 2);
+        let raw_alt = ((chunk[4] as u32 & 0x03) << 8) | (chunk[5] as u32);
+
+        let mut latitude = raw_lat as f64 * 360.0 / 524288.0;
+        if latitude > 90.0 {
+            latitude -= 180.0;
+        }
+        let mut longitude = raw_lon as f64 * 360.0 / 524288.0;
+        if longitude > 180.0 {
+            longitude -= 360.0;
+        }
+        vertices.push(FisbVertex {
+            latitude,
+            longitude,
+            altitude: raw_alt as f64 * 100.0,
+        });
+    }
+    Some(vertices)
+}
+
+fn graphic_record(data: &[u8]) -> Option<FisbGraphic> {
+    let (report_number, report_year, active) = record_report(data);
+    let overlay = ((*data.get(7)? & 0x1E) >> 1) + 1;
+
+    let (label, mut pos) = if data[7] & 0x01 == 0 {
+        let label = data.get(8..10)?;
+        ((((label[0] as u16) << 8) | (label[1] as u16)).to_string(), 10)
+    } else {
+        (dlac_decode(data.get(8..17)?).trim_end_matches(['\x03', ' ']).to_string(), 17)
+    };
+
+    let flags = *data.get(pos)?;
+    let object_type = *data.get(pos + 1)? >> 4;
+    let object_status = data[pos + 1] & 0x0F;
+    pos += 2;
+    // Object qualifier
+    if flags & 0x40 != 0 {
+        pos += 3;
+    }
+
+    let options = *data.get(pos)?;
+    let applicability = options >> 6;
+    let geometry_option = options & 0x0F;
+    let vertex_count = (*data.get(pos + 1)? & 0x3F) as usize + 1;
+    pos += 2;
+
+    let start = if applicability & 0x01 != 0 {
+        let time = record_time(data.get(pos..)?)?;
+        pos += 4;
+        Some(time)
+    } else {
+        None
+    };
+    let end = if applicability & 0x02 != 0 {
+        let time = record_time(data.get(pos..)?)?;
+        pos += 4;
+        Some(time)
+    } else {
+        None
+    };
+
+    let vertex_data = data.get(pos..)?;
+    let geometry = match geometry_option {
+        3 => FisbGeometry::PolygonMsl(extended_vertices(vertex_data, vertex_count)?),
+        4 => FisbGeometry::PolygonAgl(extended_vertices(vertex_data, vertex_count)?),
+        9 => FisbGeometry::PointAgl(*extended_vertices(vertex_data, 1)?.first()?),
+        other => FisbGeometry::Other(other, vertex_data.to_vec()),
+    };
+
+    Some(FisbGraphic {
+        report_number,
+        report_year,
+        active,
+        overlay,
+        label,
+        object_type,
+        object_status,
+        start,
+        end,
+        geometry,
+    })
+}
+
+/// Decode the records of a text with graphics product
+fn records(data: &[u8]) -> Option<Vec<FisbRecord>> {
+    let format = *data.first()? >> 4;
+    let count = (*data.get(1)? >> 4) as usize;
+
+    let mut records = Vec::with_capacity(count);
+    let mut pos = 6;
+    for _ in 0..count {
+        let record = data.get(pos..)?;
+        let length = (((*record.first()? as usize) << 8) | (*record.get(1)? as usize)).max(5);
+        let record = record.get(..length)?;
+        records.push(match format {
+            2 => FisbRecord::Graphic(graphic_record(record)?),
+            8 => {
+                let (report_number, report_year, active) = record_report(record);
+                FisbRecord::Text {
+                    report_number,
+                    report_year,
+                    active,
+                    text: dlac_decode(&record[5..]).trim_end_matches(['\x03', ' ']).to_string(),
+                }
+            },
+            _ => return None,
+        });
+        pos += length;
+    }
+    Some(records)
+}
+
+/// Decoded payload of an APDU
+#[derive(Clone, Debug, PartialEq)]
+pub enum FisbProduct {
+    /// Generic text, such as METARs, TAFs and PIREPs (product 413)
+    Text(Vec<FisbTextReport>),
+    /// Regional (product 63) or CONUS (product 64) NEXRAD reflectivity
+    Nexrad(Vec<FisbNexradBlock>),
+    /// Text with graphics, such as NOTAMs and TFRs (product 8), AIRMETs, SIGMETs and SUA
+    Records(Vec<FisbRecord>),
+    /// Part of a product split over several APDUs, which FisbReassembler puts back together
+    Segment(FisbSegment),
+    /// Product that is not decoded, or could not be, with its raw payload
+    Other(Vec<u8>),
+}
+
+/// Part of a product file that is too long for one APDU
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FisbSegment {
+    /// Identifies the product file, shared by all of its segments
+    pub file_id: u16,
+    /// Number of segments in the product file
+    pub file_length: u16,
+    /// Number of this segment, from 1
+    pub number: u16,
+    /// Part of the payload of the product file
+    pub data: Vec<u8>,
+}
+
+/// Read a big endian field of up to 32 bits, starting at a bit counted from the first byte
+fn bit_field(data: &[u8], start: usize, len: usize) -> Option<u32> {
+    let mut value = 0;
+    for bit in start..start + len {
+        let byte = *data.get(bit / 8)?;
+        value = (value << 1) | ((byte >> (7 - bit % 8)) & 0x01) as u32;
+    }
+    Some(value)
+}
+
+/// An application protocol data unit of a FIS-B product, carried in the information frames of
+/// UAT uplinks
+///
+/// Adapted from DO-358 and the decoders of dump978 and Stratux
+#[derive(Clone, Debug, PartialEq)]
+pub struct FisbApdu {
+    pub product_id: u16,
+    /// Application method flag
+    pub a_flag: bool,
+    /// Geographic location flag
+    pub g_flag: bool,
+    /// Provider specific flag
+    pub p_flag: bool,
+    pub time: FisbTime,
+    pub product: FisbProduct,
+}
+
+impl FisbApdu {
+    /// Product IDs decoded as text with graphics
+    const RECORD_PRODUCTS: [u16; 7] = [8, 11, 12, 13, 14, 15, 16];
+
+    /// Decode an APDU from the data of an information frame
+    ///
+    /// The header is packed into bits, with the segmentation fields, when present, directly after
+    /// the time, and the payload starts at the next byte. APDUs with geographic location fields are
+    /// not decoded, and their product is Other with everything after the time.
+    pub fn new(data: &[u8]) -> Option<Self> {
+        if data.len() < 4 {
+            return None;
+        }
+
+        let product_id = (((data[0] & 0x1F) as u16) << 6) | ((data[1] >> 2) as u16);
+        let segmented = data[1] & 0x02 != 0;
+        let time_option = ((data[1] & 0x01) << 1) | (data[2] >> 7);
+        let g_flag = data[0] & 0x40 != 0;
+        let (time, mut header_bits): (FisbTime, usize) = match time_option {
+            0 => (FisbTime {
+                month: None,
+                day: None,
+                hours: (data[2] & 0x7C) >> 2,
+                minutes: ((data[2] & 0x03) << 4) | (data[3] >> 4),
+                seconds: None,
+            }, 28),
+            1 => (FisbTime {
+                month: None,
+                day: None,
+                hours: (data[2] & 0x7C) >> 2,
+                minutes: ((data[2] & 0x03) << 4) | (data[3] >> 4),
+                seconds: Some(((data[3] & 0x0F) << 2) | (*data.get(4)? >> 6)),
+            }, 34),
+            2 => (FisbTime {
+                month: Some((data[2] & 0x78) >> 3),
+                day: Some(((data[2] & 0x07) << 2) | (data[3] >> 6)),
+                hours: (data[3] & 0x3E) >> 1,
+                minutes: ((data[3] & 0x01) << 5) | (*data.get(4)? >> 3),
+                seconds: None,
+            }, 37),
+            _ => (FisbTime {
+                month: Some((data[2] & 0x78) >> 3),
+                day: Some(((data[2] & 0x07) << 2) | (data[3] >> 6)),
+                hours: (data[3] & 0x3E) >> 1,
+                minutes: ((data[3] & 0x01) << 5) | (*data.get(4)? >> 3),
+                seconds: Some(((data[4] & 0x07) << 3) | (*data.get(5)? >> 5)),
+            }, 43),
+        };
+
+        let product = if g_flag {
+            FisbProduct::Other(data.get(header_bits.div_ceil(8)..)?.to_vec())
+        } else if segmented {
+            let file_id = bit_field(data, header_bits, 10)? as u16;
+            let file_length = bit_field(data, header_bits + 10, 9)? as u16;
+            let number = bit_field(data, header_bits + 19, 9)? as u16;
+            header_bits += 28;
+            FisbProduct::Segment(FisbSegment {
+                file_id,
+                file_length,
+                number,
+                data: data.get(header_bits.div_ceil(8)..)?.to_vec(),
+            })
+        } else {
+            Self::decode(product_id, data.get(header_bits.div_ceil(8)..)?)
+        };
+
+        Some(Self {
+            product_id,
+            a_flag: data[0] & 0x80 != 0,
+            g_flag,
+            p_flag: data[0] & 0x20 != 0,
+            time,
+            product,
+        })
+    }
+    /// Decode the complete payload of a product
+    fn decode(product_id: u16, payload: &[u8]) -> FisbProduct {
+        let decoded = match product_id {
+            413 => Some(FisbProduct::Text(FisbTextReport::parse_all(&dlac_decode(payload)))),
+            63 | 64 => nexrad_blocks(payload).map(FisbProduct::Nexrad),
+            id if Self::RECORD_PRODUCTS.contains(&id) => records(payload).map(FisbProduct::Records),
+            _ => None,
+        };
+        decoded.unwrap_or_else(|| FisbProduct::Other(payload.to_vec()))
+    }
+}
+
+/// Seconds that the segments of a product file are kept while waiting for the rest
+const FISB_SEGMENT_TIMEOUT_SECS: u64 = 600;
+
+/// Segments of a product file received so far
+#[derive(Debug)]
+struct FisbParts {
+    /// When the first segment was received
+    received: Instant,
+    /// Time of the first segment of the file
+    time: FisbTime,
+    parts: Vec<Option<Vec<u8>>>,
+}
+
+/// Puts products split over several APDUs back together, such as long lists of NOTAMs and TFRs
+///
+/// Segments are kept by product and file ID until every segment of the file has arrived. A file
+/// ID that is reused with a different number of segments starts over, and files with segments
+/// that never arrive are dropped after 10 minutes.
+#[derive(Debug, Default)]
+pub struct FisbReassembler {
+    files: HashMap<(u16, u16), FisbParts>,
+}
+
+impl FisbReassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an APDU, returning it if it is not a segment, the whole product if it was the last
+    /// missing segment, or None otherwise
+    pub fn push(&mut self, apdu: FisbApdu) -> Option<FisbApdu> {
+        let segment = match apdu.product {
+            FisbProduct::Segment(ref segment) => segment,
+            _ => return Some(apdu),
+        };
+        if segment.number == 0 || segment.number > segment.file_length {
+            return None;
+        }
+
+        let now = Instant::now();
+        self.expire(now);
+
+        let key = (apdu.product_id, segment.file_id);
+        let file_length = segment.file_length as usize;
+        let file = self.files.entry(key).or_insert_with(|| FisbParts {
+            received: now,
+            time: apdu.time,
+            parts: Vec::new(),
+        });
+        if file.parts.len() != file_length {
+            *file = FisbParts {
+                received: now,
+                time: apdu.time,
+                parts: vec![None; file_length],
+            };
+        }
+        if segment.number == 1 {
+            file.time = apdu.time;
+        }
+        file.parts[segment.number as usize - 1] = Some(segment.data.clone());
+        if file.parts.iter().any(|part| part.is_none()) {
+            return None;
+        }
+
+        let file = self.files.remove(&key)?;
+        let payload: Vec<u8> = file.parts.into_iter().flatten().flatten().collect();
+        Some(FisbApdu {
+            time: file.time,
+            product: FisbApdu::decode(apdu.product_id, &payload),
+            ..apdu
+        })
+    }
+
+    /// Drop product files that have been waiting too long at now for their missing segments
+    pub fn expire(&mut self, now: Instant) {
+        let timeout = Duration::from_secs(FISB_SEGMENT_TIMEOUT_SECS);
+        self.files.retain(|_, file| now.saturating_duration_since(file.received) < timeout);
+    }
+
+    /// Number of product files with segments still missing
+    pub fn pending(&self) -> usize {
+        self.files.len()
+    }
+}
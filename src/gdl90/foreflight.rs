@@ -22,6 +22,17 @@ impl Gdl90ForeFlightAhrs {
         })
     }
 
+    /// Encode as message data, after the ForeFlight sub-ID
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(10);
+        data.extend_from_slice(&self.roll.to_be_bytes());
+        data.extend_from_slice(&self.pitch.to_be_bytes());
+        data.extend_from_slice(&self.heading.to_be_bytes());
+        data.extend_from_slice(&self.indicated_airspeed.to_be_bytes());
+        data.extend_from_slice(&self.true_airspeed.to_be_bytes());
+        data
+    }
+
     pub fn roll(&self) -> Option<f64> {
         if self.roll == 0x7FFF {
             None
@@ -0,0 +1,124 @@
+use std::io::{self, Read};
+
+use crate::{Error, Result};
+use crate::gdl90::{Gdl90Message, FLAG};
+
+/// Frames longer than this without an ending flag are discarded. The longest message, an uplink,
+/// is 435 bytes with its ID and frame check sequence, or twice that with every byte escaped.
+const MAX_FRAME: usize = 1024;
+
+/// Splits a stream of bytes, such as from a serial port or batched datagrams, into messages
+///
+/// Frames may be split across any number of reads, and several may arrive in one read. Each frame
+/// is delimited by flag bytes, which may be shared between neighbouring frames. Frames that fail
+/// their frame check sequence or are malformed are skipped and counted.
+///
+/// Iterating stops when more bytes are needed, and continues after they are added.
+pub struct Gdl90Framer {
+    buf: Vec<u8>,
+    /// Offset in buf of the first byte that has not been framed
+    pos: usize,
+    crc_errors: u64,
+    frame_errors: u64,
+}
+
+impl Default for Gdl90Framer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Gdl90Framer {
+    pub fn new() -> Self {
+        Self {
+            buf: Vec::new(),
+            pos: 0,
+            crc_errors: 0,
+            frame_errors: 0,
+        }
+    }
+
+    /// Add received bytes
+    pub fn push(&mut self, data: &[u8]) {
+        // Framed bytes are dropped once they are at least half of the buffer, so each byte is
+        // moved at most once on average
+        if self.pos > 0 && self.pos >= self.buf.len() / 2 {
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+        }
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Read once from a reader and add the bytes, returning the number read
+    ///
+    /// A reader in non-blocking mode with nothing to read returns zero.
+    pub fn read<R: Read>(&mut self, reader: &mut R) -> Result<usize> {
+        let mut data = [0; 4096];
+        match reader.read(&mut data) {
+            Ok(count) => {
+                self.push(&data[..count]);
+                Ok(count)
+            },
+            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => Ok(0),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Number of frames skipped because they failed their frame check sequence
+    pub fn crc_errors(&self) -> u64 {
+        self.crc_errors
+    }
+
+    /// Number of frames skipped because they were malformed
+    pub fn frame_errors(&self) -> u64 {
+        self.frame_errors
+    }
+}
+
+impl Iterator for Gdl90Framer {
+    type Item = Gdl90Message;
+
+    /// Take the next valid message from the bytes received so far
+    fn next(&mut self) -> Option<Gdl90Message> {
+        loop {
+            // Discard anything before the starting flag, such as the end of a frame that was
+            // partly missed when the stream was opened
+            let start = match self.buf[self.pos..].iter().position(|&byte| byte == FLAG) {
+                Some(some) => self.pos + some,
+                None => {
+                    self.buf.clear();
+                    self.pos = 0;
+                    return None;
+                }
+            };
+            self.pos = start;
+
+            let end = match self.buf[start + 1..].iter().position(|&byte| byte == FLAG) {
+                Some(some) => start + some + 1,
+                None => {
+                    if self.buf.len() - start > MAX_FRAME {
+                        self.frame_errors += 1;
+                        self.buf.clear();
+                        self.pos = 0;
+                    }
+                    return None;
+                }
+            };
+
+            // Neighbouring frames with their own flags
+            if end == start + 1 {
+                self.pos = end;
+                continue;
+            }
+
+            let result = Gdl90Message::new(&self.buf[start..=end]);
+            // The ending flag may also start the next frame
+            self.pos = end;
+            match result {
+                Ok(msg) => return Some(msg),
+                Err(Error::Checksum(_)) => self.crc_errors += 1,
+                Err(_) => self.frame_errors += 1,
+            }
+        }
+    }
+}
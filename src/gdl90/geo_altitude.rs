@@ -16,6 +16,14 @@ impl Gdl90GeoAltitude {
         })
     }
 
+    /// Encode as message data
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(4);
+        data.extend_from_slice(&self.altitude.to_be_bytes());
+        data.extend_from_slice(&self.metrics.to_be_bytes());
+        data
+    }
+
     pub fn altitude(&self) -> f64 {
         (self.altitude as f64) * 5.0
     }
@@ -1,6 +1,8 @@
 #[derive(Debug)]
 pub struct Gdl90Heartbeat {
     pub status: [u8; 2],
+    /// Lower 16 bits of the seconds since UTC midnight, with the highest bit in the second status
+    /// byte
     pub timestamp: u16,
     pub counts: [u8; 2]
 }
@@ -13,8 +15,91 @@ impl Gdl90Heartbeat {
 
         Some(Self {
             status: [data[0], data[1]],
-            timestamp: (data[1] as u16) | ((data[2] as u16) << 8),
-            counts: [data[3], data[4]]
+            timestamp: (data[2] as u16) | ((data[3] as u16) << 8),
+            counts: [data[4], data[5]]
         })
     }
+
+    /// Encode as message data, with the timestamp least significant byte first
+    pub fn encode(&self) -> Vec<u8> {
+        vec![
+            self.status[0],
+            self.status[1],
+            self.timestamp as u8,
+            (self.timestamp >> 8) as u8,
+            self.counts[0],
+            self.counts[1],
+        ]
+    }
+
+    /// GPS position is valid
+    pub fn gps_position_valid(&self) -> bool {
+        self.status[0] & 0x80 != 0
+    }
+
+    /// Receiver has detected a failure and needs maintenance
+    pub fn maintenance_required(&self) -> bool {
+        self.status[0] & 0x40 != 0
+    }
+
+    /// IDENT is active in the transmitted ADS-B messages
+    pub fn ident(&self) -> bool {
+        self.status[0] & 0x20 != 0
+    }
+
+    /// ADS-B messages are transmitted with a temporary self-assigned address
+    pub fn anonymous_address(&self) -> bool {
+        self.status[0] & 0x10 != 0
+    }
+
+    /// GPS battery is low
+    pub fn gps_battery_low(&self) -> bool {
+        self.status[0] & 0x08 != 0
+    }
+
+    /// ATC services are talking back to the receiver
+    pub fn ratcs(&self) -> bool {
+        self.status[0] & 0x04 != 0
+    }
+
+    /// Receiver is initialized and operating
+    pub fn uat_initialized(&self) -> bool {
+        self.status[0] & 0x01 != 0
+    }
+
+    /// Conflict situational awareness has been requested
+    pub fn csa_requested(&self) -> bool {
+        self.status[1] & 0x40 != 0
+    }
+
+    /// Conflict situational awareness is not available
+    pub fn csa_not_available(&self) -> bool {
+        self.status[1] & 0x20 != 0
+    }
+
+    /// Timing is coupled to UTC
+    pub fn utc_ok(&self) -> bool {
+        self.status[1] & 0x01 != 0
+    }
+
+    /// Seconds since UTC midnight
+    pub fn seconds(&self) -> u32 {
+        (((self.status[1] >> 7) as u32) << 16) | (self.timestamp as u32)
+    }
+
+    /// Set the seconds since UTC midnight, including the highest bit in the second status byte
+    pub fn set_seconds(&mut self, seconds: u32) {
+        self.status[1] = (self.status[1] & 0x7F) | ((((seconds >> 16) & 0x01) as u8) << 7);
+        self.timestamp = seconds as u16;
+    }
+
+    /// Number of uplink messages received in the previous second
+    pub fn uplink_count(&self) -> u8 {
+        self.counts[0] >> 3
+    }
+
+    /// Number of basic and long messages received in the previous second
+    pub fn basic_long_count(&self) -> u16 {
+        (((self.counts[0] & 0x03) as u16) << 8) | (self.counts[1] as u16)
+    }
 }
@@ -1,66 +1,202 @@
 use std::io;
 use std::net::UdpSocket;
 
+use crate::{Error, Result};
+
+pub use self::fisb::{dlac_decode, FisbApdu, FisbGeometry, FisbGraphic, FisbNexradBlock, FisbProduct, FisbReassembler, FisbRecord, FisbSegment, FisbTextReport, FisbTime, FisbVertex};
 pub use self::foreflight::Gdl90ForeFlightAhrs;
+pub use self::framer::Gdl90Framer;
 pub use self::heartbeat::Gdl90Heartbeat;
 pub use self::geo_altitude::Gdl90GeoAltitude;
-pub use self::traffic::Gdl90Traffic;
+pub use self::traffic::{Gdl90Emergency, Gdl90EmitterCategory, Gdl90Nacp, Gdl90Nic, Gdl90TrackType, Gdl90Traffic, Gdl90TrafficAddress};
+pub use self::uat::{Gdl90UatReport, BASIC_PAYLOAD, LONG_PAYLOAD};
+pub use self::uplink::{Gdl90InfoFrame, Gdl90Uplink};
 
+mod fisb;
 mod foreflight;
+mod framer;
 mod heartbeat;
 mod geo_altitude;
 mod traffic;
+mod uat;
+mod uplink;
 
 #[derive(Debug)]
 pub enum Gdl90Kind {
     Heartbeat(Gdl90Heartbeat),
+    Uplink(Gdl90Uplink),
     Ownship(Gdl90Traffic),
     GeoAltitude(Gdl90GeoAltitude),
     Traffic(Gdl90Traffic),
+    BasicReport(Gdl90UatReport),
+    LongReport(Gdl90UatReport),
     ForeFlightAhrs(Gdl90ForeFlightAhrs)
 }
 
-pub struct Gdl90Message<'a> {
-    msg: &'a [u8],
+impl Gdl90Kind {
+    /// Message ID
+    pub fn id(&self) -> u8 {
+        match self {
+            Gdl90Kind::Heartbeat(_) => 0,
+            Gdl90Kind::Uplink(_) => 7,
+            Gdl90Kind::Ownship(_) => 10,
+            Gdl90Kind::GeoAltitude(_) => 11,
+            Gdl90Kind::Traffic(_) => 20,
+            Gdl90Kind::BasicReport(_) => 30,
+            Gdl90Kind::LongReport(_) => 31,
+            Gdl90Kind::ForeFlightAhrs(_) => 0x65,
+        }
+    }
+
+    /// Encode as a frame ready to send
+    pub fn encode(&self) -> Vec<u8> {
+        let data = match self {
+            Gdl90Kind::Heartbeat(heartbeat) => heartbeat.encode(),
+            Gdl90Kind::Uplink(uplink) => uplink.encode(),
+            Gdl90Kind::Ownship(traffic) | Gdl90Kind::Traffic(traffic) => traffic.encode(),
+            Gdl90Kind::GeoAltitude(altitude) => altitude.encode(),
+            Gdl90Kind::BasicReport(report) | Gdl90Kind::LongReport(report) => report.encode(),
+            Gdl90Kind::ForeFlightAhrs(ahrs) => {
+                let mut data = vec![0x01];
+                data.extend_from_slice(&ahrs.encode());
+                data
+            },
+        };
+        frame(self.id(), &data)
+    }
+}
+
+/// Flag byte at the start and end of each frame
+pub const FLAG: u8 = 0x7E;
+/// Control escape byte, followed by the escaped byte XOR 0x20
+pub const ESCAPE: u8 = 0x7D;
+
+/// CRC-16-CCITT lookup table, as described in the GDL90 ICD
+const CRC_TABLE: [u16; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = (crc << 1) ^ if crc & 0x8000 != 0 { 0x1021 } else { 0 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Compute the frame check sequence of a message ID and data, without byte stuffing
+pub fn crc(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for &byte in data.iter() {
+        crc = CRC_TABLE[(crc >> 8) as usize] ^ (crc << 8) ^ (byte as u16);
+    }
+    crc
+}
+
+/// Build a frame from a message ID and data, adding the frame check sequence, byte stuffing and
+/// flag bytes
+pub fn frame(id: u8, data: &[u8]) -> Vec<u8> {
+    let mut msg = Vec::with_capacity(data.len() + 3);
+    msg.push(id);
+    msg.extend_from_slice(data);
+    let fcs = crc(&msg);
+    msg.push(fcs as u8);
+    msg.push((fcs >> 8) as u8);
+
+    let mut frame = Vec::with_capacity(msg.len() + 2);
+    frame.push(FLAG);
+    for &byte in msg.iter() {
+        if byte == FLAG || byte == ESCAPE {
+            frame.push(ESCAPE);
+            frame.push(byte ^ 0x20);
+        } else {
+            frame.push(byte);
+        }
+    }
+    frame.push(FLAG);
+    frame
+}
+
+/// A message with its flag bytes, byte stuffing and frame check sequence removed
+pub struct Gdl90Message {
+    /// Message ID followed by message data
+    msg: Vec<u8>,
 }
 
-impl<'a> Gdl90Message<'a> {
-    pub fn new(msg: &'a [u8]) -> Option<Self> {
-        // Too short (flag byte, message id, message data, fcs (2), flag byte)
-        if msg.len() < 6 {
-            return None;
+impl Gdl90Message {
+    /// Decode a frame, starting and ending with flag bytes
+    pub fn new(frame: &[u8]) -> Result<Self> {
+        // Too short (flag byte, message id, fcs (2), flag byte)
+        if frame.len() < 5 {
+            return Err(Error::Decode(format!("Gdl90Message: frame of {} bytes is too short", frame.len())));
         }
 
         // Lacking flag bytes
-        if msg[0] != 0x7E || msg[msg.len() - 1] != 0x7E {
-            return None;
+        if frame[0] != FLAG || frame[frame.len() - 1] != FLAG {
+            return Err(Error::Decode("Gdl90Message: frame lacks flag bytes".to_string()));
+        }
+
+        // Remove byte stuffing
+        let mut msg = Vec::with_capacity(frame.len() - 2);
+        let mut escaped = false;
+        for &byte in frame[1..frame.len() - 1].iter() {
+            if escaped {
+                msg.push(byte ^ 0x20);
+                escaped = false;
+            } else if byte == ESCAPE {
+                escaped = true;
+            } else if byte == FLAG {
+                return Err(Error::Decode("Gdl90Message: flag byte inside frame".to_string()));
+            } else {
+                msg.push(byte);
+            }
+        }
+        if escaped {
+            return Err(Error::Decode("Gdl90Message: frame ends with escape byte".to_string()));
         }
 
-        let fcs = (msg[msg.len() - 3] as u16) |
-                  ((msg[msg.len() - 2] as u16) << 8);
-        //TODO: Check fcs
+        // Message id and fcs (2)
+        if msg.len() < 3 {
+            return Err(Error::Decode(format!("Gdl90Message: message of {} bytes is too short", msg.len())));
+        }
 
-        Some(Self {
-            msg: msg
+        // Sent least significant byte first
+        let fcs = (msg[msg.len() - 2] as u16) |
+                  ((msg[msg.len() - 1] as u16) << 8);
+        msg.truncate(msg.len() - 2);
+        let expected = crc(&msg);
+        if fcs != expected {
+            return Err(Error::Checksum(format!("Gdl90Message: FCS {:04X} is not equal to {:04X}", fcs, expected)));
+        }
+
+        Ok(Self {
+            msg
         })
     }
 
     pub fn id(&self) -> u8 {
-        self.msg[1]
+        self.msg[0]
     }
 
     pub fn data(&self) -> &[u8] {
-        &self.msg[2..self.msg.len() - 3]
+        &self.msg[1..]
     }
 
     pub fn kind(&self) -> Option<Gdl90Kind> {
         let data = self.data();
         let kind = match self.id() {
             0 => Gdl90Kind::Heartbeat(Gdl90Heartbeat::new(data)?),
+            7 => Gdl90Kind::Uplink(Gdl90Uplink::new(data)?),
             10 => Gdl90Kind::Ownship(Gdl90Traffic::new(data)?),
             11 => Gdl90Kind::GeoAltitude(Gdl90GeoAltitude::new(data)?),
             20 => Gdl90Kind::Traffic(Gdl90Traffic::new(data)?),
-            0x65 => match data.get(0)? {
+            30 => Gdl90Kind::BasicReport(Gdl90UatReport::new(data, BASIC_PAYLOAD)?),
+            31 => Gdl90Kind::LongReport(Gdl90UatReport::new(data, LONG_PAYLOAD)?),
+            0x65 => match data.first()? {
                 0x01 => Gdl90Kind::ForeFlightAhrs(Gdl90ForeFlightAhrs::new(&data[1..])?),
                 _ => return None
             },
@@ -70,33 +206,55 @@ impl<'a> Gdl90Message<'a> {
     }
 }
 
+/// Receives messages sent over UDP to port 4000, as by Stratux and most other receivers
 pub struct Gdl90 {
     socket: UdpSocket,
-    buf: [u8; 256]
+    buf: Box<[u8]>,
+    framer: Gdl90Framer,
 }
 
 impl Gdl90 {
-    pub fn new() -> io::Result<Self> {
+    pub fn new() -> Result<Self> {
         let socket = UdpSocket::bind(("0.0.0.0", 4000))?;
 
         socket.set_nonblocking(true)?;
 
         Ok(Self {
             socket,
-            buf: [0; 256],
+            buf: vec![0; 65536].into_boxed_slice(),
+            framer: Gdl90Framer::new(),
         })
     }
 
-    pub fn message<'a>(&'a mut self) -> io::Result<Option<Gdl90Message<'a>>> {
-        let (count, src) = match self.socket.recv_from(&mut self.buf) {
-            Ok(ok) => ok,
-            Err(err) => if err.kind() == io::ErrorKind::WouldBlock {
-                return Ok(None);
-            } else {
-                return Err(err);
+    /// Receive the next valid message, if any are waiting
+    ///
+    /// Datagrams may hold any number of frames, or parts of them. Messages failing their frame
+    /// check sequence, and data that are not GDL90 frames, are skipped and counted.
+    pub fn message(&mut self) -> Result<Option<Gdl90Message>> {
+        loop {
+            if let Some(msg) = self.framer.next() {
+                return Ok(Some(msg));
             }
-        };
 
-        Ok(Gdl90Message::new(&self.buf[..count]))
+            let count = match self.socket.recv_from(&mut self.buf) {
+                Ok((count, _src)) => count,
+                Err(err) => if err.kind() == io::ErrorKind::WouldBlock {
+                    return Ok(None);
+                } else {
+                    return Err(err.into());
+                }
+            };
+            self.framer.push(&self.buf[..count]);
+        }
+    }
+
+    /// Number of messages skipped because they failed their frame check sequence
+    pub fn crc_errors(&self) -> u64 {
+        self.framer.crc_errors()
+    }
+
+    /// Number of messages skipped because they were not valid frames
+    pub fn frame_errors(&self) -> u64 {
+        self.framer.frame_errors()
     }
 }
@@ -11,7 +11,134 @@ pub enum Gdl90TrafficAddress {
     Other(u8, u32)
 }
 
-#[derive(Debug)]
+/// Meaning of the track or heading
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Gdl90TrackType {
+    Invalid,
+    TrueTrack,
+    MagneticHeading,
+    TrueHeading,
+}
+
+/// Navigation integrity category, as the radius of containment
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Gdl90Nic {
+    Unknown,
+    Rc20Nm,
+    Rc8Nm,
+    Rc4Nm,
+    Rc2Nm,
+    Rc1Nm,
+    Rc0_6Nm,
+    Rc0_2Nm,
+    Rc0_1Nm,
+    Rc75M,
+    Rc25M,
+    Rc7_5M,
+    Other(u8),
+}
+
+impl Gdl90Nic {
+    /// Radius of containment in meters, if known
+    pub fn radius(&self) -> Option<f64> {
+        match self {
+            Gdl90Nic::Rc20Nm => Some(20.0 * 1852.0),
+            Gdl90Nic::Rc8Nm => Some(8.0 * 1852.0),
+            Gdl90Nic::Rc4Nm => Some(4.0 * 1852.0),
+            Gdl90Nic::Rc2Nm => Some(2.0 * 1852.0),
+            Gdl90Nic::Rc1Nm => Some(1852.0),
+            Gdl90Nic::Rc0_6Nm => Some(0.6 * 1852.0),
+            Gdl90Nic::Rc0_2Nm => Some(0.2 * 1852.0),
+            Gdl90Nic::Rc0_1Nm => Some(0.1 * 1852.0),
+            Gdl90Nic::Rc75M => Some(75.0),
+            Gdl90Nic::Rc25M => Some(25.0),
+            Gdl90Nic::Rc7_5M => Some(7.5),
+            Gdl90Nic::Unknown | Gdl90Nic::Other(_) => None,
+        }
+    }
+}
+
+/// Navigation accuracy category for position, as the estimated position uncertainty
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Gdl90Nacp {
+    Unknown,
+    Epu10Nm,
+    Epu4Nm,
+    Epu2Nm,
+    Epu1Nm,
+    Epu0_5Nm,
+    Epu0_3Nm,
+    Epu0_1Nm,
+    Epu0_05Nm,
+    Epu30M,
+    Epu10M,
+    Epu3M,
+    Other(u8),
+}
+
+impl Gdl90Nacp {
+    /// Estimated position uncertainty in meters, if known
+    pub fn uncertainty(&self) -> Option<f64> {
+        match self {
+            Gdl90Nacp::Epu10Nm => Some(10.0 * 1852.0),
+            Gdl90Nacp::Epu4Nm => Some(4.0 * 1852.0),
+            Gdl90Nacp::Epu2Nm => Some(2.0 * 1852.0),
+            Gdl90Nacp::Epu1Nm => Some(1852.0),
+            Gdl90Nacp::Epu0_5Nm => Some(0.5 * 1852.0),
+            Gdl90Nacp::Epu0_3Nm => Some(0.3 * 1852.0),
+            Gdl90Nacp::Epu0_1Nm => Some(0.1 * 1852.0),
+            Gdl90Nacp::Epu0_05Nm => Some(0.05 * 1852.0),
+            Gdl90Nacp::Epu30M => Some(30.0),
+            Gdl90Nacp::Epu10M => Some(10.0),
+            Gdl90Nacp::Epu3M => Some(3.0),
+            Gdl90Nacp::Unknown | Gdl90Nacp::Other(_) => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Gdl90EmitterCategory {
+    NoInformation,
+    /// Less than 15500 pounds
+    Light,
+    /// From 15500 to 75000 pounds
+    Small,
+    /// From 75000 to 300000 pounds
+    Large,
+    /// Such as a Boeing 757
+    HighVortexLarge,
+    /// More than 300000 pounds
+    Heavy,
+    /// More than 5 g and more than 400 knots
+    HighlyManeuverable,
+    Rotorcraft,
+    Glider,
+    LighterThanAir,
+    Parachutist,
+    UltraLight,
+    UnmannedAerialVehicle,
+    SpaceVehicle,
+    SurfaceEmergencyVehicle,
+    SurfaceServiceVehicle,
+    PointObstacle,
+    ClusterObstacle,
+    LineObstacle,
+    Other(u8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Gdl90Emergency {
+    None,
+    General,
+    Medical,
+    MinimumFuel,
+    NoCommunication,
+    UnlawfulInterference,
+    Downed,
+    Other(u8),
+}
+
+#[derive(Debug, Default)]
 pub struct Gdl90Traffic {
     pub status: u8,
     pub kind: u8,
@@ -47,7 +174,7 @@ impl Gdl90Traffic {
             misc: data[11] & 0xF,
             integrity: (data[12] >> 4) & 0xF,
             accuracy: data[12] & 0xF,
-            h_velocity: ((data[13] as u16) << 8) | ((data[14] >> 4) as u16),
+            h_velocity: ((data[13] as u16) << 4) | ((data[14] >> 4) as u16),
             v_velocity: (((data[14] & 0xF) as u16) << 8) | (data[15] as u16),
             heading: data[16],
             category: data[17],
@@ -66,6 +193,26 @@ impl Gdl90Traffic {
         })
     }
 
+    /// Encode as message data
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(27);
+        data.push((self.status << 4) | (self.kind & 0xF));
+        data.extend_from_slice(&self.address.to_be_bytes()[1..]);
+        data.extend_from_slice(&self.latitude.to_be_bytes()[1..]);
+        data.extend_from_slice(&self.longitude.to_be_bytes()[1..]);
+        data.push((self.altitude >> 4) as u8);
+        data.push(((self.altitude << 4) as u8) | (self.misc & 0xF));
+        data.push((self.integrity << 4) | (self.accuracy & 0xF));
+        data.push((self.h_velocity >> 4) as u8);
+        data.push(((self.h_velocity << 4) as u8) | ((self.v_velocity >> 8) as u8 & 0xF));
+        data.push(self.v_velocity as u8);
+        data.push(self.heading);
+        data.push(self.category);
+        data.extend_from_slice(&self.callsign);
+        data.push((self.priority << 4) | (self.spare & 0xF));
+        data
+    }
+
     pub fn id(&self) -> u32 {
         ((self.kind as u32) << 24) | self.address
     }
@@ -82,16 +229,10 @@ impl Gdl90Traffic {
         }
     }
 
+    /// Callsign up to any terminator, without padding, or empty if it is not valid text
     pub fn callsign(&self) -> &str {
-        let mut i = 0;
-        while i < self.callsign.len() {
-            if self.callsign[i] == 0 {
-                break;
-            }
-            i += 1;
-        }
-
-        unsafe { str::from_utf8_unchecked(&self.callsign[..i + 1]) }
+        let end = self.callsign.iter().position(|&byte| byte == 0).unwrap_or(self.callsign.len());
+        str::from_utf8(&self.callsign[..end]).map_or("", |callsign| callsign.trim())
     }
 
     pub fn latitude(&self) -> f64 {
@@ -119,4 +260,153 @@ impl Gdl90Traffic {
     pub fn heading(&self) -> f64 {
         (self.heading as f64) * 360.0 / 256.0
     }
+
+    /// Horizontal velocity in knots, if available
+    pub fn h_velocity(&self) -> Option<f64> {
+        match self.h_velocity {
+            0xFFF => None,
+            knots => Some(knots as f64),
+        }
+    }
+
+    /// Vertical velocity in feet per minute, if available
+    pub fn v_velocity(&self) -> Option<f64> {
+        match self.v_velocity {
+            0x800 => None,
+            // Twelve bit signed in units of 64 feet per minute
+            v => Some((((v << 4) as i16) >> 4) as f64 * 64.0),
+        }
+    }
+
+    pub fn track_type(&self) -> Gdl90TrackType {
+        match self.misc & 0x3 {
+            1 => Gdl90TrackType::TrueTrack,
+            2 => Gdl90TrackType::MagneticHeading,
+            3 => Gdl90TrackType::TrueHeading,
+            _ => Gdl90TrackType::Invalid,
+        }
+    }
+
+    /// Report was extrapolated rather than updated
+    pub fn extrapolated(&self) -> bool {
+        self.misc & 0x4 != 0
+    }
+
+    /// Airborne rather than on the ground
+    pub fn airborne(&self) -> bool {
+        self.misc & 0x8 != 0
+    }
+
+    pub fn nic(&self) -> Gdl90Nic {
+        match self.integrity {
+            0 => Gdl90Nic::Unknown,
+            1 => Gdl90Nic::Rc20Nm,
+            2 => Gdl90Nic::Rc8Nm,
+            3 => Gdl90Nic::Rc4Nm,
+            4 => Gdl90Nic::Rc2Nm,
+            5 => Gdl90Nic::Rc1Nm,
+            6 => Gdl90Nic::Rc0_6Nm,
+            7 => Gdl90Nic::Rc0_2Nm,
+            8 => Gdl90Nic::Rc0_1Nm,
+            9 => Gdl90Nic::Rc75M,
+            10 => Gdl90Nic::Rc25M,
+            11 => Gdl90Nic::Rc7_5M,
+            other => Gdl90Nic::Other(other)
+        }
+    }
+
+    pub fn nacp(&self) -> Gdl90Nacp {
+        match self.accuracy {
+            0 => Gdl90Nacp::Unknown,
+            1 => Gdl90Nacp::Epu10Nm,
+            2 => Gdl90Nacp::Epu4Nm,
+            3 => Gdl90Nacp::Epu2Nm,
+            4 => Gdl90Nacp::Epu1Nm,
+            5 => Gdl90Nacp::Epu0_5Nm,
+            6 => Gdl90Nacp::Epu0_3Nm,
+            7 => Gdl90Nacp::Epu0_1Nm,
+            8 => Gdl90Nacp::Epu0_05Nm,
+            9 => Gdl90Nacp::Epu30M,
+            10 => Gdl90Nacp::Epu10M,
+            11 => Gdl90Nacp::Epu3M,
+            other => Gdl90Nacp::Other(other)
+        }
+    }
+
+    pub fn emitter_category(&self) -> Gdl90EmitterCategory {
+        match self.category {
+            0 => Gdl90EmitterCategory::NoInformation,
+            1 => Gdl90EmitterCategory::Light,
+            2 => Gdl90EmitterCategory::Small,
+            3 => Gdl90EmitterCategory::Large,
+            4 => Gdl90EmitterCategory::HighVortexLarge,
+            5 => Gdl90EmitterCategory::Heavy,
+            6 => Gdl90EmitterCategory::HighlyManeuverable,
+            7 => Gdl90EmitterCategory::Rotorcraft,
+            9 => Gdl90EmitterCategory::Glider,
+            10 => Gdl90EmitterCategory::LighterThanAir,
+            11 => Gdl90EmitterCategory::Parachutist,
+            12 => Gdl90EmitterCategory::UltraLight,
+            14 => Gdl90EmitterCategory::UnmannedAerialVehicle,
+            15 => Gdl90EmitterCategory::SpaceVehicle,
+            17 => Gdl90EmitterCategory::SurfaceEmergencyVehicle,
+            18 => Gdl90EmitterCategory::SurfaceServiceVehicle,
+            19 => Gdl90EmitterCategory::PointObstacle,
+            20 => Gdl90EmitterCategory::ClusterObstacle,
+            21 => Gdl90EmitterCategory::LineObstacle,
+            other => Gdl90EmitterCategory::Other(other)
+        }
+    }
+
+    /// Emergency or priority code
+    pub fn emergency(&self) -> Gdl90Emergency {
+        match self.priority {
+            0 => Gdl90Emergency::None,
+            1 => Gdl90Emergency::General,
+            2 => Gdl90Emergency::Medical,
+            3 => Gdl90Emergency::MinimumFuel,
+            4 => Gdl90Emergency::NoCommunication,
+            5 => Gdl90Emergency::UnlawfulInterference,
+            6 => Gdl90Emergency::Downed,
+            other => Gdl90Emergency::Other(other)
+        }
+    }
+
+    /// Set the latitude in degrees
+    pub fn set_latitude(&mut self, latitude: f64) {
+        self.latitude = ((latitude * 8388608.0 / 180.0).round() as i32 as u32) & 0xFFFFFF;
+    }
+
+    /// Set the longitude in degrees
+    pub fn set_longitude(&mut self, longitude: f64) {
+        self.longitude = ((longitude * 8388608.0 / 180.0).round() as i32 as u32) & 0xFFFFFF;
+    }
+
+    /// Set the pressure altitude in feet, in steps of 25 feet from -1000 feet
+    pub fn set_altitude(&mut self, altitude: f64) {
+        self.altitude = ((altitude + 1000.0) / 25.0).round().max(0.0).min(0xFFE as f64) as u16;
+    }
+
+    /// Set the track or heading in degrees clockwise from north
+    pub fn set_heading(&mut self, heading: f64) {
+        self.heading = (heading.rem_euclid(360.0) * 256.0 / 360.0).round() as u32 as u8;
+    }
+
+    /// Set the horizontal velocity in knots
+    pub fn set_h_velocity(&mut self, knots: f64) {
+        self.h_velocity = knots.round().clamp(0.0, 0xFFE as f64) as u16;
+    }
+
+    /// Set the vertical velocity in feet per minute, in steps of 64 feet per minute
+    pub fn set_v_velocity(&mut self, fpm: f64) {
+        self.v_velocity = ((fpm / 64.0).round().clamp(-510.0, 510.0) as i16 as u16) & 0xFFF;
+    }
+
+    /// Set the callsign, which is truncated to eight characters and padded with spaces
+    pub fn set_callsign(&mut self, callsign: &str) {
+        self.callsign = [b' '; 8];
+        for (dst, src) in self.callsign.iter_mut().zip(callsign.bytes()) {
+            *dst = src;
+        }
+    }
 }
@@ -0,0 +1,190 @@
+use crate::gdl90::Gdl90Traffic;
+
+/// Length of the payload of a basic report
+pub const BASIC_PAYLOAD: usize = 18;
+/// Length of the payload of a long report
+pub const LONG_PAYLOAD: usize = 34;
+
+/// Characters of the base 40 encoding of callsigns
+const BASE40: &[u8; 40] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ  ..";
+
+/// UAT ADS-B report passed through without being converted to a traffic report, as sent by some
+/// receivers for some targets
+///
+/// Adapted from DO-282B and the decoder of dump978
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gdl90UatReport {
+    /// Time of reception in 80 nanosecond units after the second, if known
+    pub time_of_reception: Option<u32>,
+    /// UAT ADS-B payload, of 18 bytes for basic reports or 34 bytes for long reports
+    pub payload: Vec<u8>,
+}
+
+impl Gdl90UatReport {
+    pub fn new(data: &[u8], payload_len: usize) -> Option<Self> {
+        if data.len() != 3 + payload_len {
+            return None;
+        }
+
+        // Sent least significant byte first, with all ones when unknown
+        let time_of_reception = match (data[0] as u32) | ((data[1] as u32) << 8) | ((data[2] as u32) << 16) {
+            0xFFFFFF => None,
+            tor => Some(tor),
+        };
+
+        Some(Self {
+            time_of_reception,
+            payload: data[3..].to_vec(),
+        })
+    }
+
+    /// Encode as message data
+    pub fn encode(&self) -> Vec<u8> {
+        let tor = self.time_of_reception.unwrap_or(0xFFFFFF);
+        let mut data = vec![tor as u8, (tor >> 8) as u8, (tor >> 16) as u8];
+        data.extend_from_slice(&self.payload);
+        data
+    }
+
+    /// Type of payload, which selects the elements it contains
+    pub fn payload_type(&self) -> u8 {
+        self.payload[0] >> 3
+    }
+
+    /// Payload types with a mode status element
+    fn has_mode_status(&self) -> bool {
+        match self.payload_type() {
+            1 | 3 => self.payload.len() >= LONG_PAYLOAD,
+            _ => false,
+        }
+    }
+
+    /// Payload types with an auxiliary state vector element
+    fn has_aux_state_vector(&self) -> bool {
+        match self.payload_type() {
+            1 | 2 | 5 | 6 => self.payload.len() >= LONG_PAYLOAD,
+            _ => false,
+        }
+    }
+
+    /// Decode the header, state vector, mode status and auxiliary state vector as a traffic report
+    ///
+    /// Pressure altitude is used when either altitude is, and geometric altitude otherwise.
+    pub fn traffic(&self) -> Option<Gdl90Traffic> {
+        let p = &self.payload;
+        if p.len() < BASIC_PAYLOAD {
+            return None;
+        }
+
+        // Header, where address qualifiers match GDL90 address types
+        let mut traffic = Gdl90Traffic {
+            kind: p[0] & 0x07,
+            address: ((p[1] as u32) << 16) | ((p[2] as u32) << 8) | (p[3] as u32),
+            ..Default::default()
+        };
+
+        // State vector
+        let nic = p[11] & 0x0F;
+        let raw_lat = ((p[4] as u32) << 15) | ((p[5] as u32) << 7) | ((p[6] as u32) >> 1);
+        let raw_lon = ((p[6] as u32 & 0x01) << 23) | ((p[7] as u32) << 15) | ((p[8] as u32) << 7) | ((p[9] as u32) >> 1);
+        if nic != 0 || raw_lat != 0 || raw_lon != 0 {
+            let mut latitude = raw_lat as f64 * 360.0 / 16777216.0;
+            if latitude > 90.0 {
+                latitude -= 180.0;
+            }
+            let mut longitude = raw_lon as f64 * 360.0 / 16777216.0;
+            if longitude > 180.0 {
+                longitude -= 360.0;
+            }
+            traffic.set_latitude(latitude);
+            traffic.set_longitude(longitude);
+        }
+        traffic.integrity = nic;
+
+        // Altitudes are offset by one, so zero is unknown
+        let geometric = p[9] & 0x01 != 0;
+        let raw_alt = ((p[10] as u16) << 4) | ((p[11] as u16) >> 4);
+        let aux_alt = if self.has_aux_state_vector() {
+            ((p[29] as u16) << 4) | ((p[30] as u16) >> 4)
+        } else {
+            0
+        };
+        let alt = if geometric && aux_alt != 0 { aux_alt } else { raw_alt };
+        traffic.altitude = if alt == 0 { 0xFFF } else { alt - 1 };
+
+        traffic.h_velocity = 0xFFF;
+        traffic.v_velocity = 0x800;
+        let air_ground = p[12] >> 6;
+        let raw_a = (((p[12] & 0x1F) as i32) << 6) | ((p[13] >> 2) as i32);
+        let raw_b = (((p[13] & 0x03) as i32) << 9) | ((p[14] as i32) << 1) | ((p[15] >> 7) as i32);
+        // Track type, with bit 3 set when airborne
+        let mut misc = 0;
+        match air_ground {
+            // Subsonic or supersonic, with north and east velocity in knots
+            0 | 1 => {
+                misc |= 0x08;
+                let factor = if air_ground == 1 { 4 } else { 1 };
+                let velocity = |raw: i32| -> Option<i32> {
+                    if raw & 0x3FF == 0 {
+                        None
+                    } else if raw & 0x400 != 0 {
+                        Some(-((raw & 0x3FF) - 1) * factor)
+                    } else {
+                        Some(((raw & 0x3FF) - 1) * factor)
+                    }
+                };
+                if let (Some(north), Some(east)) = (velocity(raw_a), velocity(raw_b)) {
+                    let (north, east) = (north as f64, east as f64);
+                    traffic.set_h_velocity((north.powi(2) + east.powi(2)).sqrt());
+                    if north != 0.0 || east != 0.0 {
+                        misc |= 0x01;
+                        traffic.set_heading(east.atan2(north).to_degrees());
+                    }
+                }
+
+                let raw_vvel = (((p[15] & 0x7F) as i32) << 4) | ((p[16] >> 4) as i32);
+                if raw_vvel & 0x1FF != 0 {
+                    let fpm = ((raw_vvel & 0x1FF) - 1) * 64;
+                    traffic.set_v_velocity(if raw_vvel & 0x200 != 0 { -fpm } else { fpm } as f64);
+                }
+            },
+            // On the ground, with ground speed in knots and track or heading
+            2 => {
+                if raw_a & 0x3FF != 0 {
+                    traffic.set_h_velocity(((raw_a & 0x3FF) - 1) as f64);
+                }
+                let track_type = ((raw_b & 0x600) >> 9) as u8;
+                if track_type != 0 {
+                    misc |= track_type;
+                    traffic.set_heading((raw_b & 0x1FF) as f64 * 360.0 / 512.0);
+                }
+            },
+            _ => (),
+        }
+        traffic.misc = misc;
+
+        // Mode status, with emitter category and callsign in base 40
+        traffic.callsign = [b' '; 8];
+        if self.has_mode_status() {
+            let triplets = [
+                ((p[17] as u16) << 8) | (p[18] as u16),
+                ((p[19] as u16) << 8) | (p[20] as u16),
+                ((p[21] as u16) << 8) | (p[22] as u16),
+            ];
+            traffic.category = ((triplets[0] / 1600) % 40) as u8;
+            let chars = [
+                triplets[0] / 40, triplets[0],
+                triplets[1] / 1600, triplets[1] / 40, triplets[1],
+                triplets[2] / 1600, triplets[2] / 40, triplets[2],
+            ];
+            for (dst, ch) in traffic.callsign.iter_mut().zip(chars.iter()) {
+                *dst = BASE40[(ch % 40) as usize];
+            }
+
+            traffic.priority = (p[23] >> 5) & 0x07;
+            traffic.accuracy = p[25] >> 4;
+        }
+
+        Some(traffic)
+    }
+}
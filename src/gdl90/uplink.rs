@@ -0,0 +1,137 @@
+use crate::gdl90::FisbApdu;
+
+/// Length of the UAT payload of an uplink message
+pub const UPLINK_PAYLOAD: usize = 432;
+
+/// A frame of application data in an uplink
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gdl90InfoFrame {
+    /// Frame type, where 0 is a FIS-B APDU
+    pub kind: u8,
+    pub data: Vec<u8>,
+}
+
+impl Gdl90InfoFrame {
+    /// Decode as a FIS-B APDU, if it is one
+    pub fn apdu(&self) -> Option<FisbApdu> {
+        if self.kind == 0 {
+            FisbApdu::new(&self.data)
+        } else {
+            None
+        }
+    }
+}
+
+/// UAT uplink from a ground station, carrying FIS-B products
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gdl90Uplink {
+    /// Time of reception in 80 nanosecond units after the second, if known
+    pub time_of_reception: Option<u32>,
+    /// Position of the ground station in degrees, if valid
+    pub position: Option<(f64, f64)>,
+    /// Ground station is coupled to UTC
+    pub utc_coupled: bool,
+    /// Transmission slot of the ground station
+    pub slot_id: u8,
+    /// Site ID of TIS-B reports from the ground station
+    pub tisb_site_id: u8,
+    pub frames: Vec<Gdl90InfoFrame>,
+}
+
+impl Gdl90Uplink {
+    pub fn new(data: &[u8]) -> Option<Self> {
+        if data.len() != 3 + UPLINK_PAYLOAD {
+            return None;
+        }
+
+        // Sent least significant byte first, with all ones when unknown
+        let time_of_reception = match (data[0] as u32) | ((data[1] as u32) << 8) | ((data[2] as u32) << 16) {
+            0xFFFFFF => None,
+            tor => Some(tor),
+        };
+
+        let header = &data[3..11];
+        let position = if header[5] & 0x01 != 0 {
+            let raw_lat = ((header[0] as u32) << 15) | ((header[1] as u32) << 7) | ((header[2] as u32) >> 1);
+            let raw_lon = ((header[2] as u32 & 0x01) << 23) | ((header[3] as u32) << 15) | ((header[4] as u32) << 7) | ((header[5] as u32) >> 1);
+            let mut latitude = raw_lat as f64 * 360.0 / 16777216.0;
+            if latitude > 90.0 {
+                latitude -= 180.0;
+            }
+            let mut longitude = raw_lon as f64 * 360.0 / 16777216.0;
+            if longitude > 180.0 {
+                longitude -= 360.0;
+            }
+            Some((latitude, longitude))
+        } else {
+            None
+        };
+
+        // Information frames, if application data are valid
+        let mut frames = Vec::new();
+        if header[6] & 0x20 != 0 {
+            let mut app_data = &data[11..];
+            while app_data.len() >= 2 {
+                let length = ((app_data[0] as usize) << 1) | ((app_data[1] as usize) >> 7);
+                let kind = app_data[1] & 0x0F;
+                if length == 0 && kind == 0 {
+                    break;
+                }
+                if app_data.len() < length + 2 {
+                    break;
+                }
+
+                frames.push(Gdl90InfoFrame {
+                    kind,
+                    data: app_data[2..length + 2].to_vec(),
+                });
+                app_data = &app_data[length + 2..];
+            }
+        }
+
+        Some(Self {
+            time_of_reception,
+            position,
+            utc_coupled: header[6] & 0x80 != 0,
+            slot_id: header[6] & 0x1F,
+            tisb_site_id: header[7] >> 4,
+            frames,
+        })
+    }
+
+    /// Decode the FIS-B APDUs in the information frames
+    pub fn apdus(&self) -> Vec<FisbApdu> {
+        self.frames.iter().filter_map(|frame| frame.apdu()).collect()
+    }
+
+    /// Encode as message data, padding the application data with zeros
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(3 + UPLINK_PAYLOAD);
+        let tor = self.time_of_reception.unwrap_or(0xFFFFFF);
+        data.extend_from_slice(&[tor as u8, (tor >> 8) as u8, (tor >> 16) as u8]);
+
+        let mut header = [0; 8];
+        if let Some((latitude, longitude)) = self.position {
+            let raw_lat = ((latitude.rem_euclid(180.0) * 16777216.0 / 360.0).round() as u32) & 0x7FFFFF;
+            let raw_lon = ((longitude.rem_euclid(360.0) * 16777216.0 / 360.0).round() as u32) & 0xFFFFFF;
+            header[0] = (raw_lat >> 15) as u8;
+            header[1] = (raw_lat >> 7) as u8;
+            header[2] = ((raw_lat << 1) as u8) | ((raw_lon >> 23) as u8 & 0x01);
+            header[3] = (raw_lon >> 15) as u8;
+            header[4] = (raw_lon >> 7) as u8;
+            header[5] = ((raw_lon << 1) as u8) | 0x01;
+        }
+        header[6] = (if self.utc_coupled { 0x80 } else { 0 }) | 0x20 | (self.slot_id & 0x1F);
+        header[7] = self.tisb_site_id << 4;
+        data.extend_from_slice(&header);
+
+        for frame in self.frames.iter() {
+            let length = frame.data.len();
+            data.push((length >> 1) as u8);
+            data.push(((length << 7) as u8) | (frame.kind & 0x0F));
+            data.extend_from_slice(&frame.data);
+        }
+        data.resize(3 + UPLINK_PAYLOAD, 0);
+        data
+    }
+}
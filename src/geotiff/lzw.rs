@@ -0,0 +1,91 @@
+use crate::Result;
+use crate::geotiff::invalid;
+
+const CLEAR: usize = 256;
+const END: usize = 257;
+
+/// Decode TIFF LZW data, which uses most significant bit first codes and switches code width one
+/// code early
+pub fn decode(input: &[u8], expected_len: usize) -> Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(expected_len);
+
+    // Each entry is a prefix code and the byte appended to it
+    let mut table: Vec<(usize, u8)> = Vec::with_capacity(4096);
+    let reset = |table: &mut Vec<(usize, u8)>| {
+        table.clear();
+        for i in 0..256 {
+            table.push((usize::MAX, i as u8));
+        }
+        // Clear and end codes
+        table.push((usize::MAX, 0));
+        table.push((usize::MAX, 0));
+    };
+    reset(&mut table);
+
+    let mut width = 9;
+    let mut bit = 0;
+    let mut prev: Option<usize> = None;
+    let mut entry = Vec::new();
+
+    // Write the bytes of an entry into the scratch buffer, returning its first byte
+    let expand = |table: &[(usize, u8)], mut code: usize, entry: &mut Vec<u8>| -> u8 {
+        entry.clear();
+        while code != usize::MAX {
+            let (prefix, byte) = table[code];
+            entry.push(byte);
+            code = prefix;
+        }
+        entry.reverse();
+        entry[0]
+    };
+
+    while bit + width <= input.len() * 8 {
+        let mut code = 0;
+        for _ in 0..width {
+            let byte = input[bit / 8];
+            code = (code << 1) | ((byte >> (7 - bit % 8)) & 1) as usize;
+            bit += 1;
+        }
+
+        if code == END {
+            break;
+        }
+
+        if code == CLEAR {
+            reset(&mut table);
+            width = 9;
+            prev = None;
+            continue;
+        }
+
+        match prev {
+            None => {
+                if code >= 256 {
+                    return Err(invalid(format!("LZW code {} after clear", code)));
+                }
+                output.push(code as u8);
+            },
+            Some(prev_code) => {
+                if code < table.len() {
+                    let first = expand(&table, code, &mut entry);
+                    output.extend_from_slice(&entry);
+                    table.push((prev_code, first));
+                } else if code == table.len() {
+                    let first = expand(&table, prev_code, &mut entry);
+                    output.extend_from_slice(&entry);
+                    output.push(first);
+                    table.push((prev_code, first));
+                } else {
+                    return Err(invalid(format!("LZW code {} out of range", code)));
+                }
+            }
+        }
+        prev = Some(code);
+
+        if table.len() + 1 >= (1 << width) && width < 12 {
+            width += 1;
+        }
+    }
+
+    Ok(output)
+}
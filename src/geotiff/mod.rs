@@ -0,0 +1,294 @@
+use flate2::read::ZlibDecoder;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::{Error, Result};
+use crate::hgt::HgtSampler;
+
+use self::tiff::*;
+
+mod lzw;
+mod tiff;
+
+fn invalid(message: String) -> Error {
+    Error::Format(format!("GeoTiff: {}", message))
+}
+
+/// A single band elevation model read from a GeoTIFF, such as Copernicus GLO-30, ASTER GDEM or
+/// USGS 3DEP tiles
+pub struct GeoTiff {
+    /// Latitude of the southmost row of samples
+    pub latitude: f64,
+    /// Longitude of the westmost column of samples
+    pub longitude: f64,
+    /// Spacing between rows in degrees
+    pub latitude_spacing: f64,
+    /// Spacing between columns in degrees
+    pub longitude_spacing: f64,
+    /// Number of rows
+    pub rows: u32,
+    /// Number of columns
+    pub cols: u32,
+    /// Value used for missing samples
+    pub nodata: Option<f64>,
+    /// Samples, starting at the northwest corner, row by row
+    pub data: Box<[f32]>,
+}
+
+impl GeoTiff {
+    /// Decode a GeoTIFF from memory
+    pub fn new(file: &[u8]) -> Result<Self> {
+        let tiff = Tiff::new(file)?;
+
+        let required = |tag: u16, name: &str| -> Result<u64> {
+            tiff.unsigned_one(tag)?.ok_or_else(|| invalid(format!("missing {}", name)))
+        };
+
+        let cols = required(IMAGE_WIDTH, "ImageWidth")? as usize;
+        let rows = required(IMAGE_LENGTH, "ImageLength")? as usize;
+        if rows == 0 || cols == 0 {
+            return Err(invalid(format!("image size of {}x{}", cols, rows)));
+        }
+        let bits = tiff.unsigned_one(BITS_PER_SAMPLE)?.unwrap_or(1);
+        let format = tiff.unsigned_one(SAMPLE_FORMAT)?.unwrap_or(1);
+        let compression = tiff.unsigned_one(COMPRESSION)?.unwrap_or(1);
+        let predictor = tiff.unsigned_one(PREDICTOR)?.unwrap_or(1);
+
+        if tiff.unsigned_one(SAMPLES_PER_PIXEL)?.unwrap_or(1) != 1 {
+            return Err(invalid("only single band images are supported".to_string()));
+        }
+
+        let bytes_per_sample = match (format, bits) {
+            (1, 16) | (2, 16) => 2,
+            (2, 32) | (3, 32) => 4,
+            _ => return Err(invalid(format!("unsupported sample format {} with {} bits", format, bits))),
+        };
+
+        // Blocks are tiles, or strips that are as wide as the image
+        let (block_cols, block_rows, offsets, counts) = if let Some(tile_width) = tiff.unsigned_one(TILE_WIDTH)? {
+            (
+                tile_width as usize,
+                required(TILE_LENGTH, "TileLength")? as usize,
+                tiff.unsigned(TILE_OFFSETS)?.ok_or_else(|| invalid("missing TileOffsets".to_string()))?,
+                tiff.unsigned(TILE_BYTE_COUNTS)?.ok_or_else(|| invalid("missing TileByteCounts".to_string()))?,
+            )
+        } else {
+            (
+                cols,
+                tiff.unsigned_one(ROWS_PER_STRIP)?.map_or(rows, |r| r as usize).min(rows),
+                tiff.unsigned(STRIP_OFFSETS)?.ok_or_else(|| invalid("missing StripOffsets".to_string()))?,
+                tiff.unsigned(STRIP_BYTE_COUNTS)?.ok_or_else(|| invalid("missing StripByteCounts".to_string()))?,
+            )
+        };
+
+        if block_cols == 0 || block_rows == 0 {
+            return Err(invalid("block size of zero".to_string()));
+        }
+
+        let blocks_across = cols.div_ceil(block_cols);
+        let blocks_down = rows.div_ceil(block_rows);
+        if offsets.len() < blocks_across * blocks_down || counts.len() < offsets.len() {
+            return Err(invalid(format!("expected {} blocks, found {}", blocks_across * blocks_down, offsets.len())));
+        }
+
+        let mut data = vec![0.0f32; rows * cols];
+        for block in 0..blocks_across * blocks_down {
+            let start = offsets[block] as usize;
+            let end = start + counts[block] as usize;
+            let raw = file.get(start..end).ok_or_else(|| invalid(format!("block {} out of bounds", block)))?;
+
+            let block_len = block_cols * block_rows * bytes_per_sample;
+            let mut bytes = match compression {
+                1 => raw.to_vec(),
+                5 => lzw::decode(raw, block_len)?,
+                8 | 32946 => {
+                    let mut bytes = Vec::with_capacity(block_len);
+                    ZlibDecoder::new(raw).read_to_end(&mut bytes)?;
+                    bytes
+                },
+                other => return Err(invalid(format!("unsupported compression {}", other))),
+            };
+
+            // Strips at the bottom of the image may be short
+            let rows_in_block = (bytes.len() / (block_cols * bytes_per_sample)).min(block_rows);
+            bytes.truncate(rows_in_block * block_cols * bytes_per_sample);
+
+            let values = decode_block(&bytes, tiff.endian, block_cols, bytes_per_sample, format, predictor)?;
+
+            let row0 = (block / blocks_across) * block_rows;
+            let col0 = (block % blocks_across) * block_cols;
+            for r in 0..rows_in_block {
+                let row = row0 + r;
+                if row >= rows {
+                    break;
+                }
+                for c in 0..block_cols {
+                    let col = col0 + c;
+                    if col >= cols {
+                        break;
+                    }
+                    data[row * cols + col] = values[r * block_cols + c];
+                }
+            }
+        }
+
+        // Georeferencing from the first tie point and the pixel scale
+        let tiepoint = tiff.doubles(MODEL_TIEPOINT)?.ok_or_else(|| invalid("missing ModelTiepoint".to_string()))?;
+        let scale = tiff.doubles(MODEL_PIXEL_SCALE)?.ok_or_else(|| invalid("missing ModelPixelScale".to_string()))?;
+        if tiepoint.len() < 6 || scale.len() < 2 {
+            return Err(invalid("incomplete ModelTiepoint or ModelPixelScale".to_string()));
+        }
+
+        // GTRasterTypeGeoKey, where samples are at the center of pixels unless it is PixelIsPoint
+        let pixel_is_point = match tiff.unsigned(GEO_KEY_DIRECTORY)? {
+            Some(keys) => keys.chunks(4).skip(1).any(|key| key.len() == 4 && key[0] == 1025 && key[1] == 0 && key[3] == 2),
+            None => false,
+        };
+        let center = if pixel_is_point { 0.0 } else { 0.5 };
+
+        let longitude_spacing = scale[0];
+        let latitude_spacing = scale[1];
+        let west = tiepoint[3] + (center - tiepoint[0]) * longitude_spacing;
+        let north = tiepoint[4] - (center - tiepoint[1]) * latitude_spacing;
+        let south = north - ((rows - 1) as f64) * latitude_spacing;
+
+        let nodata = match tiff.ascii(GDAL_NODATA)? {
+            Some(string) => string.trim().parse::<f64>().ok(),
+            None => None,
+        };
+
+        Ok(Self {
+            latitude: south,
+            longitude: west,
+            latitude_spacing,
+            longitude_spacing,
+            rows: rows as u32,
+            cols: cols as u32,
+            nodata,
+            data: data.into_boxed_slice(),
+        })
+    }
+
+    /// Read a GeoTIFF from a path
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut data = Vec::new();
+        File::open(path.as_ref())?.read_to_end(&mut data)?;
+        Self::new(&data)
+    }
+
+    /// Get the height in meters at a file position, with rows counted northward
+    pub fn get(&self, row: u32, col: u32) -> Option<f64> {
+        if row < self.rows && col < self.cols {
+            let index = ((self.rows - row - 1) as usize) * (self.cols as usize) + (col as usize);
+            let sample = self.data[index] as f64;
+            if Some(sample) == self.nodata || sample.is_nan() || sample <= -32768.0 {
+                None
+            } else {
+                Some(sample)
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Produce row and column from latitude and longitude
+    pub fn position(&self, latitude: f64, longitude: f64) -> Option<(u32, u32)> {
+        let row = ((latitude - self.latitude) / self.latitude_spacing).round() as i64;
+        let col = ((longitude - self.longitude) / self.longitude_spacing).round() as i64;
+
+        if row >= 0 && row < self.rows as i64 && col >= 0 && col < self.cols as i64 {
+            Some((row as u32, col as u32))
+        } else {
+            None
+        }
+    }
+
+    /// Produce latitude and longitude from row and column
+    pub fn coordinate(&self, row: u32, col: u32) -> Option<(f64, f64)> {
+        if row < self.rows && col < self.cols {
+            Some((
+                (row as f64) * self.latitude_spacing + self.latitude,
+                (col as f64) * self.longitude_spacing + self.longitude,
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+impl HgtSampler for GeoTiff {
+    fn origin(&self) -> (f64, f64) {
+        (self.latitude, self.longitude)
+    }
+
+    fn spacing(&self) -> (f64, f64) {
+        (self.latitude_spacing, self.longitude_spacing)
+    }
+
+    fn height(&self, row: i64, col: i64) -> Option<f64> {
+        if row >= 0 && row <= u32::MAX as i64 && col >= 0 && col <= u32::MAX as i64 {
+            self.get(row as u32, col as u32)
+        } else {
+            None
+        }
+    }
+}
+
+/// Undo the predictor and convert the samples of a decompressed block to floats
+fn decode_block(bytes: &[u8], endian: Endian, width: usize, size: usize, format: u64, predictor: u64) -> Result<Vec<f32>> {
+    let row_len = width * size;
+    let mut values = Vec::with_capacity(bytes.len() / size);
+
+    for row in bytes.chunks(row_len) {
+        match predictor {
+            // Floating point predictor, which stores each byte of the samples in separate planes
+            // after horizontal differencing of the bytes
+            3 => {
+                let mut planes = row.to_vec();
+                for i in 1..planes.len() {
+                    planes[i] = planes[i].wrapping_add(planes[i - 1]);
+                }
+                for i in 0..width {
+                    let mut be = [0; 4];
+                    for (k, b) in be.iter_mut().take(size).enumerate() {
+                        *b = planes[k * width + i];
+                    }
+                    values.push(match size {
+                        4 => f32::from_be_bytes(be),
+                        _ => return Err(invalid("floating point predictor requires 32-bit floats".to_string())),
+                    });
+                }
+            },
+            _ => {
+                let mut prev: i64 = 0;
+                for sample in row.chunks(size) {
+                    let raw: u32 = match (size, endian) {
+                        (2, Endian::Little) => u16::from_le_bytes([sample[0], sample[1]]) as u32,
+                        (2, Endian::Big) => u16::from_be_bytes([sample[0], sample[1]]) as u32,
+                        (_, Endian::Little) => u32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]),
+                        (_, Endian::Big) => u32::from_be_bytes([sample[0], sample[1], sample[2], sample[3]]),
+                    };
+
+                    // Horizontal differencing of integers
+                    let raw = if predictor == 2 {
+                        let value = (prev as u32).wrapping_add(raw) & if size == 2 { 0xFFFF } else { 0xFFFFFFFF };
+                        prev = value as i64;
+                        value
+                    } else {
+                        raw
+                    };
+
+                    values.push(match (format, size) {
+                        (1, 2) => raw as u16 as f32,
+                        (2, 2) => raw as u16 as i16 as f32,
+                        (2, _) => raw as i32 as f32,
+                        _ => f32::from_bits(raw),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(values)
+}
@@ -0,0 +1,172 @@
+use std::collections::BTreeMap;
+
+use crate::Result;
+use crate::geotiff::invalid;
+
+pub const IMAGE_WIDTH: u16 = 256;
+pub const IMAGE_LENGTH: u16 = 257;
+pub const BITS_PER_SAMPLE: u16 = 258;
+pub const COMPRESSION: u16 = 259;
+pub const STRIP_OFFSETS: u16 = 273;
+pub const SAMPLES_PER_PIXEL: u16 = 277;
+pub const ROWS_PER_STRIP: u16 = 278;
+pub const STRIP_BYTE_COUNTS: u16 = 279;
+pub const PREDICTOR: u16 = 317;
+pub const TILE_WIDTH: u16 = 322;
+pub const TILE_LENGTH: u16 = 323;
+pub const TILE_OFFSETS: u16 = 324;
+pub const TILE_BYTE_COUNTS: u16 = 325;
+pub const SAMPLE_FORMAT: u16 = 339;
+pub const MODEL_PIXEL_SCALE: u16 = 33550;
+pub const MODEL_TIEPOINT: u16 = 33922;
+pub const GEO_KEY_DIRECTORY: u16 = 34735;
+pub const GDAL_NODATA: u16 = 42113;
+
+#[derive(Clone, Copy)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+/// A directory entry, with the position of its values in the file
+pub struct Entry {
+    kind: u16,
+    count: usize,
+    offset: usize,
+}
+
+/// Reader for the header and first image file directory of a classic TIFF
+pub struct Tiff<'a> {
+    pub data: &'a [u8],
+    pub endian: Endian,
+    pub entries: BTreeMap<u16, Entry>,
+}
+
+impl<'a> Tiff<'a> {
+    pub fn new(data: &'a [u8]) -> Result<Self> {
+        let endian = match data.get(..4) {
+            Some(b"II*\0") => Endian::Little,
+            Some(b"MM\0*") => Endian::Big,
+            Some(b"II+\0") | Some(b"MM\0+") => return Err(invalid("BigTIFF is not supported".to_string())),
+            _ => return Err(invalid("missing TIFF header".to_string())),
+        };
+
+        let mut tiff = Self {
+            data,
+            endian,
+            entries: BTreeMap::new(),
+        };
+
+        let ifd = tiff.u32_at(4)? as usize;
+        let count = tiff.u16_at(ifd)? as usize;
+        for i in 0..count {
+            let pos = ifd + 2 + i * 12;
+            let tag = tiff.u16_at(pos)?;
+            let kind = tiff.u16_at(pos + 2)?;
+            let count = tiff.u32_at(pos + 4)? as usize;
+            let size = match kind {
+                1 | 2 | 6 | 7 => 1,
+                3 | 8 => 2,
+                4 | 9 | 11 => 4,
+                5 | 10 | 12 => 8,
+                _ => continue,
+            };
+            let offset = if size * count <= 4 {
+                pos + 8
+            } else {
+                tiff.u32_at(pos + 8)? as usize
+            };
+            if offset + size * count > data.len() {
+                return Err(invalid(format!("tag {} out of bounds", tag)));
+            }
+            tiff.entries.insert(tag, Entry { kind, count, offset });
+        }
+
+        Ok(tiff)
+    }
+
+    fn bytes(&self, pos: usize, len: usize) -> Result<&'a [u8]> {
+        self.data.get(pos..pos + len).ok_or_else(|| invalid(format!("read of {} bytes at {} out of bounds", len, pos)))
+    }
+
+    pub fn u16_at(&self, pos: usize) -> Result<u16> {
+        let b = self.bytes(pos, 2)?;
+        let b = [b[0], b[1]];
+        Ok(match self.endian {
+            Endian::Little => u16::from_le_bytes(b),
+            Endian::Big => u16::from_be_bytes(b),
+        })
+    }
+
+    pub fn u32_at(&self, pos: usize) -> Result<u32> {
+        let b = self.bytes(pos, 4)?;
+        let b = [b[0], b[1], b[2], b[3]];
+        Ok(match self.endian {
+            Endian::Little => u32::from_le_bytes(b),
+            Endian::Big => u32::from_be_bytes(b),
+        })
+    }
+
+    pub fn u64_at(&self, pos: usize) -> Result<u64> {
+        let b = self.bytes(pos, 8)?;
+        let b = [b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]];
+        Ok(match self.endian {
+            Endian::Little => u64::from_le_bytes(b),
+            Endian::Big => u64::from_be_bytes(b),
+        })
+    }
+
+    /// Read the values of a tag as unsigned integers
+    pub fn unsigned(&self, tag: u16) -> Result<Option<Vec<u64>>> {
+        let entry = match self.entries.get(&tag) {
+            Some(some) => some,
+            None => return Ok(None),
+        };
+
+        let mut values = Vec::with_capacity(entry.count);
+        for i in 0..entry.count {
+            values.push(match entry.kind {
+                1 | 7 => self.bytes(entry.offset + i, 1)?[0] as u64,
+                3 => self.u16_at(entry.offset + i * 2)? as u64,
+                4 => self.u32_at(entry.offset + i * 4)? as u64,
+                _ => return Err(invalid(format!("tag {} has non-integer type {}", tag, entry.kind))),
+            });
+        }
+        Ok(Some(values))
+    }
+
+    /// Read the first value of a tag as an unsigned integer
+    pub fn unsigned_one(&self, tag: u16) -> Result<Option<u64>> {
+        Ok(self.unsigned(tag)?.and_then(|values| values.first().cloned()))
+    }
+
+    /// Read the values of a tag as doubles
+    pub fn doubles(&self, tag: u16) -> Result<Option<Vec<f64>>> {
+        let entry = match self.entries.get(&tag) {
+            Some(some) => some,
+            None => return Ok(None),
+        };
+
+        let mut values = Vec::with_capacity(entry.count);
+        for i in 0..entry.count {
+            values.push(match entry.kind {
+                11 => f32::from_bits(self.u32_at(entry.offset + i * 4)?) as f64,
+                12 => f64::from_bits(self.u64_at(entry.offset + i * 8)?),
+                _ => return Err(invalid(format!("tag {} has non-float type {}", tag, entry.kind))),
+            });
+        }
+        Ok(Some(values))
+    }
+
+    /// Read the value of a tag as a string, without the terminating null
+    pub fn ascii(&self, tag: u16) -> Result<Option<String>> {
+        let entry = match self.entries.get(&tag) {
+            Some(some) => some,
+            None => return Ok(None),
+        };
+
+        let bytes = self.bytes(entry.offset, entry.count)?;
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        Ok(Some(String::from_utf8_lossy(&bytes[..end]).into_owned()))
+    }
+}
@@ -1,63 +1,317 @@
 use std::fs;
-use std::io::{self, Write};
+use std::io::{self, Cursor, Read, Write};
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
-use crate::hgt::{HgtFile, HgtResolution, HgtSrtm};
+use crate::{Error, Result};
+use crate::hgt::{write_atomic, HgtFile, HgtMosaic, HgtResolution, HgtSrtm, HgtTileId};
 
+/// Settings for filling voids in files loaded by HgtCache
+#[derive(Clone, Copy, Debug)]
+pub struct HgtVoidFill {
+    /// Maximum distance in samples to search for valid samples
+    pub max_distance: u16,
+    /// Resolution of a file to take void samples from before interpolating
+    pub fallback: Option<HgtResolution>,
+}
+
+/// How HgtCache stores files on disk
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HgtCacheCompression {
+    /// Raw .hgt files
+    None,
+    /// .hgt.zip files, like those downloaded from SRTM mirrors
+    Zip,
+    /// .hgt.zst files
+    Zstd,
+}
+
+impl HgtCacheCompression {
+    fn extension(&self) -> &'static str {
+        match *self {
+            HgtCacheCompression::None => "hgt",
+            HgtCacheCompression::Zip => "hgt.zip",
+            HgtCacheCompression::Zstd => "hgt.zst",
+        }
+    }
+
+    fn all() -> [Self; 3] {
+        [HgtCacheCompression::None, HgtCacheCompression::Zip, HgtCacheCompression::Zstd]
+    }
+}
+
+/// A file stored in the cache
+#[derive(Clone, Debug)]
+pub struct HgtCacheEntry {
+    /// Path of the stored file
+    pub path: PathBuf,
+    /// Name of the file, such as N39W105
+    pub name: String,
+    pub resolution: HgtResolution,
+    pub compression: HgtCacheCompression,
+    /// Size on disk in bytes, including the checksum file
+    pub size: u64,
+    /// Time the file was last read or written
+    pub accessed: SystemTime,
+}
+
+/// Local storage of HgtFiles, downloading missing files with HgtSrtm
+///
+/// Each file is stored next to a .crc32 file with the checksum of its uncompressed data. Files that
+/// fail validation, or have no checksum, are removed and downloaded again. When a size limit is
+/// set, the least recently used files are removed until the cache fits, both when the limit is
+/// set and after each download.
 pub struct HgtCache {
     path: PathBuf,
+    srtm: HgtSrtm,
+    void_fill: Option<HgtVoidFill>,
+    compression: HgtCacheCompression,
+    max_bytes: Option<u64>,
 }
 
 impl HgtCache {
     pub fn new<P: AsRef<Path>>(path: P) -> Self {
         Self {
-            path: path.as_ref().to_owned()
+            path: path.as_ref().to_owned(),
+            srtm: HgtSrtm::default(),
+            void_fill: None,
+            compression: HgtCacheCompression::None,
+            max_bytes: None,
         }
     }
 
-    pub fn get(&self, latitude: f64, longitude: f64, resolution: HgtResolution) -> io::Result<HgtFile> {
-        let name = format!(
-            "{}{:02}{}{:03}",
-            if latitude < 0.0 {
-                "S"
-            } else {
-                "N"
-            },
-            latitude.abs() as u32,
-            if longitude < 0.0 {
-                "W"
-            } else {
-                "E"
-            },
-            longitude.abs() as u32
-        );
+    /// Set the downloader used for files missing from the cache
+    pub fn set_srtm(&mut self, srtm: HgtSrtm) {
+        self.srtm = srtm;
+    }
+
+    /// Fill voids in files as they are loaded. Cached files keep their original voids.
+    pub fn set_void_fill(&mut self, void_fill: Option<HgtVoidFill>) {
+        self.void_fill = void_fill;
+    }
 
-        let root = match resolution {
+    /// Set how new files are stored. Files already stored with other compression are still read.
+    pub fn set_compression(&mut self, compression: HgtCacheCompression) {
+        self.compression = compression;
+    }
+
+    /// Limit the size of the cache on disk, in bytes, evicting files until it fits
+    pub fn set_max_bytes(&mut self, max_bytes: Option<u64>) -> Result<()> {
+        self.max_bytes = max_bytes;
+        match max_bytes {
+            Some(max_bytes) => self.evict(max_bytes, None),
+            None => Ok(()),
+        }
+    }
+
+    fn root(resolution: HgtResolution) -> &'static str {
+        match resolution {
             HgtResolution::One => "SRTM1",
             HgtResolution::Three => "SRTM3",
+        }
+    }
+
+    pub fn get(&self, latitude: f64, longitude: f64, resolution: HgtResolution) -> Result<HgtFile> {
+        let mut hgt_file = self.get_raw(latitude, longitude, resolution)?;
+
+        if let Some(void_fill) = self.void_fill {
+            if hgt_file.voids() > 0 {
+                // Without the fallback, voids are still interpolated
+                let fallback_opt = match void_fill.fallback {
+                    Some(fallback_res) if fallback_res != resolution => {
+                        match self.get_raw(latitude, longitude, fallback_res) {
+                            Ok(fallback_file) => {
+                                let mut mosaic = HgtMosaic::new(fallback_res);
+                                mosaic.insert(fallback_file)?;
+                                Some(mosaic)
+                            },
+                            Err(_) => None
+                        }
+                    },
+                    _ => None
+                };
+
+                hgt_file.fill_voids(void_fill.max_distance, fallback_opt.as_ref());
+            }
+        }
+
+        Ok(hgt_file)
+    }
+
+    fn get_raw(&self, latitude: f64, longitude: f64, resolution: HgtResolution) -> Result<HgtFile> {
+        let tile = HgtTileId::from_degrees(latitude, longitude);
+        let name = tile.name();
+
+        let dir = self.path.join(Self::root(resolution));
+
+        for compression in HgtCacheCompression::all().iter() {
+            let path = dir.join(format!("{}.{}", name, compression.extension()));
+            if ! path.exists() {
+                continue;
+            }
+
+            match Self::read(&path, *compression) {
+                Ok(data) => match HgtFile::new(tile.south(), tile.west(), resolution, data.into_boxed_slice()) {
+                    Ok(hgt_file) => {
+                        // Track last use for eviction
+                        if let Ok(file) = fs::OpenOptions::new().write(true).open(&path) {
+                            let _ = file.set_modified(SystemTime::now());
+                        }
+                        return Ok(hgt_file);
+                    },
+                    Err(err) => println!("HgtCache: {}: {}", path.display(), err),
+                },
+                Err(err) => println!("HgtCache: {}: {}", path.display(), err),
+            }
+
+            println!("HgtCache: removing invalid {}", path.display());
+            Self::remove_file(&path)?;
+        }
+
+        let hgt_file = self.srtm.get(tile.south(), tile.west(), resolution)?;
+
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.{}", name, self.compression.extension()));
+        Self::write(&path, &name, self.compression, &hgt_file.data)?;
+
+        if let Some(max_bytes) = self.max_bytes {
+            self.evict(max_bytes, Some(&path))?;
+        }
+
+        Ok(hgt_file)
+    }
+
+    fn checksum_path(path: &Path) -> PathBuf {
+        let mut name = path.file_name().unwrap_or_default().to_owned();
+        name.push(".crc32");
+        path.with_file_name(name)
+    }
+
+    /// Read and decompress a stored file, checking it against its checksum
+    fn read(path: &Path, compression: HgtCacheCompression) -> Result<Vec<u8>> {
+        let stored = fs::read(path)?;
+        let data = match compression {
+            HgtCacheCompression::None => stored,
+            HgtCacheCompression::Zip => {
+                let mut zip = zip::ZipArchive::new(Cursor::new(stored))?;
+                let mut zip_file = zip.by_index(0)?;
+                let mut data = Vec::new();
+                zip_file.read_to_end(&mut data)?;
+                data
+            },
+            HgtCacheCompression::Zstd => zstd::decode_all(Cursor::new(stored))?,
         };
 
-        let path = {
-            let mut path = self.path.clone();
-            path.push(root);
-            path.push(&format!("{}.hgt", name));
-            path
+        match fs::read_to_string(Self::checksum_path(path)) {
+            Ok(checksum) => {
+                let expected = u32::from_str_radix(checksum.trim(), 16).map_err(|err| Error::Format(
+                    format!("HgtCache: invalid checksum file: {}", err)
+                ))?;
+                let actual = crc32fast::hash(&data);
+                if actual != expected {
+                    return Err(Error::Checksum(
+                        format!("HgtCache: checksum {:08x} is not equal to {:08x}", actual, expected)
+                    ));
+                }
+            },
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Err(Error::Checksum(
+                "HgtCache: missing checksum file".to_string()
+            )),
+            Err(err) => return Err(err.into()),
+        }
+
+        Ok(data)
+    }
+
+    fn write(path: &Path, name: &str, compression: HgtCacheCompression, data: &[u8]) -> Result<()> {
+        let stored = match compression {
+            HgtCacheCompression::None => data.to_vec(),
+            HgtCacheCompression::Zip => {
+                let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+                zip.start_file(format!("{}.hgt", name), zip::write::FileOptions::default())?;
+                zip.write_all(data)?;
+                zip.finish()?.into_inner()
+            },
+            HgtCacheCompression::Zstd => zstd::encode_all(data, 0)?,
         };
 
-        if path.exists() {
-            return HgtFile::from_path(latitude, longitude, resolution, path);
+        // The checksum is written first, so a file is never present without it
+        write_atomic(&Self::checksum_path(path), format!("{:08x}\n", crc32fast::hash(data)).as_bytes())?;
+        Ok(write_atomic(path, &stored)?)
+    }
+
+    fn remove_file(path: &Path) -> io::Result<()> {
+        fs::remove_file(path)?;
+        match fs::remove_file(Self::checksum_path(path)) {
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            other => other,
+        }
+    }
+
+    /// List the files stored in the cache
+    pub fn list(&self) -> Result<Vec<HgtCacheEntry>> {
+        let mut entries = Vec::new();
+        for resolution in [HgtResolution::One, HgtResolution::Three].iter() {
+            let dir = self.path.join(Self::root(*resolution));
+            let read_dir = match fs::read_dir(&dir) {
+                Ok(ok) => ok,
+                Err(ref err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
+            };
+
+            for dir_entry_res in read_dir {
+                let dir_entry = dir_entry_res?;
+                let file_name = dir_entry.file_name().to_string_lossy().into_owned();
+                for compression in HgtCacheCompression::all().iter() {
+                    let suffix = format!(".{}", compression.extension());
+                    if ! file_name.ends_with(&suffix) {
+                        continue;
+                    }
+
+                    let path = dir_entry.path();
+                    let metadata = dir_entry.metadata()?;
+                    let checksum_size = fs::metadata(Self::checksum_path(&path)).map(|m| m.len()).unwrap_or(0);
+                    entries.push(HgtCacheEntry {
+                        name: file_name[..file_name.len() - suffix.len()].to_string(),
+                        path,
+                        resolution: *resolution,
+                        compression: *compression,
+                        size: metadata.len() + checksum_size,
+                        accessed: metadata.modified()?,
+                    });
+                }
+            }
         }
 
-        let hgt_file = HgtSrtm::get(latitude, longitude, resolution)?;
+        entries.sort_by_key(|entry| entry.accessed);
+        Ok(entries)
+    }
 
-        {
-            let mut path = self.path.clone();
-            path.push(root);
-            fs::create_dir_all(&path)?;
-            path.push(&format!("{}.hgt", name));
-            fs::File::create(&path)?.write_all(&hgt_file.data)?;
+    /// Remove every file from the cache, returning the number of bytes freed
+    pub fn purge(&self) -> Result<u64> {
+        let mut freed = 0;
+        for entry in self.list()? {
+            Self::remove_file(&entry.path)?;
+            freed += entry.size;
         }
+        Ok(freed)
+    }
 
-        Ok(hgt_file)
+    /// Remove the least recently used files, other than keep, until the cache is within max_bytes
+    fn evict(&self, max_bytes: u64, keep: Option<&Path>) -> Result<()> {
+        let entries = self.list()?;
+        let mut total: u64 = entries.iter().map(|entry| entry.size).sum();
+        for entry in entries.iter() {
+            if total <= max_bytes {
+                break;
+            }
+            if Some(entry.path.as_path()) == keep {
+                continue;
+            }
+            println!("HgtCache: evicting {}", entry.path.display());
+            Self::remove_file(&entry.path)?;
+            total -= entry.size;
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,120 @@
+use crate::hgt::HgtFile;
+
+/// A square of samples of an HgtFile, as a node of a quadtree used to draw distant terrain with
+/// less detail
+///
+/// The mesh of a chunk always has cells + 1 samples along each side, where cells is a power of
+/// two, so it can be simplified by an Rtin. Chunks smaller than that repeat samples.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HgtChunk {
+    pub row0: u16,
+    pub row1: u16,
+    pub col0: u16,
+    pub col1: u16,
+    /// Number of cells along each side of the mesh
+    pub cells: u16,
+    /// Estimated error in meters of the mesh of this chunk compared to all of its samples
+    pub error: f64,
+    /// Lowest height in meters of the samples of the mesh, or zero if they are all voids
+    pub low: f64,
+    /// Highest height in meters of the samples of the mesh, or zero if they are all voids
+    pub high: f64,
+}
+
+impl HgtChunk {
+    /// Create a chunk covering samples from row0 to row1 and col0 to col1 of a file
+    pub fn new(file: &HgtFile, cells: u16, row0: u16, row1: u16, col0: u16, col1: u16) -> Self {
+        let mut chunk = Self {
+            row0,
+            row1,
+            col0,
+            col1,
+            cells,
+            error: 0.0,
+            low: 0.0,
+            high: 0.0,
+        };
+        let (rows, cols) = chunk.indexes();
+
+        // Compare the sample in the middle of each cell with the cell corners
+        let height = |row: u16, col: u16| file.sample(row, col).map(|h| h as f64);
+        let mut low = f64::MAX;
+        let mut high = f64::MIN;
+        for i in 0..rows.len() {
+            for j in 0..cols.len() {
+                if let Some(h) = height(rows[i], cols[j]) {
+                    low = low.min(h);
+                    high = high.max(h);
+                }
+                if i + 1 < rows.len() && j + 1 < cols.len() {
+                    let mid_row = (rows[i] + rows[i + 1]) / 2;
+                    let mid_col = (cols[j] + cols[j + 1]) / 2;
+                    let corners = [
+                        height(rows[i], cols[j]),
+                        height(rows[i], cols[j + 1]),
+                        height(rows[i + 1], cols[j]),
+                        height(rows[i + 1], cols[j + 1]),
+                    ];
+                    if let (Some(mid), [Some(a), Some(b), Some(c), Some(d)]) = (height(mid_row, mid_col), corners) {
+                        let ty = (mid_row - rows[i]) as f64 / (rows[i + 1] - rows[i]).max(1) as f64;
+                        let tx = (mid_col - cols[j]) as f64 / (cols[j + 1] - cols[j]).max(1) as f64;
+                        let estimate = (a * (1.0 - tx) + b * tx) * (1.0 - ty) + (c * (1.0 - tx) + d * tx) * ty;
+                        chunk.error = chunk.error.max((mid - estimate).abs());
+                    }
+                }
+            }
+        }
+        if low <= high {
+            chunk.low = low;
+            chunk.high = high;
+        }
+
+        chunk
+    }
+
+    /// Create a chunk covering the whole file
+    pub fn root(file: &HgtFile, cells: u16) -> Self {
+        let last = file.resolution.samples() - 1;
+        Self::new(file, cells, 0, last, 0, last)
+    }
+
+    /// Rows and columns of the samples used by the mesh
+    pub fn indexes(&self) -> (Vec<u16>, Vec<u16>) {
+        let index = |start: u16, end: u16| -> Vec<u16> {
+            let span = (end - start) as u32;
+            let cells = self.cells as u32;
+            (0..=cells).map(|i| start + ((i * span + cells / 2) / cells) as u16).collect()
+        };
+        (index(self.row0, self.row1), index(self.col0, self.col1))
+    }
+
+    /// Check if the mesh includes every sample of the chunk, so it can not be split
+    pub fn is_leaf(&self) -> bool {
+        self.row1 - self.row0 <= self.cells && self.col1 - self.col0 <= self.cells
+    }
+
+    /// Split into the southwest, southeast, northwest and northeast quarters
+    pub fn children(&self, file: &HgtFile) -> [Self; 4] {
+        let mid_row = (self.row0 + self.row1) / 2;
+        let mid_col = (self.col0 + self.col1) / 2;
+        [
+            Self::new(file, self.cells, self.row0, mid_row, self.col0, mid_col),
+            Self::new(file, self.cells, self.row0, mid_row, mid_col, self.col1),
+            Self::new(file, self.cells, mid_row, self.row1, self.col0, mid_col),
+            Self::new(file, self.cells, mid_row, self.row1, mid_col, self.col1),
+        ]
+    }
+
+    /// Error in pixels of the mesh, simplified by up to mesh_error meters and with heights
+    /// multiplied by exaggeration, seen from distance meters with scale pixels per meter of error
+    /// at one meter
+    pub fn screen_error(&self, mesh_error: f64, exaggeration: f64, scale: f64, distance: f64) -> f64 {
+        (self.error + mesh_error) * exaggeration * scale / distance
+    }
+
+    /// Check if the chunk should be drawn as its children instead, as its screen error is more
+    /// than max_error pixels
+    pub fn split(&self, screen_error: f64, max_error: f64) -> bool {
+        screen_error > max_error && ! self.is_leaf()
+    }
+}
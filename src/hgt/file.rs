@@ -1,8 +1,9 @@
 use std::fs::File;
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
 use std::path::Path;
 
-use crate::hgt::HgtResolution;
+use crate::{Error, Result};
+use crate::hgt::{write_atomic, HgtInterpolation, HgtMosaic, HgtResolution, HgtSampler, HgtTileId};
 
 pub struct HgtFile {
     /// Identifies the southmost latitude
@@ -12,15 +13,16 @@ pub struct HgtFile {
     /// Identifies the resolution of the file
     pub resolution: HgtResolution,
     /// Data loaded from file
-    pub data: Box<[u8]>
+    pub data: Box<[u8]>,
+    /// Bit mask of samples filled by `fill_voids`, in the same order as data
+    pub filled: Option<Box<[u8]>>,
 }
 
 impl HgtFile {
-    pub fn new(latitude: f64, longitude: f64, resolution: HgtResolution, data: Box<[u8]>) -> io::Result<Self> {
+    pub fn new(latitude: f64, longitude: f64, resolution: HgtResolution, data: Box<[u8]>) -> Result<Self> {
         let expected_len = (resolution.samples() as usize).pow(2) * 2;
         if data.len() != expected_len {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
+            return Err(Error::Format(
                 format!("HgtFile: data size of {} is not equal to {}", data.len(), expected_len)
             ));
         }
@@ -29,12 +31,13 @@ impl HgtFile {
             latitude,
             longitude,
             resolution,
-            data: data
+            data: data,
+            filled: None,
         })
     }
 
     /// Creates a new HgtFile from a path, origin in latitude and longitude, and resolution in arc-seconds
-    pub fn from_path<P: AsRef<Path>>(latitude: f64, longitude: f64, resolution: HgtResolution, path: P) -> io::Result<Self> {
+    pub fn from_path<P: AsRef<Path>>(latitude: f64, longitude: f64, resolution: HgtResolution, path: P) -> Result<Self> {
         let data = {
             let mut file = File::open(path.as_ref())?;
             let metadata = file.metadata()?;
@@ -43,12 +46,7 @@ impl HgtFile {
             data.into_boxed_slice()
         };
 
-        Ok(Self {
-            latitude,
-            longitude,
-            resolution,
-            data
-        })
+        Self::new(latitude, longitude, resolution, data)
     }
 
     pub fn from_value(latitude: f64, longitude: f64, resolution: HgtResolution, value: i16) -> Self {
@@ -68,12 +66,33 @@ impl HgtFile {
             latitude,
             longitude,
             resolution,
-            data
+            data,
+            filled: None,
+        }
+    }
+
+    /// Create a new HgtFile with the height in meters of each sample from a function of its
+    /// latitude and longitude, where None is stored as a void
+    pub fn from_fn<F: Fn(f64, f64) -> Option<f64>>(latitude: f64, longitude: f64, resolution: HgtResolution, f: F) -> Self {
+        let mut file = Self::from_value(latitude, longitude, resolution, 0);
+        let samples = resolution.samples();
+        let res = resolution.degrees();
+        for row in 0..samples {
+            for col in 0..samples {
+                let value = f(latitude + (row as f64) * res, longitude + (col as f64) * res);
+                file.set(row, col, value.map(|h| h.round().clamp(-32767.0, 32767.0) as i16));
+            }
         }
+        file
     }
 
-    /// Get the height in meters at a file position
-    pub fn get(&self, row: u16, col: u16) -> Option<i16> {
+    /// Tile covered by this file
+    pub fn tile(&self) -> HgtTileId {
+        HgtTileId::from_degrees(self.latitude, self.longitude)
+    }
+
+    /// Get the height in meters at a file position, or None for a void
+    pub fn get(&self, row: u16, col: u16) -> Result<Option<i16>> {
         let samples = self.resolution.samples();
         if row > 0 && row < samples && col > 0 && col < samples {
             let offset = (((samples - row - 1) as usize) * (samples as usize) + ((col - 1) as usize)) * 2;
@@ -82,6 +101,28 @@ impl HgtFile {
             let low = self.data[offset + 1];
 
 
+            let sample = ((high as i16) << 8) + (low as i16);
+            if sample == -32768 {
+                Ok(None)
+            } else {
+                Ok(Some(sample))
+            }
+        } else {
+            Err(Error::OutOfRange(
+                format!("HgtFile: {}, {} out of bounds of {}", row, col, samples)
+            ))
+        }
+    }
+
+    /// Get the height in meters at a file position, including the first and last rows and columns
+    pub fn sample(&self, row: u16, col: u16) -> Option<i16> {
+        let samples = self.resolution.samples();
+        if row < samples && col < samples {
+            let offset = (((samples - row - 1) as usize) * (samples as usize) + (col as usize)) * 2;
+
+            let high = self.data[offset];
+            let low = self.data[offset + 1];
+
             let sample = ((high as i16) << 8) + (low as i16);
             if sample == -32768 {
                 None
@@ -89,11 +130,193 @@ impl HgtFile {
                 Some(sample)
             }
         } else {
-            println!("HgtFile: {}, {} out of bounds of {}", row, col, samples);
             None
         }
     }
 
+    /// Set the height in meters at a file position, or make it a void with None. Positions
+    /// outside the file are ignored.
+    pub fn set(&mut self, row: u16, col: u16, value: Option<i16>) {
+        let samples = self.resolution.samples();
+        if row < samples && col < samples {
+            let offset = (((samples - row - 1) as usize) * (samples as usize) + (col as usize)) * 2;
+            let sample = value.unwrap_or(-32768);
+            self.data[offset] = (sample >> 8) as u8;
+            self.data[offset + 1] = sample as u8;
+        }
+    }
+
+    /// Count the void samples in the file
+    pub fn voids(&self) -> usize {
+        self.data.chunks(2).filter(|pair| pair[0] == 0x80 && pair[1] == 0x00).count()
+    }
+
+    /// Check if the sample at a file position was filled by `fill_voids`
+    pub fn is_filled(&self, row: u16, col: u16) -> bool {
+        let samples = self.resolution.samples();
+        if let Some(ref filled) = self.filled {
+            if row < samples && col < samples {
+                let index = ((samples - row - 1) as usize) * (samples as usize) + (col as usize);
+                return filled[index / 8] & (1 << (index % 8)) != 0;
+            }
+        }
+        false
+    }
+
+    /// Fill void samples, returning the number of samples filled
+    ///
+    /// Voids are first taken from the fallback, if it covers them. Any remaining voids are filled
+    /// by inverse distance weighting of the nearest valid samples in eight directions, searching up
+    /// to max_distance samples away. Voids with no valid samples in range are left as voids.
+    pub fn fill_voids(&mut self, max_distance: u16, fallback: Option<&HgtMosaic>) -> usize {
+        let samples = self.resolution.samples() as usize;
+        let res = self.resolution.degrees();
+
+        let heights: Vec<Option<i16>> = self.data.chunks(2).map(|pair| {
+            let sample = ((pair[0] as i16) << 8) + (pair[1] as i16);
+            if sample == -32768 {
+                None
+            } else {
+                Some(sample)
+            }
+        }).collect();
+
+        let directions = [
+            (-1, -1), (-1, 0), (-1, 1),
+            (0, -1), (0, 1),
+            (1, -1), (1, 0), (1, 1),
+        ];
+
+        let mut filled = self.filled.take().unwrap_or_else(|| {
+            vec![0; (samples * samples).div_ceil(8)].into_boxed_slice()
+        });
+        let mut count = 0;
+        for (index, height) in heights.iter().enumerate() {
+            if height.is_some() {
+                continue;
+            }
+
+            let y = (index / samples) as i64;
+            let x = (index % samples) as i64;
+
+            let fallback_value = fallback.and_then(|mosaic| {
+                let latitude = self.latitude + ((samples as i64 - y - 1) as f64) * res;
+                let longitude = self.longitude + (x as f64) * res;
+                mosaic.elevation(latitude, longitude)
+            });
+
+            let value = fallback_value.or_else(|| {
+                let mut total = 0.0;
+                let mut weights = 0.0;
+                for &(dy, dx) in directions.iter() {
+                    for step in 1..=max_distance as i64 {
+                        let sy = y + dy * step;
+                        let sx = x + dx * step;
+                        if sy < 0 || sy >= samples as i64 || sx < 0 || sx >= samples as i64 {
+                            break;
+                        }
+                        if let Some(h) = heights[(sy as usize) * samples + (sx as usize)] {
+                            let distance_sq = ((dy * step).pow(2) + (dx * step).pow(2)) as f64;
+                            total += (h as f64) / distance_sq;
+                            weights += 1.0 / distance_sq;
+                            break;
+                        }
+                    }
+                }
+                if weights > 0.0 {
+                    Some(total / weights)
+                } else {
+                    None
+                }
+            });
+
+            if let Some(value) = value {
+                let sample = (value.round() as i16).max(-32767);
+                self.data[index * 2] = (sample >> 8) as u8;
+                self.data[index * 2 + 1] = sample as u8;
+                filled[index / 8] |= 1 << (index % 8);
+                count += 1;
+            }
+        }
+
+        self.filled = Some(filled);
+        count
+    }
+
+    /// Copy the file, with samples outside of bounds, as (south, west, north, east) in degrees,
+    /// made into voids
+    ///
+    /// The copy still covers the whole tile, as .hgt files can not be smaller, so bounds are only
+    /// known from where the voids are. Voids inside bounds are kept.
+    pub fn crop(&self, bounds: (f64, f64, f64, f64)) -> Self {
+        let (south, west, north, east) = bounds;
+        let res = self.resolution.degrees();
+        let tolerance = res * 1e-6;
+        let mut file = Self::from_value(self.latitude, self.longitude, self.resolution, 0);
+        file.data.copy_from_slice(&self.data);
+
+        let samples = self.resolution.samples();
+        for row in 0..samples {
+            let latitude = self.latitude + (row as f64) * res;
+            let row_inside = latitude >= south - tolerance && latitude <= north + tolerance;
+            for col in 0..samples {
+                let longitude = self.longitude + (col as f64) * res;
+                if ! row_inside || longitude < west - tolerance || longitude > east + tolerance {
+                    file.set(row, col, None);
+                }
+            }
+        }
+        file
+    }
+
+    /// Copy samples from another file of the same tile and resolution into voids of this file,
+    /// returning the number of samples copied
+    pub fn merge(&mut self, other: &Self) -> Result<usize> {
+        if other.resolution != self.resolution || other.tile() != self.tile() {
+            return Err(Error::OutOfRange(
+                format!(
+                    "HgtFile: cannot merge {} {:?} into {} {:?}",
+                    other.tile(), other.resolution, self.tile(), self.resolution
+                )
+            ));
+        }
+
+        let mut count = 0;
+        for (pair, other_pair) in self.data.chunks_mut(2).zip(other.data.chunks(2)) {
+            if pair[0] == 0x80 && pair[1] == 0x00 && ! (other_pair[0] == 0x80 && other_pair[1] == 0x00) {
+                pair.copy_from_slice(other_pair);
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Convert to another resolution. Samples shared by both resolutions keep their height, and
+    /// others are interpolated.
+    pub fn resample(&self, resolution: HgtResolution, interpolation: HgtInterpolation) -> Self {
+        Self::from_fn(self.latitude, self.longitude, resolution, |latitude, longitude| {
+            self.elevation_at(latitude, longitude, interpolation)
+        })
+    }
+
+    /// Write the samples in .hgt format
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&self.data)
+    }
+
+    /// Write a .hgt file. If path is a directory, the file is named after its tile, such as
+    /// N39W105.hgt. The file is written to a temporary file and renamed, so an existing file is
+    /// never left partially written.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let path = if path.is_dir() {
+            path.join(format!("{}.hgt", self.tile()))
+        } else {
+            path.to_owned()
+        };
+        write_atomic(&path, &self.data)
+    }
+
     /// Produce row and column from latitude and longitude
     pub fn position(&self, latitude: f64, longitude: f64) -> Option<(u16, u16)> {
         let res = self.resolution.degrees();
@@ -121,63 +344,24 @@ impl HgtFile {
             None
         }
     }
+}
 
-    pub fn interpolate(&self, latitude: f64, longitude: f64) -> Option<f64> {
-        let res = self.resolution.degrees();
-        let x = (longitude - self.longitude) / res;
-        let y = (latitude - self.latitude) / res;
-
-        let (x1i, y1i) = {
-            let samples = self.resolution.samples() as i64;
-            let xi = x.floor() as i64;
-            let yi = y.floor() as i64;
-            if xi > 0 && xi + 1 < samples && yi > 0 && yi + 1 < samples {
-                (xi as u16, yi as u16)
-            } else {
-                return None;
-            }
-        };
+impl HgtSampler for HgtFile {
+    fn origin(&self) -> (f64, f64) {
+        (self.latitude, self.longitude)
+    }
 
-        let (x2i, y2i) = (x1i + 1, y1i + 1);
-
-        let mut h_total = 0.0;
-        let mut h_count = 0;
-        let (h11_opt, h12_opt, h21_opt, h22_opt) = {
-            let mut h_fn = |col: u16, row: u16| -> Option<f64> {
-                let h = self.get(row, col)? as f64;
-                h_total += h;
-                h_count += 1;
-                Some(h)
-            };
-
-            (
-                h_fn(x1i, y1i),
-                h_fn(x1i, y2i),
-                h_fn(x2i, y1i),
-                h_fn(x2i, y2i),
-            )
-        };
+    fn spacing(&self) -> (f64, f64) {
+        let res = self.resolution.degrees();
+        (res, res)
+    }
 
-        if h_count == 0 {
-            return None;
+    fn height(&self, row: i64, col: i64) -> Option<f64> {
+        let samples = self.resolution.samples() as i64;
+        if row >= 0 && row < samples && col >= 0 && col < samples {
+            self.sample(row as u16, col as u16).map(|h| h as f64)
+        } else {
+            None
         }
-
-        let h_avg = h_total / (h_count as f64);
-        let (h11, h12, h21, h22) = (
-            h11_opt.unwrap_or(h_avg),
-            h12_opt.unwrap_or(h_avg),
-            h21_opt.unwrap_or(h_avg),
-            h22_opt.unwrap_or(h_avg),
-        );
-
-        let (x1, y1) = (x1i as f64, y1i as f64);
-        let (x2, y2) = (x2i as f64, y2i as f64);
-
-        Some((1.0 / ((x2 - x1) * (y2 - y1))) * (
-            h11 * (x2 - x) * (y2 - y) +
-            h21 * (x - x1) * (y2 - y) +
-            h12 * (x2 - x) * (y - y1) +
-            h22 * (x - x1) * (y - y1)
-        ))
     }
 }
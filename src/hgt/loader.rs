@@ -0,0 +1,182 @@
+use std::collections::HashSet;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use crate::Error;
+use crate::hgt::{HgtCache, HgtFile, HgtResolution, HgtTileId};
+
+/// Result of a tile requested from HgtLoader
+pub enum HgtLoaderEvent<T> {
+    /// The tile was loaded and processed
+    Loaded(HgtTileId, T),
+    /// The tile could not be loaded
+    Failed(HgtTileId, Error),
+}
+
+struct HgtLoaderQueue {
+    /// Tiles waiting for a worker
+    pending: Vec<HgtTileId>,
+    /// Tiles waiting or being loaded
+    in_flight: HashSet<HgtTileId>,
+    /// Position of the viewer, used to load the closest tiles first
+    viewer: (f64, f64),
+    stopped: bool,
+}
+
+impl HgtLoaderQueue {
+    /// Distance from the viewer to the center of a tile, in degrees of latitude
+    fn distance(&self, tile: HgtTileId) -> f64 {
+        let dlat = (tile.south() + 0.5) - self.viewer.0;
+        let dlon = ((tile.west() + 0.5) - self.viewer.1) * self.viewer.0.to_radians().cos();
+        (dlat.powi(2) + dlon.powi(2)).sqrt()
+    }
+
+    /// Remove the pending tile closest to the viewer
+    fn pop(&mut self) -> Option<HgtTileId> {
+        let mut best: Option<(usize, f64)> = None;
+        for (i, tile) in self.pending.iter().enumerate() {
+            let distance = self.distance(*tile);
+            if best.is_none_or(|(_, best_distance)| distance < best_distance) {
+                best = Some((i, distance));
+            }
+        }
+        best.map(|(i, _)| self.pending.swap_remove(i))
+    }
+}
+
+struct HgtLoaderShared {
+    queue: Mutex<HgtLoaderQueue>,
+    condvar: Condvar,
+}
+
+/// Loads tiles from an HgtCache on a pool of worker threads
+///
+/// Each tile is loaded at most once at a time, the tiles closest to the viewer are loaded first,
+/// and every request produces exactly one event unless it is cancelled before a worker starts it.
+pub struct HgtLoader<T> {
+    shared: Arc<HgtLoaderShared>,
+    events: Receiver<HgtLoaderEvent<T>>,
+}
+
+impl<T: Send + 'static> HgtLoader<T> {
+    /// Create a loader with the given number of workers, which process each file with map before
+    /// sending it back
+    pub fn new<F>(cache: Arc<HgtCache>, resolution: HgtResolution, workers: usize, map: F) -> Self
+        where F: Fn(HgtFile) -> T + Send + Sync + 'static
+    {
+        let shared = Arc::new(HgtLoaderShared {
+            queue: Mutex::new(HgtLoaderQueue {
+                pending: Vec::new(),
+                in_flight: HashSet::new(),
+                viewer: (0.0, 0.0),
+                stopped: false,
+            }),
+            condvar: Condvar::new(),
+        });
+
+        let (sender, events) = mpsc::channel();
+        let map = Arc::new(map);
+
+        for _ in 0..workers.max(1) {
+            let shared = shared.clone();
+            let cache = cache.clone();
+            let map = map.clone();
+            let sender: Sender<HgtLoaderEvent<T>> = sender.clone();
+            thread::spawn(move || loop {
+                let tile = {
+                    let mut queue = shared.queue.lock().unwrap();
+                    loop {
+                        if queue.stopped {
+                            return;
+                        }
+                        if let Some(tile) = queue.pop() {
+                            break tile;
+                        }
+                        queue = shared.condvar.wait(queue).unwrap();
+                    }
+                };
+
+                // A panic while loading or processing fails the tile instead of the worker, so the
+                // tile can be requested again
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    cache.get(tile.south(), tile.west(), resolution).map(|file| map(file))
+                }));
+                let event = match result {
+                    Ok(Ok(value)) => HgtLoaderEvent::Loaded(tile, value),
+                    Ok(Err(err)) => HgtLoaderEvent::Failed(tile, err),
+                    Err(payload) => {
+                        let message = payload.downcast_ref::<&str>().copied()
+                            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                            .unwrap_or("unknown panic");
+                        HgtLoaderEvent::Failed(tile, Error::Io(io::Error::other(
+                            format!("HgtLoader: panicked while loading {}: {}", tile, message)
+                        )))
+                    },
+                };
+
+                shared.queue.lock().unwrap().in_flight.remove(&tile);
+                if sender.send(event).is_err() {
+                    return;
+                }
+            });
+        }
+
+        Self {
+            shared,
+            events,
+        }
+    }
+
+    /// Request a tile, if it is not already being loaded
+    pub fn request(&self, tile: HgtTileId) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        if queue.in_flight.insert(tile) {
+            queue.pending.push(tile);
+            self.shared.condvar.notify_one();
+        }
+    }
+
+    /// Check if a tile is waiting or being loaded
+    pub fn is_loading(&self, tile: HgtTileId) -> bool {
+        self.shared.queue.lock().unwrap().in_flight.contains(&tile)
+    }
+
+    /// Update the position of the viewer, used to prioritize pending tiles
+    pub fn set_viewer(&self, latitude: f64, longitude: f64) {
+        self.shared.queue.lock().unwrap().viewer = (latitude, longitude);
+    }
+
+    /// Cancel pending tiles for which keep returns false. Tiles already being loaded are not
+    /// cancelled.
+    pub fn retain<F: Fn(HgtTileId) -> bool>(&self, keep: F) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        let mut cancelled = Vec::new();
+        queue.pending.retain(|tile| {
+            if keep(*tile) {
+                true
+            } else {
+                cancelled.push(*tile);
+                false
+            }
+        });
+        for tile in cancelled {
+            queue.in_flight.remove(&tile);
+        }
+    }
+
+    /// Get the next event, if one is ready
+    pub fn poll(&self) -> Option<HgtLoaderEvent<T>> {
+        self.events.try_recv().ok()
+    }
+}
+
+impl<T> Drop for HgtLoader<T> {
+    /// Stop the workers once they finish their current tile
+    fn drop(&mut self) {
+        self.shared.queue.lock().unwrap().stopped = true;
+        self.shared.condvar.notify_all();
+    }
+}
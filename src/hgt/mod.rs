@@ -1,12 +1,30 @@
-pub use self::cache::HgtCache;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+
+pub use self::cache::{HgtCache, HgtCacheCompression, HgtCacheEntry, HgtVoidFill};
+pub use self::chunk::HgtChunk;
 pub use self::file::HgtFile;
+pub use self::loader::{HgtLoader, HgtLoaderEvent};
+pub use self::mosaic::HgtMosaic;
+pub use self::sampler::{HgtInterpolation, HgtSampler};
+pub(crate) use self::sampler::MEAN_RADIUS;
 pub use self::srtm::HgtSrtm;
+pub use self::tile_id::HgtTileId;
+pub use self::water::HgtWaterMask;
 
 mod cache;
+mod chunk;
 mod file;
+mod loader;
+mod mosaic;
+mod sampler;
 mod srtm;
+mod tile_id;
+mod water;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum HgtResolution {
     /// One arc-second resolution
     One,
@@ -35,3 +53,18 @@ impl HgtResolution {
         }
     }
 }
+
+/// Write a file atomically, by writing to a temporary file and renaming it
+pub(crate) fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_owned();
+    temp_name.push(format!(".{}.tmp", process::id()));
+    let temp_path = path.with_file_name(temp_name);
+
+    {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+    }
+
+    fs::rename(&temp_path, path)
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use crate::{Error, Result};
+use crate::hgt::{HgtFile, HgtInterpolation, HgtResolution, HgtSampler, HgtTileId};
+
+/// A set of HgtFiles of the same resolution, sampled as one seamless grid
+///
+/// Neighbouring files share their edge rows and columns, so samples on a tile edge or
+/// corner are taken from whichever file covering them is loaded.
+pub struct HgtMosaic {
+    resolution: HgtResolution,
+    files: HashMap<HgtTileId, HgtFile>,
+}
+
+impl HgtMosaic {
+    /// Create an empty HgtMosaic
+    pub fn new(resolution: HgtResolution) -> Self {
+        Self {
+            resolution,
+            files: HashMap::new(),
+        }
+    }
+
+    /// Resolution of the files in this mosaic
+    pub fn resolution(&self) -> HgtResolution {
+        self.resolution
+    }
+
+    /// Add a file, returning the file it replaced, if any
+    pub fn insert(&mut self, file: HgtFile) -> Result<Option<HgtFile>> {
+        if file.resolution != self.resolution {
+            return Err(Error::OutOfRange(
+                format!("HgtMosaic: resolution {:?} is not equal to {:?}", file.resolution, self.resolution)
+            ));
+        }
+
+        let tile = HgtTileId::from_degrees(file.latitude, file.longitude);
+        Ok(self.files.insert(tile, file))
+    }
+
+    /// Remove the file for a tile
+    pub fn remove(&mut self, tile: HgtTileId) -> Option<HgtFile> {
+        self.files.remove(&tile)
+    }
+
+    /// Get the file for a tile
+    pub fn get(&self, tile: HgtTileId) -> Option<&HgtFile> {
+        self.files.get(&tile)
+    }
+
+    /// Iterate over all files
+    pub fn files(&self) -> impl Iterator<Item = &HgtFile> {
+        self.files.values()
+    }
+
+    /// Number of files
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// True if there are no files
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Get the height in meters of a sample, with row and column counted from the equator and
+    /// prime meridian
+    pub fn sample(&self, row: i64, col: i64) -> Option<i16> {
+        let intervals = (self.resolution.samples() - 1) as i64;
+
+        // A sample on an edge belongs to both the tile it starts and the tile it ends
+        let candidates = |index: i64| -> [Option<(i16, u16)>; 2] {
+            let tile = index.div_euclid(intervals);
+            let offset = index.rem_euclid(intervals);
+            [
+                Some((tile as i16, offset as u16)),
+                if offset == 0 {
+                    Some(((tile - 1) as i16, intervals as u16))
+                } else {
+                    None
+                },
+            ]
+        };
+
+        for &(file_lat, file_row) in candidates(row).iter().flatten() {
+            for &(file_lon, file_col) in candidates(col).iter().flatten() {
+                if let Some(file) = self.files.get(&HgtTileId::new(file_lat, file_lon)) {
+                    if let Some(sample) = file.sample(file_row, file_col) {
+                        return Some(sample);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Bilinear interpolation of the height in meters at any latitude and longitude
+    pub fn elevation(&self, latitude: f64, longitude: f64) -> Option<f64> {
+        self.elevation_at(latitude, longitude, HgtInterpolation::Bilinear)
+    }
+}
+
+impl HgtSampler for HgtMosaic {
+    fn origin(&self) -> (f64, f64) {
+        (0.0, 0.0)
+    }
+
+    fn spacing(&self) -> (f64, f64) {
+        let res = self.resolution.degrees();
+        (res, res)
+    }
+
+    fn height(&self, row: i64, col: i64) -> Option<f64> {
+        self.sample(row, col).map(|h| h as f64)
+    }
+}
@@ -0,0 +1,166 @@
+use std::f64;
+
+/// Mean radius of Earth in meters, used to convert sample spacing to distance
+///
+/// From https://en.wikipedia.org/wiki/Earth_radius#Mean_radius
+pub(crate) const MEAN_RADIUS: f64 = 6371008.8;
+
+/// Method used to find heights between samples
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HgtInterpolation {
+    /// Height of the closest sample
+    Nearest,
+    /// Weighted average of the four surrounding samples
+    Bilinear,
+    /// Catmull-Rom spline through the sixteen surrounding samples, falling back to bilinear when
+    /// any of them is missing
+    Bicubic,
+}
+
+/// A regular grid of heights in meters that can be queried at any latitude and longitude
+pub trait HgtSampler {
+    /// Latitude and longitude of the sample at row 0 and column 0
+    fn origin(&self) -> (f64, f64);
+
+    /// Spacing between rows and between columns, in degrees
+    fn spacing(&self) -> (f64, f64);
+
+    /// Height in meters of the sample at a row (counted northward) and column (counted eastward)
+    fn height(&self, row: i64, col: i64) -> Option<f64>;
+
+    /// Height in meters at a latitude and longitude
+    fn elevation_at(&self, latitude: f64, longitude: f64, interpolation: HgtInterpolation) -> Option<f64> {
+        let (y, x) = grid_position(self, latitude, longitude);
+        match interpolation {
+            HgtInterpolation::Nearest => self.height(y.round() as i64, x.round() as i64),
+            HgtInterpolation::Bilinear => bilinear(self, y, x),
+            HgtInterpolation::Bicubic => bicubic(self, y, x).or_else(|| bilinear(self, y, x)),
+        }
+    }
+
+    /// Rate of change of height toward the north and toward the east, in meters per meter
+    fn gradient(&self, latitude: f64, longitude: f64, interpolation: HgtInterpolation) -> Option<(f64, f64)> {
+        let (dlat, dlon) = self.spacing();
+        let (dy, dx) = grid_meters(self, latitude);
+
+        let n = self.elevation_at(latitude + dlat, longitude, interpolation)?;
+        let s = self.elevation_at(latitude - dlat, longitude, interpolation)?;
+        let e = self.elevation_at(latitude, longitude + dlon, interpolation)?;
+        let w = self.elevation_at(latitude, longitude - dlon, interpolation)?;
+
+        Some(((n - s) / (2.0 * dy), (e - w) / (2.0 * dx)))
+    }
+
+    /// Steepness of the surface in degrees from horizontal
+    fn slope(&self, latitude: f64, longitude: f64, interpolation: HgtInterpolation) -> Option<f64> {
+        let (gn, ge) = self.gradient(latitude, longitude, interpolation)?;
+        Some((gn.powi(2) + ge.powi(2)).sqrt().atan().to_degrees())
+    }
+
+    /// Direction the surface faces (downhill) in degrees clockwise from north, or None if flat
+    fn aspect(&self, latitude: f64, longitude: f64, interpolation: HgtInterpolation) -> Option<f64> {
+        let (gn, ge) = self.gradient(latitude, longitude, interpolation)?;
+        if gn == 0.0 && ge == 0.0 {
+            None
+        } else {
+            Some((-ge).atan2(-gn).rem_euclid(2.0 * f64::consts::PI).to_degrees())
+        }
+    }
+
+    /// Laplacian of the surface in 1/meters, positive in valleys and negative on ridges
+    fn curvature(&self, latitude: f64, longitude: f64, interpolation: HgtInterpolation) -> Option<f64> {
+        let (dlat, dlon) = self.spacing();
+        let (dy, dx) = grid_meters(self, latitude);
+
+        let c = self.elevation_at(latitude, longitude, interpolation)?;
+        let n = self.elevation_at(latitude + dlat, longitude, interpolation)?;
+        let s = self.elevation_at(latitude - dlat, longitude, interpolation)?;
+        let e = self.elevation_at(latitude, longitude + dlon, interpolation)?;
+        let w = self.elevation_at(latitude, longitude - dlon, interpolation)?;
+
+        Some((n - 2.0 * c + s) / dy.powi(2) + (e - 2.0 * c + w) / dx.powi(2))
+    }
+
+    /// Unit surface normal as (east, north, up)
+    fn normal(&self, latitude: f64, longitude: f64, interpolation: HgtInterpolation) -> Option<(f64, f64, f64)> {
+        let (gn, ge) = self.gradient(latitude, longitude, interpolation)?;
+        let l = (gn.powi(2) + ge.powi(2) + 1.0).sqrt();
+        Some((-ge / l, -gn / l, 1.0 / l))
+    }
+}
+
+/// Fractional row and column of a latitude and longitude
+fn grid_position<S: HgtSampler + ?Sized>(sampler: &S, latitude: f64, longitude: f64) -> (f64, f64) {
+    let (lat0, lon0) = sampler.origin();
+    let (dlat, dlon) = sampler.spacing();
+    (snap((latitude - lat0) / dlat), snap((longitude - lon0) / dlon))
+}
+
+/// Round positions within floating point error of a sample, so neighbours with a weight of zero
+/// are not required
+fn snap(v: f64) -> f64 {
+    let rounded = v.round();
+    if (v - rounded).abs() < 1e-6 {
+        rounded
+    } else {
+        v
+    }
+}
+
+/// Distance in meters between rows and between columns at a latitude
+fn grid_meters<S: HgtSampler + ?Sized>(sampler: &S, latitude: f64) -> (f64, f64) {
+    let (dlat, dlon) = sampler.spacing();
+    let dy = dlat.to_radians() * MEAN_RADIUS;
+    let dx = dlon.to_radians() * MEAN_RADIUS * latitude.to_radians().cos();
+    (dy, dx)
+}
+
+fn bilinear<S: HgtSampler + ?Sized>(sampler: &S, y: f64, x: f64) -> Option<f64> {
+    let row = y.floor();
+    let col = x.floor();
+    let fy = y - row;
+    let fx = x - col;
+
+    let mut total = 0.0;
+    for &(dr, wy) in &[(0, 1.0 - fy), (1, fy)] {
+        for &(dc, wx) in &[(0, 1.0 - fx), (1, fx)] {
+            let weight = wy * wx;
+            if weight > 0.0 {
+                total += weight * sampler.height(row as i64 + dr, col as i64 + dc)?;
+            }
+        }
+    }
+
+    Some(total)
+}
+
+/// Catmull-Rom weights for the four samples around a fraction t
+fn cubic_weights(t: f64) -> [f64; 4] {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    [
+        0.5 * (-t3 + 2.0 * t2 - t),
+        0.5 * (3.0 * t3 - 5.0 * t2 + 2.0),
+        0.5 * (-3.0 * t3 + 4.0 * t2 + t),
+        0.5 * (t3 - t2),
+    ]
+}
+
+fn bicubic<S: HgtSampler + ?Sized>(sampler: &S, y: f64, x: f64) -> Option<f64> {
+    let row = y.floor();
+    let col = x.floor();
+    let wy = cubic_weights(y - row);
+    let wx = cubic_weights(x - col);
+
+    let mut total = 0.0;
+    for (i, wr) in wy.iter().enumerate() {
+        for (j, wc) in wx.iter().enumerate() {
+            let weight = wr * wc;
+            if weight != 0.0 {
+                total += weight * sampler.height(row as i64 + i as i64 - 1, col as i64 + j as i64 - 1)?;
+            }
+        }
+    }
+
+    Some(total)
+}
@@ -1,14 +1,15 @@
 use reqwest;
+use std::fs;
 use std::io::{self, Cursor, Read};
 use zip;
 
-use crate::{reqwest_err, zip_err};
-use crate::hgt::{HgtFile, HgtResolution};
+use crate::{Error, Result};
+use crate::hgt::{HgtFile, HgtResolution, HgtTileId};
 
-//TODO: original source was removed? static SRTM_URL: &'static str = "https://dds.cr.usgs.gov/srtm/version2_1";
-static SRTM_URL: &'static str = "https://srtm.kurviger.de";
+//TODO: original source was removed? static SRTM_URL: &str = "https://dds.cr.usgs.gov/srtm/version2_1";
+static SRTM_URL: &str = "https://srtm.kurviger.de/{root}/{region}/{name}.hgt.zip";
 
-static SRTM1_DIRS: [&'static str; 7] = [
+static SRTM1_DIRS: [&str; 7] = [
     "Region_01",
     "Region_02",
     "Region_03",
@@ -18,7 +19,7 @@ static SRTM1_DIRS: [&'static str; 7] = [
     "Region_07",
 ];
 
-static SRTM3_DIRS: [&'static str; 6] = [
+static SRTM3_DIRS: [&str; 6] = [
     "Africa",
     "Australia",
     "Eurasia",
@@ -27,59 +28,212 @@ static SRTM3_DIRS: [&'static str; 6] = [
     "South_America",
 ];
 
-pub struct HgtSrtm;
+/// A region directory and its approximate bounds as (south, west, north, east)
+type RegionBounds = (&'static str, (i16, i16, i16, i16));
+
+/// Approximate bounds of each region directory, used to request the most likely directory first
+static SRTM1_BOUNDS: [RegionBounds; 7] = [
+    ("Region_01", (38, -126, 50, -111)),
+    ("Region_02", (38, -111, 50, -97)),
+    ("Region_03", (28, -126, 38, -100)),
+    ("Region_04", (24, -100, 38, -85)),
+    ("Region_05", (38, -97, 50, -66)),
+    ("Region_06", (17, -85, 38, -64)),
+    ("Region_07", (-15, -180, 72, -128)),
+];
+
+static SRTM3_BOUNDS: [RegionBounds; 6] = [
+    ("North_America", (10, -180, 61, -52)),
+    ("South_America", (-57, -93, 15, -32)),
+    ("Africa", (-36, -19, 38, 56)),
+    ("Eurasia", (-11, -16, 61, 180)),
+    ("Australia", (-45, 111, -10, 180)),
+    ("Islands", (-90, -180, 90, 180)),
+];
+
+/// Downloads HgtFiles from a list of sources, tried in order
+///
+/// Sources are URL templates, where `{root}` is replaced by SRTM1 or SRTM3, `{region}` by each
+/// region directory, most likely first, and `{name}` by the file name, such as N39W105. Sources
+/// may be `http://`, `https://`, `file://` or plain paths, and files ending in `.zip` are
+/// extracted.
+pub struct HgtSrtm {
+    sources: Vec<String>,
+    offline: bool,
+}
+
+impl Default for HgtSrtm {
+    fn default() -> Self {
+        Self::new(vec![SRTM_URL.to_string()])
+    }
+}
 
 impl HgtSrtm {
-    pub fn get(latitude: f64, longitude: f64, resolution: HgtResolution) -> io::Result<HgtFile> {
-        let name = format!(
-            "{}{:02}{}{:03}",
-            if latitude < 0.0 {
-                "S"
-            } else {
-                "N"
-            },
-            latitude.abs() as u32,
-            if longitude < 0.0 {
-                "W"
-            } else {
-                "E"
-            },
-            longitude.abs() as u32
-        );
-
-        let (root, dirs): (&str, &[&str]) = match resolution {
-            HgtResolution::One => ("SRTM1", &SRTM1_DIRS),
-            HgtResolution::Three => ("SRTM3", &SRTM3_DIRS),
+    pub fn new(sources: Vec<String>) -> Self {
+        Self {
+            sources,
+            offline: false,
+        }
+    }
+
+    /// Sources that will be tried, in order
+    pub fn sources(&self) -> &[String] {
+        &self.sources
+    }
+
+    /// Only use local sources, which are files and servers on localhost, failing immediately when
+    /// a file is not available locally
+    pub fn set_offline(&mut self, offline: bool) {
+        self.offline = offline;
+    }
+
+    pub fn offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Region directories for a tile, most likely first
+    fn regions(latitude: i16, longitude: i16, resolution: HgtResolution) -> Vec<&'static str> {
+        let (dirs, bounds): (&[&str], &[RegionBounds]) = match resolution {
+            HgtResolution::One => (&SRTM1_DIRS, &SRTM1_BOUNDS),
+            HgtResolution::Three => (&SRTM3_DIRS, &SRTM3_BOUNDS),
+        };
+
+        let mut regions = Vec::with_capacity(dirs.len());
+        for &(dir, (south, west, north, east)) in bounds.iter() {
+            if latitude >= south && latitude < north && longitude >= west && longitude < east {
+                regions.push(dir);
+            }
+        }
+        for &dir in dirs.iter() {
+            if ! regions.contains(&dir) {
+                regions.push(dir);
+            }
+        }
+        regions
+    }
+
+    /// Check if a source is a file or on a server on this machine
+    fn is_local(source: &str) -> bool {
+        let rest = match source.split_once("://") {
+            Some(("http", rest)) | Some(("https", rest)) => rest,
+            _ => return true,
         };
 
-        for dir in dirs.iter() {
-            let url = format!("{}/{}/{}/{}.hgt.zip", SRTM_URL, root, dir, name);
+        let authority = rest.split('/').next().unwrap_or("");
+        let host_port = authority.rsplit('@').next().unwrap_or("");
+        let host = if host_port.starts_with('[') {
+            host_port.split(']').next().unwrap_or("").trim_start_matches('[')
+        } else {
+            host_port.split(':').next().unwrap_or("")
+        };
+        host.eq_ignore_ascii_case("localhost") || host.starts_with("127.") || host == "::1"
+    }
+
+    /// Read the contents of a source URL, returning None if it does not exist
+    fn fetch(url: &str) -> Result<Option<Vec<u8>>> {
+        if url.starts_with("http://") || url.starts_with("https://") {
             println!("{}", url);
 
-            let mut response = reqwest::get(&url).map_err(reqwest_err)?;
+            let mut response = reqwest::get(url)?;
             let status = response.status();
             println!("  status {}", status);
 
             if status.is_success() {
-                let mut zip_data = Vec::new();
-                response.copy_to(&mut zip_data).map_err(reqwest_err)?;
-                println!("  received {} bytes", zip_data.len());
+                let mut data = Vec::new();
+                response.copy_to(&mut data)?;
+                println!("  received {} bytes", data.len());
+                Ok(Some(data))
+            } else {
+                Ok(None)
+            }
+        } else {
+            let path = url.trim_start_matches("file://");
+            match fs::read(path) {
+                Ok(data) => Ok(Some(data)),
+                Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err.into()),
+            }
+        }
+    }
 
-                let mut zip = zip::ZipArchive::new(Cursor::new(zip_data)).map_err(zip_err)?;
-                let mut zip_file = zip.by_name(&format!("{}.hgt", name)).map_err(zip_err)?;
+    /// Extract the file from a zip archive, if the URL is one
+    fn decode(url: &str, name: &str, data: Vec<u8>) -> Result<Vec<u8>> {
+        if url.ends_with(".zip") {
+            let mut zip = zip::ZipArchive::new(Cursor::new(data))?;
+            let mut zip_file = zip.by_name(&format!("{}.hgt", name))?;
 
-                let mut data = Vec::new();
-                zip_file.read_to_end(&mut data)?;
+            let mut data = Vec::new();
+            zip_file.read_to_end(&mut data)?;
+
+            println!("  uncompressed {} bytes", data.len());
 
-                println!("  uncompressed {} bytes", data.len());
+            Ok(data)
+        } else {
+            Ok(data)
+        }
+    }
 
-                return HgtFile::new(latitude, longitude, resolution, data.into_boxed_slice());
+    pub fn get(&self, latitude: f64, longitude: f64, resolution: HgtResolution) -> Result<HgtFile> {
+        let tile = HgtTileId::from_degrees(latitude, longitude);
+        let name = tile.name();
+
+        let root = match resolution {
+            HgtResolution::One => "SRTM1",
+            HgtResolution::Three => "SRTM3",
+        };
+
+        let regions = Self::regions(tile.latitude, tile.longitude, resolution);
+
+        // Errors from one source are only returned if no other source has the file
+        let mut error_opt = None;
+        for source in self.sources.iter() {
+            if self.offline && ! Self::is_local(source) {
+                continue;
             }
+
+            let template = source.replace("{root}", root).replace("{name}", &name);
+            let urls: Vec<String> = if template.contains("{region}") {
+                regions.iter().map(|region| template.replace("{region}", region)).collect()
+            } else {
+                vec![template]
+            };
+
+            for url in urls.iter() {
+                let result = Self::fetch(url).and_then(|data_opt| match data_opt {
+                    Some(data) => Self::decode(url, &name, data).map(Some),
+                    None => Ok(None),
+                });
+                let data = match result {
+                    Ok(Some(some)) => some,
+                    Ok(None) => continue,
+                    Err(err) => {
+                        println!("  {}", err);
+                        error_opt = Some(err);
+                        continue;
+                    }
+                };
+
+                match HgtFile::new(tile.south(), tile.west(), resolution, data.into_boxed_slice()) {
+                    Ok(hgt_file) => return Ok(hgt_file),
+                    Err(err) => {
+                        println!("  {}", err);
+                        error_opt = Some(err);
+                    }
+                }
+            }
+        }
+
+        if let Some(err) = error_opt {
+            return Err(err);
         }
 
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            format!("HgtSrtm: failed to find {} in {}", name, root)
+        Err(Error::NotFound(
+            format!(
+                "HgtSrtm: failed to find {} in {}{}",
+                name,
+                root,
+                if self.offline { " while offline" } else { "" }
+            )
         ))
     }
 }
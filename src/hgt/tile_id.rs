@@ -0,0 +1,163 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Error, Result};
+use crate::coordinate::Coordinate;
+use crate::hgt::sampler::MEAN_RADIUS;
+use crate::spheroid::Spheroid;
+
+/// Identifies a one degree tile by the latitude and longitude of its southwest corner
+///
+/// Tiles are named like SRTM files, so the tile containing (-0.5, -0.5) is S01W001.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct HgtTileId {
+    /// Southmost latitude, from -90 to 89
+    pub latitude: i16,
+    /// Westmost longitude, from -180 to 179
+    pub longitude: i16,
+}
+
+impl HgtTileId {
+    pub fn new(latitude: i16, longitude: i16) -> Self {
+        Self {
+            latitude,
+            longitude,
+        }
+    }
+
+    /// Get the tile containing a latitude and longitude. The north pole belongs to the tiles at 89
+    /// degrees, and longitudes are wrapped to [-180, 180).
+    pub fn from_degrees(latitude: f64, longitude: f64) -> Self {
+        let latitude = latitude.floor().clamp(-90.0, 89.0) as i16;
+        let longitude = ((longitude.floor() as i64 + 180).rem_euclid(360) - 180) as i16;
+        Self::new(latitude, longitude)
+    }
+
+    /// Name of the tile, such as N39W105
+    pub fn name(&self) -> String {
+        self.to_string()
+    }
+
+    /// Latitude of the southwest corner in degrees
+    pub fn south(&self) -> f64 {
+        self.latitude as f64
+    }
+
+    /// Longitude of the southwest corner in degrees
+    pub fn west(&self) -> f64 {
+        self.longitude as f64
+    }
+
+    /// Check if a latitude and longitude is inside the tile, including its edges
+    pub fn contains(&self, latitude: f64, longitude: f64) -> bool {
+        latitude >= self.south() && latitude <= self.south() + 1.0 &&
+        longitude >= self.west() && longitude <= self.west() + 1.0
+    }
+
+    /// All tiles covering a bounding box in degrees. If west is greater than east, the box crosses
+    /// the antimeridian.
+    pub fn covering(south: f64, west: f64, north: f64, east: f64) -> Vec<Self> {
+        let south = south.floor().max(-90.0) as i64;
+        let north = (north.ceil().min(90.0) as i64 - 1).max(south);
+
+        let west_i = west.floor() as i64;
+        let mut east_i = east.ceil() as i64 - 1;
+        if east < west {
+            east_i += 360;
+        }
+        let east_i = east_i.max(west_i);
+        let count = (east_i - west_i + 1).min(360);
+
+        let mut tiles = Vec::new();
+        for latitude in south..=north {
+            for longitude in west_i..west_i + count {
+                tiles.push(Self::from_degrees(latitude as f64, longitude as f64));
+            }
+        }
+        tiles
+    }
+
+    /// All tiles with any point within a distance in meters of a latitude and longitude
+    pub fn within_radius(latitude: f64, longitude: f64, radius: f64) -> Vec<Self> {
+        let dlat = (radius / MEAN_RADIUS).to_degrees();
+        let cos = (latitude.abs() + dlat).min(90.0).to_radians().cos();
+        let dlon = if cos > 1e-9 { (dlat / cos).min(180.0) } else { 180.0 };
+
+        Self::covering(latitude - dlat, longitude - dlon, latitude + dlat, longitude + dlon)
+            .into_iter()
+            .filter(|tile| tile.distance(latitude, longitude) <= radius)
+            .collect()
+    }
+
+    /// Great-circle distance in meters from a latitude and longitude to the closest point of the
+    /// tile
+    pub fn distance(&self, latitude: f64, longitude: f64) -> f64 {
+        // Longitude relative to the tile, wrapped to the nearest side
+        let dlon = (longitude - self.west() + 180.0).rem_euclid(360.0) - 180.0;
+        let closest_lat = latitude.clamp(self.south(), self.south() + 1.0);
+        let closest_lon = self.west() + dlon.clamp(0.0, 1.0);
+
+        let f1 = latitude.to_radians();
+        let f2 = closest_lat.to_radians();
+        let dl = (self.west() + dlon - closest_lon).to_radians();
+        let th = 2.0 * (
+            ((f2 - f1) / 2.0).sin().powi(2) + f1.cos() * f2.cos() * (dl / 2.0).sin().powi(2)
+        ).sqrt().asin();
+        th * MEAN_RADIUS
+    }
+}
+
+impl<'a, 'r, R: Spheroid> From<&'a Coordinate<'r, R>> for HgtTileId {
+    fn from(coordinate: &'a Coordinate<'r, R>) -> Self {
+        Self::from_degrees(coordinate.latitude, coordinate.longitude)
+    }
+}
+
+impl fmt::Display for HgtTileId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{:02}{}{:03}",
+            if self.latitude < 0 { "S" } else { "N" },
+            self.latitude.abs(),
+            if self.longitude < 0 { "W" } else { "E" },
+            self.longitude.abs()
+        )
+    }
+}
+
+impl FromStr for HgtTileId {
+    type Err = Error;
+
+    /// Parse a name such as N39W105, ignoring case and any extension
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::Format(
+            format!("HgtTileId: invalid name {:?}", s)
+        );
+
+        let name = s.split('.').next().unwrap_or("").to_ascii_uppercase();
+        let bytes = name.as_bytes();
+        if bytes.len() != 7 || ! bytes.iter().enumerate().all(|(i, b)| i == 0 || i == 3 || b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+
+        let latitude: i16 = name[1..3].parse().map_err(|_| invalid())?;
+        let longitude: i16 = name[4..7].parse().map_err(|_| invalid())?;
+        let latitude = match bytes[0] {
+            b'N' => latitude,
+            b'S' => -latitude,
+            _ => return Err(invalid()),
+        };
+        let longitude = match bytes[3] {
+            b'E' => longitude,
+            b'W' => -longitude,
+            _ => return Err(invalid()),
+        };
+
+        if ! (-90..=89).contains(&latitude) || ! (-180..=179).contains(&longitude) {
+            return Err(invalid());
+        }
+
+        Ok(Self::new(latitude, longitude))
+    }
+}
@@ -0,0 +1,360 @@
+use std::fs;
+use std::collections::VecDeque;
+use std::path::Path;
+
+use crate::{Error, Result};
+use crate::geotiff::GeoTiff;
+use crate::hgt::{HgtFile, HgtResolution, HgtTileId};
+
+/// Water bodies whose samples differ by at most this many meters are flattened to their lowest
+/// sample. Others, such as rivers, only have noise removed.
+const LAKE_RANGE: f64 = 20.0;
+
+fn invalid(message: String) -> Error {
+    Error::Format(format!("HgtWaterMask: {}", message))
+}
+
+/// Which samples of a tile are water, on the same grid as an HgtFile
+pub struct HgtWaterMask {
+    pub tile: HgtTileId,
+    pub resolution: HgtResolution,
+    /// One bit per sample, starting at the southwest corner, row by row
+    bits: Box<[u64]>,
+}
+
+impl HgtWaterMask {
+    /// Create a mask with no water
+    pub fn new(tile: HgtTileId, resolution: HgtResolution) -> Self {
+        let samples = resolution.samples() as usize;
+        Self {
+            tile,
+            resolution,
+            bits: vec![0; (samples * samples).div_ceil(64)].into_boxed_slice(),
+        }
+    }
+
+    /// Rasterize the polygons of an ESRI shapefile, such as the SRTM Water Body Data, with
+    /// coordinates in degrees. Samples inside any polygon, by the even-odd rule, are water.
+    pub fn from_shapefile(tile: HgtTileId, resolution: HgtResolution, data: &[u8]) -> Result<Self> {
+        let rings = shapefile_rings(data)?;
+
+        let mut mask = Self::new(tile, resolution);
+        let samples = resolution.samples();
+        let res = resolution.degrees();
+        let mut crossings = Vec::new();
+        for row in 0..samples {
+            let latitude = tile.south() + (row as f64) * res;
+
+            crossings.clear();
+            for ring in rings.iter() {
+                for pair in ring.windows(2) {
+                    let ((x1, y1), (x2, y2)) = (pair[0], pair[1]);
+                    if (y1 <= latitude) != (y2 <= latitude) {
+                        crossings.push(x1 + (latitude - y1) * (x2 - x1) / (y2 - y1));
+                    }
+                }
+            }
+            crossings.sort_by(f64::total_cmp);
+
+            for span in crossings.chunks(2) {
+                if span.len() < 2 {
+                    continue;
+                }
+                let first = ((span[0] - tile.west()) / res).ceil().max(0.0);
+                let last = ((span[1] - tile.west()) / res).ceil().min(samples as f64);
+                for col in first as u16..last.max(first) as u16 {
+                    mask.set(row, col, true);
+                }
+            }
+        }
+
+        Ok(mask)
+    }
+
+    /// Use a raster mask, such as the Copernicus Water Body Mask, where samples of any value other
+    /// than zero are water
+    pub fn from_geotiff(tile: HgtTileId, resolution: HgtResolution, geotiff: &GeoTiff) -> Self {
+        let mut mask = Self::new(tile, resolution);
+        let samples = resolution.samples();
+        let res = resolution.degrees();
+        for row in 0..samples {
+            for col in 0..samples {
+                let latitude = tile.south() + (row as f64) * res;
+                let longitude = tile.west() + (col as f64) * res;
+                let water = geotiff.position(latitude, longitude)
+                    .and_then(|(r, c)| geotiff.get(r, c))
+                    .is_some_and(|value| value != 0.0);
+                if water {
+                    mask.set(row, col, true);
+                }
+            }
+        }
+        mask
+    }
+
+    /// Read a mask from a shapefile (.shp) or GeoTIFF (.tif or .tiff)
+    pub fn open<P: AsRef<Path>>(tile: HgtTileId, resolution: HgtResolution, path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let extension = path.extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "shp" => Self::from_shapefile(tile, resolution, &fs::read(path)?),
+            "tif" | "tiff" => Ok(Self::from_geotiff(tile, resolution, &GeoTiff::from_path(path)?)),
+            _ => Err(Error::Format(
+                format!("HgtWaterMask: unsupported file {}", path.display())
+            )),
+        }
+    }
+
+    /// Find and read the mask of a tile in a directory, named like SWBD files (w105n39n.shp, with
+    /// any continent letter) or like HGT files (N39W105.shp or N39W105.tif)
+    pub fn find<P: AsRef<Path>>(tile: HgtTileId, resolution: HgtResolution, dir: P) -> Result<Option<Self>> {
+        let swbd = format!(
+            "{}{:03}{}{:02}",
+            if tile.longitude < 0 { "w" } else { "e" },
+            tile.longitude.abs(),
+            if tile.latitude < 0 { "s" } else { "n" },
+            tile.latitude.abs()
+        );
+        let hgt = tile.name().to_ascii_lowercase();
+
+        let mut paths: Vec<_> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_ascii_lowercase());
+                let extension = path.extension().map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+                match (stem, extension.as_deref()) {
+                    (Some(stem), Some("shp")) => stem == hgt || (stem.starts_with(&swbd) && stem.len() == swbd.len() + 1),
+                    (Some(stem), Some("tif")) | (Some(stem), Some("tiff")) => stem == hgt,
+                    _ => false,
+                }
+            })
+            .collect();
+        paths.sort();
+
+        // A tile on a coast may have files for several continents
+        let mut found: Option<Self> = None;
+        for path in paths {
+            let mask = Self::open(tile, resolution, &path)?;
+            match found {
+                Some(ref mut found) => {
+                    for (a, b) in found.bits.iter_mut().zip(mask.bits.iter()) {
+                        *a |= *b;
+                    }
+                },
+                None => found = Some(mask),
+            }
+        }
+        Ok(found)
+    }
+
+    fn index(&self, row: u16, col: u16) -> Option<usize> {
+        let samples = self.resolution.samples();
+        if row < samples && col < samples {
+            Some((row as usize) * (samples as usize) + (col as usize))
+        } else {
+            None
+        }
+    }
+
+    /// Check if the sample at a row (counted northward) and column is water
+    pub fn is_water(&self, row: u16, col: u16) -> bool {
+        self.index(row, col).is_some_and(|i| self.bits[i / 64] & (1 << (i % 64)) != 0)
+    }
+
+    pub fn set(&mut self, row: u16, col: u16, water: bool) {
+        if let Some(i) = self.index(row, col) {
+            if water {
+                self.bits[i / 64] |= 1 << (i % 64);
+            } else {
+                self.bits[i / 64] &= !(1 << (i % 64));
+            }
+        }
+    }
+
+    /// Check if the sample closest to a latitude and longitude is water
+    pub fn water_at(&self, latitude: f64, longitude: f64) -> bool {
+        let res = self.resolution.degrees();
+        let row = ((latitude - self.tile.south()) / res).round();
+        let col = ((longitude - self.tile.west()) / res).round();
+        row >= 0.0 && col >= 0.0 && row <= u16::MAX as f64 && col <= u16::MAX as f64 &&
+            self.is_water(row as u16, col as u16)
+    }
+
+    /// Number of water samples
+    pub fn count(&self) -> usize {
+        self.bits.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Level the water of a file of the same tile and resolution, returning the number of samples
+    /// changed
+    ///
+    /// Each connected body of water is set to its lowest sample if it is nearly level, like a lake.
+    /// Otherwise, each sample is set to the lowest of its water neighbours, which keeps the slope
+    /// of rivers but removes noise.
+    pub fn flatten(&self, file: &mut HgtFile) -> Result<usize> {
+        if file.tile() != self.tile || file.resolution != self.resolution {
+            return Err(Error::OutOfRange(
+                format!("HgtWaterMask: cannot flatten {} with mask of {}", file.tile(), self.tile)
+            ));
+        }
+
+        let samples = self.resolution.samples();
+        let mut visited = vec![false; samples as usize * samples as usize];
+        let mut changed = 0;
+        for start_row in 0..samples {
+            for start_col in 0..samples {
+                let start = (start_row as usize) * (samples as usize) + (start_col as usize);
+                if visited[start] || ! self.is_water(start_row, start_col) {
+                    continue;
+                }
+
+                // Flood fill the body of water, with four neighbours
+                let mut body = Vec::new();
+                let mut queue = VecDeque::new();
+                visited[start] = true;
+                queue.push_back((start_row, start_col));
+                while let Some((row, col)) = queue.pop_front() {
+                    body.push((row, col));
+                    let neighbours = [
+                        (row.wrapping_sub(1), col),
+                        (row + 1, col),
+                        (row, col.wrapping_sub(1)),
+                        (row, col + 1),
+                    ];
+                    for &(r, c) in neighbours.iter() {
+                        if let Some(i) = self.index(r, c) {
+                            if ! visited[i] && self.is_water(r, c) {
+                                visited[i] = true;
+                                queue.push_back((r, c));
+                            }
+                        }
+                    }
+                }
+
+                let heights: Vec<i16> = body.iter().filter_map(|&(row, col)| file.sample(row, col)).collect();
+                let (low, high) = match (heights.iter().min(), heights.iter().max()) {
+                    (Some(&low), Some(&high)) => (low, high),
+                    _ => continue,
+                };
+
+                let levels: Vec<i16> = if high as f64 - low as f64 <= LAKE_RANGE {
+                    vec![low; body.len()]
+                } else {
+                    body.iter().map(|&(row, col)| {
+                        let mut level = file.sample(row, col).unwrap_or(high);
+                        for r in row.saturating_sub(1)..=(row + 1).min(samples - 1) {
+                            for c in col.saturating_sub(1)..=(col + 1).min(samples - 1) {
+                                if self.is_water(r, c) {
+                                    if let Some(h) = file.sample(r, c) {
+                                        level = level.min(h);
+                                    }
+                                }
+                            }
+                        }
+                        level
+                    }).collect()
+                };
+
+                for (&(row, col), &level) in body.iter().zip(levels.iter()) {
+                    if file.sample(row, col) != Some(level) {
+                        file.set(row, col, Some(level));
+                        changed += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(changed)
+    }
+}
+
+/// Read the rings of all polygons in a shapefile as (longitude, latitude) points
+///
+/// From https://www.esri.com/content/dam/esrisites/sitecore-archive/Files/Pdfs/library/whitepapers/pdfs/shapefile.pdf
+fn shapefile_rings(data: &[u8]) -> Result<Vec<Vec<(f64, f64)>>> {
+    let be_i32 = |pos: usize| -> Result<i32> {
+        data.get(pos..pos + 4)
+            .map(|b| i32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(|| invalid(format!("unexpected end of file at {}", pos)))
+    };
+    let le_i32 = |pos: usize| -> Result<i32> {
+        data.get(pos..pos + 4)
+            .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(|| invalid(format!("unexpected end of file at {}", pos)))
+    };
+    let le_f64 = |pos: usize| -> Result<f64> {
+        data.get(pos..pos + 8)
+            .map(|b| f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+            .ok_or_else(|| invalid(format!("unexpected end of file at {}", pos)))
+    };
+
+    // Lengths and counts that are negative or overflow cannot be valid
+    let count = |value: i32| -> Result<usize> {
+        usize::try_from(value).map_err(|_| invalid(format!("negative length or count {}", value)))
+    };
+    let overflow = || invalid("length or count is too large".to_string());
+
+    if be_i32(0)? != 9994 {
+        return Err(invalid("not a shapefile".to_string()));
+    }
+    let len = count(be_i32(24)?)?.checked_mul(2).ok_or_else(overflow)?.min(data.len());
+
+    let mut rings = Vec::new();
+    let mut pos: usize = 100;
+    while pos.saturating_add(8) <= len {
+        let content = count(be_i32(pos + 4)?)?.checked_mul(2).ok_or_else(overflow)?;
+        let record = pos + 8;
+        pos = record.checked_add(content).ok_or_else(overflow)?;
+
+        // Polygon, PolygonZ and PolygonM share the layout of their points
+        match le_i32(record)? {
+            0 => continue,
+            5 | 15 | 25 => (),
+            other => return Err(invalid(format!("unsupported shape type {}", other))),
+        }
+
+        let parts = count(le_i32(record + 36)?)?;
+        let points = count(le_i32(record + 40)?)?;
+        let points_start = parts.checked_mul(4)
+            .and_then(|parts_len| (record + 44).checked_add(parts_len))
+            .ok_or_else(overflow)?;
+        let points_end = points.checked_mul(16)
+            .and_then(|points_len| points_start.checked_add(points_len))
+            .ok_or_else(overflow)?;
+        if points_end > pos {
+            return Err(invalid(format!("record at {} is too short", record)));
+        }
+
+        for part in 0..parts {
+            let first = count(le_i32(record + 44 + part * 4)?)?;
+            let last = if part + 1 < parts {
+                count(le_i32(record + 44 + (part + 1) * 4)?)?
+            } else {
+                points
+            };
+            if first > last || last > points {
+                return Err(invalid(format!("invalid part {} of record at {}", part, record)));
+            }
+
+            let mut ring = Vec::with_capacity(last - first);
+            for i in first..last {
+                let point = points_start + i * 16;
+                let (x, y) = (le_f64(point)?, le_f64(point + 8)?);
+                if ! x.is_finite() || ! y.is_finite() {
+                    return Err(invalid(format!("point {} of record at {} is not finite", i, record)));
+                }
+                ring.push((x, y));
+            }
+            if ring.first() != ring.last() {
+                let first = ring[0];
+                ring.push(first);
+            }
+            rings.push(ring);
+        }
+    }
+
+    Ok(rings)
+}
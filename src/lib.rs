@@ -1,4 +1,6 @@
+extern crate crc32fast;
 extern crate csv;
+extern crate flate2;
 extern crate osmpbfreader;
 extern crate plain;
 extern crate reqwest;
@@ -6,38 +8,29 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate zip;
+extern crate zstd;
 
-use std::io;
+pub use crate::error::{Error, Result};
 
+pub mod color_ramp;
+pub mod contour;
 pub mod coordinate;
 pub mod earth;
+mod error;
 pub mod gdl90;
+pub mod geotiff;
 pub mod hgt;
+pub mod mesh;
 pub mod osm;
 pub mod ourairports;
 pub mod perspective;
 pub mod position;
+pub mod profile;
 pub mod reference;
 pub mod screen;
 pub mod spheroid;
+pub mod taws;
 pub mod vector;
 pub mod viewport;
 pub mod unit;
 pub mod x_plane;
-
-fn reqwest_err(err: reqwest::Error) -> io::Error {
-    io::Error::new(
-        io::ErrorKind::Other,
-        err
-    )
-}
-
-fn zip_err(err: zip::result::ZipError) -> io::Error {
-    match err {
-        zip::result::ZipError::Io(io_err) => io_err,
-        _ => io::Error::new(
-            io::ErrorKind::Other,
-            err
-        )
-    }
-}
@@ -1,19 +1,24 @@
-use dashmap::DashMap;
+use friar::Error;
+use friar::color_ramp::ColorRamp;
+use friar::contour::Contour;
 use friar::coordinate::Coordinate;
 use friar::earth::Earth;
 use friar::gdl90::{Gdl90, Gdl90Kind};
-use friar::hgt::{HgtCache, HgtFile, HgtResolution};
+use friar::hgt::{HgtCache, HgtChunk, HgtFile, HgtInterpolation, HgtLoader, HgtLoaderEvent, HgtMosaic, HgtResolution, HgtSampler, HgtSrtm, HgtTileId, HgtVoidFill, HgtWaterMask};
+use friar::mesh::Rtin;
 use friar::osm::Osm;
 use friar::ourairports;
 use friar::position::Position;
+use friar::profile::Profile;
 use friar::reference::Reference;
 use friar::spheroid::Spheroid;
+use friar::taws::{Taws, TawsAlert, TawsLevel, TawsOwnship};
 use friar::x_plane::XPlane;
 use orbclient::{Color, EventOption, Renderer, Window, WindowFlag};
 use orbfont::{Font, Text};
 use rayon::prelude::*;
-use std::{cmp, mem, thread};
-use std::collections::HashMap;
+use std::{cmp, env, fs, mem, thread};
+use std::collections::{HashMap, HashSet};
 use std::fmt::{self, Write};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
@@ -773,14 +778,14 @@ fn oap_runway_triangles<'r, R: Spheroid>(runways: &[RunwayEnd<'r, R>], bounds: (
     }
 }
 
-fn hgt_intersect<'r, R: Spheroid>(file: &HgtFile, reference: &'r R, origin: &Coordinate<'r, R>, heading: f64, pitch: f64) -> Option<Coordinate<'r, R>> {
+fn hgt_intersect<'r, R: Spheroid>(file: &HgtFile, reference: &'r R, exaggeration: f64, origin: &Coordinate<'r, R>, heading: f64, pitch: f64) -> Option<Coordinate<'r, R>> {
     let mut a = origin.duplicate();
-    let mut a_h = file.interpolate(a.latitude, a.longitude)?;
+    let mut a_h = file.elevation_at(a.latitude, a.longitude, HgtInterpolation::Bilinear)? * exaggeration;
     let mut a_dh = a.elevation - a_h;
     //TODO: Consider parallelizing
     loop {
         let b = a.offset(1.0, heading, pitch);
-        let b_h = file.interpolate(b.latitude, b.longitude)?;
+        let b_h = file.elevation_at(b.latitude, b.longitude, HgtInterpolation::Bilinear)? * exaggeration;
         let b_dh = b.elevation - b_h;
 
         // Upon transition, return b
@@ -811,285 +816,475 @@ fn hgt_intersect<'r, R: Spheroid>(file: &HgtFile, reference: &'r R, origin: &Coo
 
 type HgtTriangle<'r, R> = (Position<'r, R>, Position<'r, R>, Position<'r, R>, (f32, f32, f32), (u8, u8, u8));
 
-type HgtTile<'r, R> = (HgtTriangle<'r, R>, HgtTriangle<'r, R>);
+/// Maximum number of cells along each side of a chunk mesh
+const HGT_CHUNK_CELLS: u16 = 32;
+
+/// Number of selections a chunk mesh is kept for after it was last used
+const HGT_CHUNK_KEEP: u64 = 64;
+
+/// Parameters for selecting the detail of HgtFileTiles
+struct HgtView<'a, 'r, R: Spheroid + 'r> {
+    viewer: &'a Position<'r, R>,
+    /// Pixels per meter of error at a distance of one meter
+    scale: f64,
+    /// Largest allowed error in pixels
+    max_error: f64,
+    /// Distance beyond which chunks are not drawn
+    horizon: f64,
+    /// Alerting and the altitude of the ownship in meters, to color terrain near or above it
+    taws: Option<(&'a Taws, f64)>,
+    caution_color: Color,
+    warning_color: Color,
+}
 
-struct HgtFileTiles<'r, R: Spheroid + 'r> {
-    file: HgtFile,
-    tiles_levels: Box<[Box<[Box<[Option<HgtTile<'r, R>>]>]>]>
+/// How terrain is lit
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum HgtShading {
+    /// Every surface at full brightness
+    Flat,
+    /// Darker as the surface gets steeper
+    Slope,
+    /// Lit by the sun from the northwest, 45 degrees above the horizon
+    Hillshade,
 }
 
-impl<'r, R: Spheroid + Sync> HgtFileTiles<'r, R> {
-    fn new(file: HgtFile, reference: &'r R, ground_color: Color, ocean_color: Color, levels: usize) -> Self {
-        let samples = file.resolution.samples();
+impl HgtShading {
+    fn next(self) -> Self {
+        match self {
+            HgtShading::Flat => HgtShading::Slope,
+            HgtShading::Slope => HgtShading::Hillshade,
+            HgtShading::Hillshade => HgtShading::Flat,
+        }
+    }
+}
 
-        let rgb = |low: f64, high: f64| -> (u8, u8, u8) {
-            let scale = 1.0; //(1.0 - (high - low).log2() * 0.125).max(0.125).min(1.0);
-            let color = if low.abs() < 1.0 && high.abs() < 1.0 {
-                ocean_color
-            } else {
-                ground_color
-            };
-            (
-                (color.r() as f64 * scale) as u8,
-                (color.g() as f64 * scale) as u8,
-                (color.b() as f64 * scale) as u8
-            )
-        };
+/// Appearance of terrain, which requires rebuilding meshes when changed
+#[derive(Clone, Debug, PartialEq)]
+struct HgtStyle {
+    ramp: ColorRamp,
+    shading: HgtShading,
+    /// Factor applied to elevations
+    exaggeration: f64,
+}
 
-        let (center_lat, center_lon) = file.coordinate(samples/2, samples/2).unwrap();
-        let center = reference.coordinate(center_lat, center_lon, 0.0);
-        let center_pos = center.position();
+/// What chunk meshes are built from
+struct HgtChunkSource<'a, 'r, R: Spheroid + 'r> {
+    file: &'a HgtFile,
+    reference: &'r R,
+    style: &'a HgtStyle,
+    water: Option<&'a HgtWaterMask>,
+    water_color: Color,
+    /// Largest error in meters allowed when simplifying chunk meshes
+    mesh_error: f64,
+}
 
-        let down = center.offset(1.0, 0.0, 270.0);
-        let down_pos = down.position();
-        let down_vec = center_pos.vector(&down_pos).normalize();
+/// A node of the quadtree of an HgtFile, with its mesh and children built when first needed
+struct HgtChunkNode<'r, R: Spheroid + 'r> {
+    chunk: HgtChunk,
+    /// Sphere containing the chunk
+    center: Position<'r, R>,
+    radius: f64,
+    /// Triangles, each with the highest real elevation of its corners
+    triangles: Option<Vec<(HgtTriangle<'r, R>, f64)>>,
+    children: Option<Box<[HgtChunkNode<'r, R>]>>,
+    last_used: u64,
+}
 
-        let intensity = |p1: &Position<'r, R>, p2: &Position<'r, R>, p3: &Position<'r, R>| -> f32 {
-            let v12 = p1.vector(&p2);
-            let v13 = p1.vector(&p3);
+impl<'r, R: Spheroid> HgtChunkNode<'r, R> {
+    fn new(file: &HgtFile, reference: &'r R, exaggeration: f64, chunk: HgtChunk) -> Self {
+        let (low, high) = (chunk.low * exaggeration, chunk.high * exaggeration);
 
-            let dot = v12.cross(&v13).normalize().dot(&down_vec);
-            dot.powi(10) as f32
-        };
+        let res = file.resolution.degrees();
+        let coordinate = |row: u16, col: u16, h: f64| reference.coordinate(
+            file.latitude + (row as f64) * res,
+            file.longitude + (col as f64) * res,
+            h
+        );
 
-        let intensity_intense = |p1: &Position<'r, R>, p2: &Position<'r, R>, p3: &Position<'r, R>, c4_opt: &Option<Coordinate<'r, R>>, c5_opt: &Option<Coordinate<'r, R>>| -> f32 {
-            let mut int = intensity(p1, p2, p3);
-            let mut count = 1.0;
+        let center = coordinate((chunk.row0 + chunk.row1) / 2, (chunk.col0 + chunk.col1) / 2, (low + high) / 2.0).position();
+        let mut radius: f64 = 0.0;
+        for &(row, col) in [(chunk.row0, chunk.col0), (chunk.row0, chunk.col1), (chunk.row1, chunk.col0), (chunk.row1, chunk.col1)].iter() {
+            for &h in [low, high].iter() {
+                let corner = coordinate(row, col, h).position();
+                radius = radius.max(center.vector(&corner).norm());
+            }
+        }
 
-            if let Some(c4) = c4_opt {
-                let p4 = c4.position();
-                int += intensity(p1, p3, &p4);
-                count += 1.0;
+        Self {
+            chunk,
+            center,
+            radius,
+            triangles: None,
+            children: None,
+            last_used: 0,
+        }
+    }
 
-                if let Some(c5) = c5_opt {
-                    let p5 = c5.position();
-                    int += intensity(p1, &p4, &p5);
-                    count += 1.0;
+    /// Build the mesh of this chunk, simplified where it is flat, with skirts hanging from its
+    /// edges to hide cracks between chunks of different detail
+    fn mesh(&self, source: &HgtChunkSource<'_, 'r, R>) -> Vec<(HgtTriangle<'r, R>, f64)> {
+        let (file, reference, style) = (source.file, source.reference, source.style);
+        let (rows, cols) = self.chunk.indexes();
+        let res = file.resolution.degrees();
+
+        // Direction of the light as local (east, north, up)
+        let light = match style.shading {
+            HgtShading::Hillshade => {
+                let (azimuth, altitude) = (315.0f64.to_radians(), 45.0f64.to_radians());
+                (altitude.cos() * azimuth.sin(), altitude.cos() * azimuth.cos(), altitude.sin())
+            },
+            _ => (0.0, 0.0, 1.0),
+        };
+
+        let vertices: Vec<Option<(f64, Position<'r, R>)>> = rows.iter().flat_map(|&row| {
+            cols.iter().map(move |&col| (row, col))
+        }).map(|(row, col)| {
+            let h = file.sample(row, col)? as f64;
+            let coordinate = reference.coordinate(
+                file.latitude + (row as f64) * res,
+                file.longitude + (col as f64) * res,
+                h * style.exaggeration
+            );
+            Some((h, coordinate.position()))
+        }).collect();
+        let vertex = |i: usize, j: usize| vertices[i * cols.len() + j].as_ref();
+
+        // Water if every corner is water in the mask, or without a mask, at sea level. Otherwise
+        // colored by the real elevations of the corners.
+        let rgb = |corners: &[(usize, usize)]| -> (u8, u8, u8) {
+            let water = match source.water {
+                Some(water) => corners.iter().all(|&(i, j)| water.is_water(rows[i], cols[j])),
+                None => corners.iter().all(|&(i, j)| vertex(i, j).is_some_and(|v| v.0.abs() < 1.0)),
+            };
+            if water {
+                let color = source.water_color;
+                (color.r(), color.g(), color.b())
+            } else {
+                let heights: Vec<f64> = corners.iter().filter_map(|&(i, j)| vertex(i, j)).map(|v| v.0).collect();
+                let (r, g, b) = style.ramp.color(heights.iter().sum::<f64>() / heights.len().max(1) as f64);
+                // Terrain made up by filling voids is faded toward gray
+                if corners.iter().any(|&(i, j)| file.is_filled(rows[i], cols[j])) {
+                    let fade = |c: u8| ((c as u16 + 0x80) / 2) as u8;
+                    (fade(r), fade(g), fade(b))
+                } else {
+                    (r, g, b)
                 }
             }
+        };
 
-            if let Some(c5) = c5_opt {
-                let p5 = c5.position();
-                int += intensity(p1, &p5, p2);
-                count += 1.0;
+        // Shade each vertex by how much its normal faces the light. Normals come from the samples
+        // around each vertex in the file, so they match along the edges of chunks. On the edges
+        // of the file, the normal of the next sample in is used.
+        let last_sample = file.resolution.samples() - 2;
+        let intensities: Vec<f32> = rows.iter().flat_map(|&row| cols.iter().map(move |&col| (row, col))).map(|(row, col)| {
+            if style.shading == HgtShading::Flat {
+                return 1.0;
+            }
+            let normal_opt = file.normal(
+                file.latitude + (row.clamp(1, last_sample) as f64) * res,
+                file.longitude + (col.clamp(1, last_sample) as f64) * res,
+                HgtInterpolation::Bilinear
+            );
+            match normal_opt {
+                Some((e, n, u)) => {
+                    let k = style.exaggeration;
+                    let norm = ((e * k).powi(2) + (n * k).powi(2) + u.powi(2)).sqrt();
+                    let dot = (e * k * light.0 + n * k * light.1 + u * light.2) / norm;
+                    match style.shading {
+                        HgtShading::Slope => dot.max(0.0).powi(10) as f32,
+                        _ => dot.max(0.0) as f32,
+                    }
+                },
+                None => 1.0,
             }
+        }).collect();
+        let intensity = |i: usize, j: usize| intensities[i * cols.len() + j];
 
-            int/count
+        let rtin = match Rtin::new(rows.len(), |i, j| vertex(i, j).map(|v| v.0)) {
+            Ok(rtin) => rtin,
+            Err(err) => {
+                println!("Failed to simplify chunk: {}", err);
+                return Vec::new();
+            }
         };
 
-        let mut tiles_levels = Vec::with_capacity(levels);
-        for level in 0..levels {
-            let cell_map = |prev_row: u16, prev_col: u16, row: u16, col: u16| -> Option<HgtTile<'r, R>> {
-                let coord = |row, col| -> Option<Coordinate<'r, R>> {
-                    let h = file.get(row, col)?;
-                    let (lat, lon) = file.coordinate(row, col)?;
-                    Some(reference.coordinate(lat, lon, h as f64))
-                };
-
-                //   au bu
-                // al a b br
-                // cl c d dr
-                //   cd dd
+        // Skirts are deep enough to cover the error of neighbouring chunks
+        let last = rows.len() - 1;
+        let depth = (2.0 * (self.chunk.error + source.mesh_error) * style.exaggeration).max(10.0);
+
+        let mut triangles = Vec::new();
+        for corners in rtin.triangles(source.mesh_error) {
+            let [a, b, c] = corners;
+            // Repeated samples in small chunks leave triangles without area
+            let sample = |(i, j): (usize, usize)| (rows[i], cols[j]);
+            if sample(a) == sample(b) || sample(b) == sample(c) || sample(c) == sample(a) {
+                continue;
+            }
+            let (va, vb, vc) = match (vertex(a.0, a.1), vertex(b.0, b.1), vertex(c.0, c.1)) {
+                (Some(va), Some(vb), Some(vc)) => (va, vb, vc),
+                _ => continue,
+            };
 
-                let a = coord(prev_row, prev_col)?;
-                let b = coord(prev_row, col)?;
-                let c = coord(row, prev_col)?;
-                let d = coord(row, col)?;
+            triangles.push(((
+                va.1.duplicate(),
+                vb.1.duplicate(),
+                vc.1.duplicate(),
+                (intensity(a.0, a.1), intensity(b.0, b.1), intensity(c.0, c.1)),
+                rgb(&[a, b, c]),
+            ), va.0.max(vb.0).max(vc.0)));
+
+            // Skirts along the edges of triangles on the perimeter
+            for &(p, q) in [(a, b), (b, c), (c, a)].iter() {
+                let on_edge = (p.0 == q.0 && (p.0 == 0 || p.0 == last)) ||
+                    (p.1 == q.1 && (p.1 == 0 || p.1 == last));
+                if ! on_edge || sample(p) == sample(q) {
+                    continue;
+                }
 
-                let (au, bu) = if prev_row > 1 {
-                    (coord(prev_row - 1, prev_col), coord(prev_row - 1, col))
-                } else {
-                    (None, None)
+                let (top0, top1) = match (vertex(p.0, p.1), vertex(q.0, q.1)) {
+                    (Some(top0), Some(top1)) => (top0, top1),
+                    _ => continue,
                 };
+                let bottom = |(i, j): (usize, usize), h: f64| reference.coordinate(
+                    file.latitude + (rows[i] as f64) * res,
+                    file.longitude + (cols[j] as f64) * res,
+                    h * style.exaggeration - depth
+                ).position();
+                let (bottom0, bottom1) = (bottom(p, top0.0), bottom(q, top1.0));
+                let (int0, int1) = (intensity(p.0, p.1), intensity(q.0, q.1));
+                let color = rgb(&[p, q]);
+
+                let high = top0.0.max(top1.0);
+                triangles.push(((top0.1.duplicate(), top1.1.duplicate(), bottom1.duplicate(), (int0, int1, int1), color), high));
+                triangles.push(((top0.1.duplicate(), bottom1, bottom0, (int0, int1, int0), color), high));
+            }
+        }
 
-                let (al, cl) = if prev_col > 1 {
-                    (coord(prev_row, prev_col - 1), coord(row, prev_col - 1))
-                } else {
-                    (None, None)
-                };
+        triangles
+    }
 
-                let (cd, dd) = if row < samples - 1 {
-                    (coord(row + 1, prev_col), coord(row + 1, col))
-                } else {
-                    (None, None)
-                };
+    /// Add the triangles of the chunks with enough detail for the view
+    fn select(&mut self, source: &HgtChunkSource<'_, 'r, R>, view: &HgtView<'_, 'r, R>, frame: u64, triangles: &mut Vec<HgtTriangle<'r, R>>) {
+        self.last_used = frame;
 
-                let (br, dr) = if col < samples - 1 {
-                    (coord(prev_row, col + 1), coord(row, col + 1))
-                } else {
-                    (None, None)
+        let distance = (view.viewer.vector(&self.center).norm() - self.radius).max(1.0);
+        if distance > view.horizon {
+            return;
+        }
+
+        // Error of this chunk in pixels, including simplification of its mesh
+        let screen_error = self.chunk.screen_error(source.mesh_error, source.style.exaggeration, view.scale, distance);
+        if self.chunk.split(screen_error, view.max_error) {
+            let (file, reference, exaggeration, chunk) = (source.file, source.reference, source.style.exaggeration, self.chunk);
+            let children = self.children.get_or_insert_with(|| {
+                chunk.children(file).iter().map(|&child| {
+                    HgtChunkNode::new(file, reference, exaggeration, child)
+                }).collect()
+            });
+            for child in children.iter_mut() {
+                child.select(source, view, frame, triangles);
+            }
+        } else {
+            if self.triangles.is_none() {
+                self.triangles = Some(self.mesh(source));
+            }
+            let chunk_triangles = self.triangles.as_ref().unwrap();
+            for (triangle, high) in chunk_triangles.iter() {
+                // Terrain near or above the ownship is colored by TAWS
+                let color = match view.taws.map(|(taws, altitude)| taws.terrain_level(altitude, *high)) {
+                    Some(TawsLevel::Warning) => (view.warning_color.r(), view.warning_color.g(), view.warning_color.b()),
+                    Some(TawsLevel::Caution) => (view.caution_color.r(), view.caution_color.g(), view.caution_color.b()),
+                    _ => triangle.4,
                 };
+                triangles.push((
+                    triangle.0.duplicate(),
+                    triangle.1.duplicate(),
+                    triangle.2.duplicate(),
+                    triangle.3,
+                    color
+                ));
+            }
+        }
+    }
 
-                let a_pos = a.position();
-                let b_pos = b.position();
-                let c_pos = c.position();
-                let d_pos = d.position();
+    /// Drop the meshes of this chunk and its children, keeping the quadtree. Bounding spheres are
+    /// moved when the exaggeration changes.
+    fn clear(&mut self, file: &HgtFile, reference: &'r R, exaggeration_opt: Option<f64>) {
+        if let Some(exaggeration) = exaggeration_opt {
+            let node = Self::new(file, reference, exaggeration, self.chunk);
+            self.center = node.center;
+            self.radius = node.radius;
+        }
+        self.triangles = None;
+        if let Some(ref mut children) = self.children {
+            for child in children.iter_mut() {
+                child.clear(file, reference, exaggeration_opt);
+            }
+        }
+    }
 
-                //   au bu
-                // al a b br
-                // cl c d dr
-                //   cd dd
+    /// Drop meshes and children that have not been used recently
+    fn prune(&mut self, frame: u64) {
+        if self.last_used + HGT_CHUNK_KEEP < frame {
+            self.triangles = None;
+            self.children = None;
+        } else if let Some(ref mut children) = self.children {
+            for child in children.iter_mut() {
+                child.prune(frame);
+            }
+        }
+    }
+}
 
-                let a_int = intensity_intense(&a_pos, &c_pos, &b_pos, &au, &al);
-                let b_int = intensity_intense(&b_pos, &a_pos, &d_pos, &br, &bu);
-                let c_int = intensity_intense(&c_pos, &d_pos, &a_pos, &cl, &cd);
-                let d_int = intensity_intense(&d_pos, &b_pos, &c_pos, &dd, &dr);
+/// A quadtree of chunks of an HgtFile, each with a mesh built when it is first drawn. The file
+/// itself is kept in an HgtMosaic and passed to each method.
+struct HgtFileTiles<'r, R: Spheroid + 'r> {
+    reference: &'r R,
+    style: HgtStyle,
+    water: Option<HgtWaterMask>,
+    water_color: Color,
+    mesh_error: f64,
+    root: HgtChunkNode<'r, R>,
+    contour_interval: f64,
+    /// Contour lines, with positions raised slightly above the terrain, generated when first used
+    contours: Option<Vec<(Contour<'r, R>, Vec<Position<'r, R>>)>>,
+}
 
-                let abc = {
-                    let low = a.elevation.min(b.elevation).min(c.elevation);
-                    let high = a.elevation.max(b.elevation).max(c.elevation);
+impl<'r, R: Spheroid> HgtFileTiles<'r, R> {
+    fn new(file: &HgtFile, water: Option<HgtWaterMask>, reference: &'r R, style: HgtStyle, water_color: Color, mesh_error: f64, contour_interval: f64) -> Self {
+        let root = Self::root(file, reference, style.exaggeration);
 
-                    (
-                        a_pos,
-                        b_pos.duplicate(),
-                        c_pos.duplicate(),
-                        (
-                            a_int,
-                            b_int,
-                            c_int,
-                        ),
-                        rgb(low, high),
-                    )
-                };
+        Self {
+            reference,
+            style,
+            water,
+            water_color,
+            mesh_error,
+            root,
+            contour_interval,
+            contours: None,
+        }
+    }
 
-                let bcd = {
-                    let low = b.elevation.min(c.elevation).min(d.elevation);
-                    let high = b.elevation.max(c.elevation).max(d.elevation);
-
-                    (
-                        b_pos,
-                        c_pos,
-                        d_pos,
-                        (
-                            b_int,
-                            c_int,
-                            d_int,
-                        ),
-                        rgb(low, high),
-                    )
-                };
+    fn root(file: &HgtFile, reference: &'r R, exaggeration: f64) -> HgtChunkNode<'r, R> {
+        HgtChunkNode::new(file, reference, exaggeration, HgtChunk::root(file, HGT_CHUNK_CELLS))
+    }
 
-                Some((abc, bcd))
-            };
+    /// Contour lines of the file, generating them if needed
+    fn contours(&mut self, file: &HgtFile) -> &[(Contour<'r, R>, Vec<Position<'r, R>>)] {
+        let Self { reference, style, contour_interval, contours, .. } = self;
+        contours.get_or_insert_with(|| {
+            Contour::generate(
+                file,
+                *reference,
+                (file.latitude, file.longitude, file.latitude + 1.0, file.longitude + 1.0),
+                *contour_interval
+            ).into_iter().map(|contour| {
+                let positions = Self::contour_positions(&contour, reference, style.exaggeration);
+                (contour, positions)
+            }).collect()
+        })
+    }
 
-            let level_factor = 2u16.pow(level as u32);
-            let mut tiles = Vec::with_capacity(samples as usize);
-            let mut row = 1 + level_factor;
-            while row < samples {
-                let mut tiles_row = Vec::with_capacity(samples as usize);
-                let mut col = 1 + level_factor;
-                while col < samples {
-                    tiles_row.push(cell_map(row - level_factor, col - level_factor, row, col));
-                    col += level_factor;
-                }
-                tiles.push(tiles_row.into_boxed_slice());
-                row += level_factor;
+    fn contour_positions(contour: &Contour<'r, R>, reference: &'r R, exaggeration: f64) -> Vec<Position<'r, R>> {
+        contour.points.iter().map(|point| {
+            reference.coordinate(point.latitude, point.longitude, point.elevation * exaggeration + 2.0).position()
+        }).collect()
+    }
+
+    /// Change the appearance of the terrain, dropping all meshes
+    fn set_style(&mut self, file: &HgtFile, style: HgtStyle) {
+        let exaggeration_opt = if style.exaggeration != self.style.exaggeration {
+            for (contour, positions) in self.contours.iter_mut().flatten() {
+                *positions = Self::contour_positions(contour, self.reference, style.exaggeration);
             }
-            // println!("level {} factor {} tiles {}", level, level_factor, tiles.len());
-            tiles_levels.push(tiles.into_boxed_slice());
-        }
+            Some(style.exaggeration)
+        } else {
+            None
+        };
+        self.root.clear(file, self.reference, exaggeration_opt);
+        self.style = style;
+    }
 
-        Self {
+    /// Add triangles with enough detail for the view, dropping meshes that are no longer used
+    fn triangles(&mut self, file: &HgtFile, view: &HgtView<'_, 'r, R>, frame: u64, triangles: &mut Vec<HgtTriangle<'r, R>>) {
+        let source = HgtChunkSource {
             file,
-            tiles_levels: tiles_levels.into_boxed_slice(),
-        }
+            reference: self.reference,
+            style: &self.style,
+            water: self.water.as_ref(),
+            water_color: self.water_color,
+            mesh_error: self.mesh_error,
+        };
+        self.root.select(&source, view, frame, triangles);
+        self.root.prune(frame);
     }
+}
 
-    fn triangles(&self, bounds_levels: &[(f64, f64, f64, f64)], triangles: &mut Vec<HgtTriangle<'r, R>>) {
-        let samples = self.file.resolution.samples();
-
-        let min = self.file.coordinate(1, 1).unwrap();
-        let max = self.file.coordinate(samples - 1, samples - 1).unwrap();
+/// Seconds before a tile that failed to load is requested again, unless it does not exist
+const HGT_RETRY_SECS: u64 = 30;
 
-        let mut prev_opt: Option<(u16, u16, u16, u16)> = None;
-        for (level, bounds) in bounds_levels.iter().enumerate() {
-            if let Some(tiles) = self.tiles_levels.get(level) {
-                let level_factor = 2u16.pow(level as u32);
+/// Height in pixels of the vertical situation display at the bottom of the window
+const VSD_PANEL_HEIGHT: i32 = 160;
 
-                let start = self.file.position(bounds.0.min(max.0).max(min.0), bounds.1.min(max.1).max(min.1)).unwrap();
-                let end = self.file.position(bounds.2.min(max.0).max(min.0), bounds.3.min(max.1).max(min.1)).unwrap();
+fn hgt_nearby_files<T: Send + 'static>(loader: &HgtLoader<(HgtFile, T)>, latitude: f64, longitude: f64, hgt_files: &HashMap<HgtTileId, T>, hgt_failed: &HashMap<HgtTileId, (String, Instant)>, hgt_missing: &HashSet<HgtTileId>) {
+    let nearby = HgtTileId::covering(latitude - 1.0, longitude - 1.0, latitude + 1.0, longitude + 1.0);
 
-                let start_row = cmp::max(1, start.0 / level_factor);
-                let start_col = cmp::max(1, start.1 / level_factor);
-                let end_row = cmp::min(tiles.len() as u16 - 1, end.0 / level_factor);
-                let end_col = cmp::min(tiles[0].len() as u16 - 1, end.1 / level_factor);
+    loader.set_viewer(latitude, longitude);
 
-                if end_row > start_row && end_col > start_col {
-                    let mut push = |triangle: &HgtTriangle<'r, R>| {
-                        triangles.push((
-                            triangle.0.duplicate(),
-                            triangle.1.duplicate(),
-                            triangle.2.duplicate(),
-                            triangle.3,
-                            triangle.4
-                        ));
-                    };
-
-                    for row in start_row..end_row + 1 {
-                        if let Some(tiles_row) = tiles.get(row as usize) {
-                            for col in start_col..end_col + 1 {
-                                if let Some(prev) = prev_opt {
-                                    // Skip if area was previously handled
-                                    if row * level_factor > prev.0
-                                    && row * level_factor < prev.2
-                                    && col * level_factor > prev.1
-                                    && col * level_factor < prev.3
-                                    { continue; }
-                                }
-                                if let Some(tile_opt) = tiles_row.get(col as usize) {
-                                    if let Some(tile) = tile_opt {
-                                        push(&tile.0);
-                                        push(&tile.1);
-                                    }
-                                } else {
-                                    break;
-                                }
-                            }
-                        } else {
-                            break;
-                        }
-                    }
-                }
+    // Tiles that are no longer nearby do not need to be loaded
+    loader.retain(|tile| nearby.contains(&tile));
 
-                prev_opt = Some((
-                    start_row * level_factor,
-                    start_col * level_factor,
-                    end_row * level_factor,
-                    end_col * level_factor
-                ));
-            }
+    for tile in nearby.iter() {
+        if ! hgt_files.contains_key(tile) && ! hgt_failed.contains_key(tile) && ! hgt_missing.contains(tile) {
+            loader.request(*tile);
         }
     }
 }
 
-fn hgt_nearby_files<R: Spheroid + Send + Sync>(cache: &Arc<HgtCache>, latitude: f64, longitude: f64, res: HgtResolution, reference: &'static R, ground_color: Color, ocean_color: Color, levels: usize, hgt_files: &Arc<DashMap<(i16, i16), HgtFileTiles<'static, R>>>) {
-    let f_center = latitude.floor() as i16;
-    let l_center = longitude.floor() as i16;
-    for f in f_center - 1 ..= f_center + 1 {
-        for l in l_center - 1 ..= l_center + 1 {
-            let index = (f, l);
-            if ! hgt_files.contains_key(&index) {
-                //TODO: prevent reloading
-                println!("loading {:?}", index);
-
-                let cache = cache.clone();
-                let hgt_files = hgt_files.clone();
-                thread::spawn(move || {
-                    let hgt_file = HgtFileTiles::new(
-                        cache.get(f as f64, l as f64, res).unwrap(),
-                        reference,
-                        ground_color,
-                        ocean_color,
-                        levels
-                    );
-                    hgt_files.insert(index, hgt_file);
-                    println!("loaded {:?}", index);
-                });
+/// Handle files that finished loading, adding them to the mosaic, and return true if there were any
+///
+/// Tiles that do not exist, such as over the ocean, or that cannot be used are missing and never
+/// requested again. Other failures may be transient, and are retried later.
+fn hgt_loader_events<T: Send + 'static>(loader: &HgtLoader<(HgtFile, T)>, hgt_mosaic: &mut HgtMosaic, hgt_files: &mut HashMap<HgtTileId, T>, hgt_failed: &mut HashMap<HgtTileId, (String, Instant)>, hgt_missing: &mut HashSet<HgtTileId>) -> bool {
+    let mut changed = false;
+    while let Some(event) = loader.poll() {
+        match event {
+            HgtLoaderEvent::Loaded(tile, (file, tiles)) => match hgt_mosaic.insert(file) {
+                Ok(_) => {
+                    println!("loaded {}", tile);
+                    hgt_files.insert(tile, tiles);
+                },
+                Err(err) => {
+                    println!("failed to add {}: {}", tile, err);
+                    hgt_missing.insert(tile);
+                }
+            },
+            HgtLoaderEvent::Failed(tile, err) => {
+                println!("failed to load {}: {}", tile, err);
+                match err {
+                    Error::NotFound(_) => {
+                        hgt_missing.insert(tile);
+                    },
+                    _ => {
+                        let retry = Instant::now() + Duration::from_secs(HGT_RETRY_SECS);
+                        hgt_failed.insert(tile, (format!("{}", err), retry));
+                    }
+                }
             }
         }
+        changed = true;
     }
+    changed
 }
 
+/// Forget failures that are due to be retried, returning true if there were any
+fn hgt_retry_failed(hgt_failed: &mut HashMap<HgtTileId, (String, Instant)>) -> bool {
+    let now = Instant::now();
+    let count = hgt_failed.len();
+    hgt_failed.retain(|_, (_, retry)| *retry > now);
+    hgt_failed.len() != count
+}
 
 fn main() {
     let mut w = Window::new_flags(-1, -1, 1024, 768, "FRIAR", &[WindowFlag::Async, WindowFlag::Resizable]).unwrap();
@@ -1098,6 +1293,10 @@ fn main() {
     let sky_color = Color::rgb(0x00, 0xBF, 0xFF);
     let ocean_color = Color::rgb(0x1C, 0x6B, 0xA0);
     let ground_color = Color::rgb(0x7A, 0x79, 0x4C);
+    let caution_color = Color::rgb(0xFF, 0xD7, 0x00);
+    let warning_color = Color::rgb(0xFF, 0x20, 0x20);
+    let contour_color = Color::rgba(0x5A, 0x3A, 0x1A, 0x9F);
+    let contour_index_color = Color::rgba(0x5A, 0x3A, 0x1A, 0xFF);
 
     let hud_font = Font::from_path("res/fonts/RobotoMono/RobotoMono-Regular.ttf").unwrap();
     let mut hud_cache = FontCache::new(&hud_font, 24.0);
@@ -1154,13 +1353,93 @@ fn main() {
         (HgtResolution::Three, [4_000.0, 16_000.0, 64_000.0])
     };
 
-    let hgt_cache = Arc::new(HgtCache::new("cache"));
+    let hgt_cache = {
+        let mut hgt_srtm = match env::var("FRIAR_SRTM_SOURCES") {
+            Ok(sources) => HgtSrtm::new(sources.split(';').map(|source| source.to_string()).collect()),
+            Err(_) => HgtSrtm::default(),
+        };
+        hgt_srtm.set_offline(env::var_os("FRIAR_OFFLINE").is_some());
+
+        let mut hgt_cache = HgtCache::new("cache");
+        hgt_cache.set_srtm(hgt_srtm);
+        hgt_cache.set_void_fill(Some(HgtVoidFill {
+            max_distance: 64,
+            fallback: Some(match hgt_res {
+                HgtResolution::One => HgtResolution::Three,
+                HgtResolution::Three => HgtResolution::One,
+            }),
+        }));
+        Arc::new(hgt_cache)
+    };
+
+    let mesh_error = env::var("FRIAR_MESH_ERROR").ok()
+        .and_then(|error| error.parse::<f64>().ok())
+        .unwrap_or(5.0);
+
+    let contour_interval = env::var("FRIAR_CONTOUR_INTERVAL").ok()
+        .and_then(|interval| interval.parse::<f64>().ok())
+        .unwrap_or(100.0);
+
+    // Built-in color ramps, followed by gradient files
+    let mut hgt_ramps = vec![ColorRamp::solid("ground", (ground_color.r(), ground_color.g(), ground_color.b()))];
+    hgt_ramps.extend(ColorRamp::builtin());
+    if let Ok(paths) = env::var("FRIAR_COLOR_RAMPS") {
+        for path in paths.split(';') {
+            match ColorRamp::open(path) {
+                Ok(ramp) => hgt_ramps.push(ramp),
+                Err(err) => println!("Failed to read color ramp {}: {}", path, err),
+            }
+        }
+    }
+    let mut hgt_ramp_index = 0;
+
+    let mut hgt_style = HgtStyle {
+        ramp: hgt_ramps[hgt_ramp_index].clone(),
+        shading: HgtShading::Slope,
+        exaggeration: env::var("FRIAR_EXAGGERATION").ok()
+            .and_then(|exaggeration| exaggeration.parse::<f64>().ok())
+            .unwrap_or(1.0),
+    };
+
+    // Directory of SWBD shapefiles or GeoTIFF water masks
+    let water_dir = env::var_os("FRIAR_WATER");
+
+    let hgt_loader = {
+        let hgt_style = hgt_style.clone();
+        HgtLoader::new(hgt_cache, hgt_res, 4, move |mut file| {
+            // Water is leveled before contours and meshes are built
+            let water = water_dir.as_ref().and_then(|dir| {
+                match HgtWaterMask::find(file.tile(), file.resolution, dir) {
+                    Ok(water) => water,
+                    Err(err) => {
+                        println!("Failed to read water mask of {}: {}", file.tile(), err);
+                        None
+                    }
+                }
+            });
+            if let Some(ref water) = water {
+                if let Err(err) = water.flatten(&mut file) {
+                    println!("Failed to flatten water of {}: {}", file.tile(), err);
+                }
+            }
+            let tiles = HgtFileTiles::new(&file, water, &earth, hgt_style.clone(), ocean_color, mesh_error, contour_interval);
+            (file, tiles)
+        })
+    };
+    let mut hgt_frame = 0;
+
+    // Loaded files, sampled as one grid, and the meshes of each
+    let mut hgt_mosaic = HgtMosaic::new(hgt_res);
+    let mut hgt_files: HashMap<HgtTileId, HgtFileTiles<Earth>> = HashMap::new();
+    let mut hgt_failed: HashMap<HgtTileId, (String, Instant)> = HashMap::new();
+    let mut hgt_missing: HashSet<HgtTileId> = HashSet::new();
 
-    let hgt_files: Arc<DashMap<(i16, i16), HgtFileTiles<Earth>>> = Arc::new(DashMap::new());
+    hgt_nearby_files(&hgt_loader, center_lat, center_lon, &hgt_files, &hgt_failed, &hgt_missing);
 
-    hgt_nearby_files(&hgt_cache, center_lat, center_lon, hgt_res, &earth, ground_color, ocean_color, hgt_horizons.len(), &hgt_files);
+    let center_tile = HgtTileId::from_degrees(center_lat, center_lon);
+    while ! hgt_files.contains_key(&center_tile) && ! hgt_failed.contains_key(&center_tile) && ! hgt_missing.contains(&center_tile) {
+        hgt_loader_events(&hgt_loader, &mut hgt_mosaic, &mut hgt_files, &mut hgt_failed, &mut hgt_missing);
 
-    while hgt_files.get(&(center_lat.floor() as i16, center_lon.floor() as i16)).is_none() {
         let mut found_event = true;
         while found_event {
             found_event = false;
@@ -1185,9 +1464,9 @@ fn main() {
         thread::sleep(Duration::from_millis(10));
     }
 
-    let ground = if let Some(hgt_file) = hgt_files.get(&(center_lat.floor() as i16, center_lon.floor() as i16)) {
-        if let Some((row, col)) = hgt_file.file.position(center_lat, center_lon) {
-            hgt_file.file.get(row, col).unwrap_or(0) as f64
+    let ground = if let Some(hgt_file) = hgt_mosaic.get(center_tile) {
+        if let Some((row, col)) = hgt_file.position(center_lat, center_lon) {
+            hgt_file.get(row, col).ok().flatten().unwrap_or(0) as f64
         } else {
             0.0f64
         }
@@ -1238,6 +1517,16 @@ fn main() {
     let mut traffics = HashMap::new();
     let mut traffic_triangles = Vec::new();
 
+    // Ownship from X-Plane or GDL90, used for terrain alerting
+    let taws = Taws::new();
+    let mut taws_enabled = true;
+    let mut taws_ownship: Option<TawsOwnship> = None;
+    let mut taws_alert = TawsAlert::clear();
+
+    // Vertical situation display of terrain ahead
+    let mut vsd = true;
+    let mut vsd_profile: Option<Profile<Earth>> = None;
+
     let mut intersect_heading = 0.0;
     let mut intersect_pitch = 0.0;
     let mut intersect_opt = None;
@@ -1281,6 +1570,7 @@ fn main() {
     let mut redraw_times = 2;
     let mut fill = true;
     let mut hud = true;
+    let mut contours = false;
     let mut z_buffer = vec![0.0; (w.width() * w.height()) as usize];
     let mut row_mutexes = Vec::with_capacity(w.height() as usize);
     for _ in 0..w.height() {
@@ -1386,6 +1676,56 @@ fn main() {
                             hud = !hud;
                             redraw = true;
                         },
+                        orbclient::K_T if key_event.pressed => {
+                            contours = !contours;
+                            redraw = true;
+                        },
+                        orbclient::K_M if key_event.pressed => {
+                            hgt_ramp_index = (hgt_ramp_index + 1) % hgt_ramps.len();
+                            hgt_style.ramp = hgt_ramps[hgt_ramp_index].clone();
+                            println!("Color ramp: {}", hgt_style.ramp.name);
+                            rehgt = true;
+                        },
+                        orbclient::K_N if key_event.pressed => {
+                            hgt_style.shading = hgt_style.shading.next();
+                            println!("Shading: {:?}", hgt_style.shading);
+                            rehgt = true;
+                        },
+                        orbclient::K_V if key_event.pressed => {
+                            // Shift reduces exaggeration
+                            hgt_style.exaggeration = if shift {
+                                (hgt_style.exaggeration - 0.5).max(1.0)
+                            } else {
+                                (hgt_style.exaggeration + 0.5).min(10.0)
+                            };
+                            println!("Exaggeration: {}", hgt_style.exaggeration);
+                            rehgt = true;
+                        },
+                        orbclient::K_Y if key_event.pressed => {
+                            taws_enabled = !taws_enabled;
+                            rehgt = true;
+                        },
+                        orbclient::K_1 if key_event.pressed => {
+                            vsd = !vsd;
+                            rehgt = true;
+                        },
+                        orbclient::K_G if key_event.pressed => {
+                            // Export contours of the current tile
+                            let viewer_tile = HgtTileId::from_degrees(viewer.latitude, viewer.longitude);
+                            if let (Some(file), Some(hgt_file)) = (hgt_mosaic.get(viewer_tile), hgt_files.get_mut(&viewer_tile)) {
+                                let lines: Vec<Contour<Earth>> = hgt_file.contours(file).iter().map(|(contour, _)| Contour {
+                                    elevation: contour.elevation,
+                                    points: contour.points.iter().map(|point| point.duplicate()).collect(),
+                                    closed: contour.closed,
+                                }).collect();
+                                let mut geojson = Vec::new();
+                                let path = "contours.geojson";
+                                match Contour::write_geojson(&lines, &mut geojson).and_then(|()| fs::write(path, geojson)) {
+                                    Ok(()) => println!("wrote {} contours to {}", lines.len(), path),
+                                    Err(err) => println!("failed to write {}: {}", path, err),
+                                }
+                            }
+                        },
 
                         _ => (),
                     },
@@ -1478,12 +1818,12 @@ fn main() {
 
             if zoom_in {
                 fov = (fov - speed_zoom).max(1.0);
-                redraw = true;
+                rehgt = true;
             }
 
             if zoom_out {
                 fov = (fov + speed_zoom).min(180.0);
-                redraw = true;
+                rehgt = true;
             }
 
             if boresight_intersect {
@@ -1551,9 +1891,11 @@ fn main() {
                             traffic.heading()
                         );
 
+                        taws_ownship = Some(TawsOwnship::from_gdl90(&traffic, traffic_ownship_alt));
                         traffics.insert(traffic.id(), (traffic, traffic_ownship_alt));
 
                         retraffic = true;
+                        rehgt = true;
                     },
                     Gdl90Kind::GeoAltitude(altitude) => {
                         traffic_ownship_alt = Some(altitude.altitude());
@@ -1572,6 +1914,13 @@ fn main() {
 
                         retraffic = true;
                     },
+                    Gdl90Kind::BasicReport(report) | Gdl90Kind::LongReport(report) => {
+                        if let Some(traffic) = report.traffic() {
+                            traffics.insert(traffic.id(), (traffic, None));
+
+                            retraffic = true;
+                        }
+                    },
                     _ => ()
                 }
             }
@@ -1592,6 +1941,8 @@ fn main() {
                 pitch = position.pitch as f64;
                 roll = -position.roll as f64;
 
+                taws_ownship = Some(TawsOwnship::from_x_plane(&position));
+
                 rehgt = true;
             }
         }
@@ -1701,8 +2052,8 @@ fn main() {
 
             reintersect = false;
 
-            intersect_opt = if let Some(hgt_file) = hgt_files.get(&(viewer.latitude.floor() as i16, viewer.longitude.floor() as i16)) {
-                hgt_intersect(&hgt_file.file, &earth, &viewer, intersect_heading, intersect_pitch)
+            intersect_opt = if let Some(hgt_file) = hgt_mosaic.get(HgtTileId::from_degrees(viewer.latitude, viewer.longitude)) {
+                hgt_intersect(hgt_file, &earth, hgt_style.exaggeration, &viewer, intersect_heading, intersect_pitch)
             } else {
                 None
             };
@@ -1788,6 +2139,13 @@ fn main() {
             drop(timer);
         }
 
+        if hgt_loader_events(&hgt_loader, &mut hgt_mosaic, &mut hgt_files, &mut hgt_failed, &mut hgt_missing) {
+            rehgt = true;
+        }
+        if hgt_retry_failed(&mut hgt_failed) {
+            rehgt = true;
+        }
+
         let viewer_pos = viewer.position();
 
         if rehgt {
@@ -1795,24 +2153,25 @@ fn main() {
 
             rehgt = false;
 
-            let mut bounds_levels = Vec::with_capacity(hgt_horizons.len());
-            for hgt_horizon in hgt_horizons.iter() {
-                let viewer_sw = viewer.offset(*hgt_horizon, 225.0, 0.0);
-                let viewer_ne = viewer.offset(*hgt_horizon, 45.0, 0.0);
-                let bounds = (
+            let hgt_horizon = hgt_horizons[hgt_horizons.len() - 1];
+            let bounds = {
+                let viewer_sw = viewer.offset(hgt_horizon, 225.0, 0.0);
+                let viewer_ne = viewer.offset(hgt_horizon, 45.0, 0.0);
+                (
                     viewer_sw.latitude,
                     viewer_sw.longitude,
                     viewer_ne.latitude,
                     viewer_ne.longitude
-                );
-                // println!("bounds {}: {:?}", hgt_horizon, bounds);
-                bounds_levels.push(bounds);
-            }
+                )
+            };
 
-            if hgt_files.get(&(viewer.latitude.floor() as i16, viewer.longitude.floor() as i16)).is_none() {
-                hgt_nearby_files(&hgt_cache, viewer.latitude, viewer.longitude, hgt_res, &earth, ground_color, ocean_color, hgt_horizons.len(), &hgt_files);
+            hgt_nearby_files(&hgt_loader, viewer.latitude, viewer.longitude, &hgt_files, &hgt_failed, &hgt_missing);
+
+            let viewer_tile = HgtTileId::from_degrees(viewer.latitude, viewer.longitude);
+            if ! hgt_files.contains_key(&viewer_tile) {
+                while ! hgt_files.contains_key(&viewer_tile) && ! hgt_failed.contains_key(&viewer_tile) && ! hgt_missing.contains(&viewer_tile) {
+                    hgt_loader_events(&hgt_loader, &mut hgt_mosaic, &mut hgt_files, &mut hgt_failed, &mut hgt_missing);
 
-                while hgt_files.get(&(viewer.latitude.floor() as i16, viewer.longitude.floor() as i16)).is_none() {
                     let mut found_event = true;
                     while found_event {
                         found_event = false;
@@ -1838,18 +2197,60 @@ fn main() {
                 }
             }
 
+            let taws_active = if taws_enabled { taws_ownship.as_ref() } else { None };
+            taws_alert = match taws_active {
+                Some(ownship) => taws.check(&hgt_mosaic, ownship),
+                None => TawsAlert::clear(),
+            };
+
+            vsd_profile = if vsd {
+                // Along the flight path of the ownship, or the view when there is none
+                let (start, track, angle) = match taws_ownship {
+                    Some(ownship) => (
+                        earth.coordinate(ownship.latitude, ownship.longitude, ownship.altitude),
+                        ownship.track,
+                        ownship.vertical_speed.atan2(ownship.ground_speed).to_degrees(),
+                    ),
+                    None => (viewer.duplicate(), heading, pitch),
+                };
+                let range = hgt_horizons[1];
+                let path = [start.duplicate(), start.offset(range, track, angle)];
+
+                Some(Profile::new(&hgt_mosaic, &path, range / 256.0))
+            } else {
+                None
+            };
+
+            hgt_frame += 1;
+            let hgt_view = HgtView {
+                viewer: &viewer_pos,
+                scale: (w.height() as f64) / (2.0 * (fov.to_radians() / 2.0).tan()),
+                max_error: 2.0,
+                horizon: hgt_horizon,
+                taws: taws_active.map(|ownship| (&taws, ownship.altitude)),
+                caution_color,
+                warning_color,
+            };
             hgt_triangles.clear();
-            for hgt_file in hgt_files.iter() {
-                hgt_file.triangles(&bounds_levels, &mut hgt_triangles);
+            for (tile, hgt_file) in hgt_files.iter_mut() {
+                let file = match hgt_mosaic.get(*tile) {
+                    Some(file) => file,
+                    None => continue,
+                };
+                // Files loaded before the last change of style are rebuilt
+                if hgt_file.style != hgt_style {
+                    hgt_file.set_style(file, hgt_style.clone());
+                }
+                hgt_file.triangles(file, &hgt_view, hgt_frame, &mut hgt_triangles);
             }
 
             if let Some(ref osm) = osm_opt.take() { //TODO: Improve performance
                 osm_triangles.clear();
-                osm_way_triangles(osm, &earth, bounds_levels[bounds_levels.len() - 1], ground, &mut osm_triangles)
+                osm_way_triangles(osm, &earth, bounds, ground, &mut osm_triangles)
             }
 
             oap_triangles.clear();
-            oap_runway_triangles(&runways, bounds_levels[bounds_levels.len() - 1], &mut oap_triangles);
+            oap_runway_triangles(&runways, bounds, &mut oap_triangles);
 
             redraw = true;
 
@@ -2069,6 +2470,56 @@ fn main() {
                 drop(timer);
             }
 
+            if contours {
+                let timer = Timer::new("contours", debug);
+
+                // Lines are drawn where they are not behind the terrain
+                let contour_range = hgt_horizons[1];
+                let project = |position: &Position<Earth>| -> Option<(f64, f64)> {
+                    let dist = viewer_pos.vector(position).norm();
+                    if dist > contour_range { return None; }
+
+                    let p_ground = ground_perspective.transform(position);
+                    let p_screen = screen.transform(&p_ground);
+                    if ! clip_valid(&p_screen) { return None; }
+
+                    let (x, y) = (p_screen.0 as i32, p_screen.1 as i32);
+                    if x >= 0 && x < w_w && y >= 0 && y < w_h {
+                        let z = 1.0 / dist as f32;
+                        if z < z_buffer[(y * w_w + x) as usize] * 0.99 { return None; }
+                    }
+
+                    Some((p_screen.0, p_screen.1))
+                };
+
+                for (tile, hgt_file) in hgt_files.iter_mut() {
+                    let file = match hgt_mosaic.get(*tile) {
+                        Some(file) => file,
+                        None => continue,
+                    };
+                    for (contour, positions) in hgt_file.contours(file).iter() {
+                        // Every fifth line is an index contour
+                        let color = if (contour.elevation / contour_interval).round() as i64 % 5 == 0 {
+                            contour_index_color
+                        } else {
+                            contour_color
+                        };
+
+                        let count = positions.len() + if contour.closed { 1 } else { 0 };
+                        let mut prev_opt: Option<(f64, f64)> = None;
+                        for i in 0..count {
+                            let screen_opt = project(&positions[i % positions.len()]);
+                            if let (Some(a), Some(b)) = (prev_opt, screen_opt) {
+                                line_f64(&mut w, a.0, a.1, b.0, b.1, color);
+                            }
+                            prev_opt = screen_opt;
+                        }
+                    }
+                }
+
+                drop(timer);
+            }
+
             if hud {
                 let timer = Timer::new("hud", debug);
 
@@ -2345,6 +2796,71 @@ fn main() {
                 drop(timer);
             }
 
+            if let Some(ref profile) = vsd_profile {
+                let panel_h = VSD_PANEL_HEIGHT;
+                let top = w_h - panel_h;
+                w.rect(0, top, w_w as u32, panel_h as u32, Color::rgba(0x00, 0x00, 0x00, 0xA0));
+
+                let start = profile.points.first().map_or(0.0, |point| point.coordinate.elevation);
+                let end = profile.points.last().map_or(0.0, |point| point.coordinate.elevation);
+                let low = profile.lowest().and_then(|point| point.elevation).unwrap_or(start).min(start).min(end) - 100.0;
+                let high = profile.highest().and_then(|point| point.elevation).unwrap_or(start).max(start).max(end) + 100.0;
+                let length = profile.length().max(1.0);
+
+                let x = |distance: f64| distance / length * (w_w as f64);
+                let y = |elevation: f64| (top + panel_h) as f64 - (elevation - low) / (high - low) * (panel_h as f64);
+
+                // Terrain, colored by the clearance of the path above it
+                let column_w = (w_w as f64 / profile.points.len().max(1) as f64).ceil() as u32;
+                for point in profile.points.iter() {
+                    if let Some(elevation) = point.elevation {
+                        let color = match taws.terrain_level(point.coordinate.elevation, elevation) {
+                            TawsLevel::Warning => warning_color,
+                            TawsLevel::Caution => caution_color,
+                            TawsLevel::Clear => ground_color,
+                        };
+                        let column_y = y(elevation).round().max(top as f64) as i32;
+                        w.rect(
+                            x(point.distance).round() as i32,
+                            column_y,
+                            column_w,
+                            (top + panel_h - column_y).max(0) as u32,
+                            color
+                        );
+                    }
+                }
+
+                // Flight path
+                line_f64(&mut w, x(0.0), y(start), x(length), y(end), hud_color);
+
+                let _ = write!(
+                    WindowWriter::new(&mut w, 0, top, hud_color),
+                    "{}m ahead",
+                    length.round() as u32
+                );
+                if let Some(clearance) = profile.min_clearance().and_then(|point| point.clearance()) {
+                    let _ = write!(
+                        WindowWriter::new(&mut w, 0, top + 16, hud_color),
+                        "Clearance: {}m",
+                        clearance.round() as i32
+                    );
+                }
+            }
+
+            if taws_alert.level != TawsLevel::Clear {
+                let (message, color) = match taws_alert.level {
+                    TawsLevel::Warning => ("PULL UP", warning_color),
+                    _ => ("TERRAIN", caution_color),
+                };
+                let text = hud_cache.render(message);
+                text.draw(
+                    &mut w,
+                    (w_w - text.width() as i32) / 2,
+                    w_h / 4,
+                    color
+                );
+            }
+
             if debug {
                 let mut y = 0;
 
@@ -2411,6 +2927,14 @@ fn main() {
                 );
                 y += 16;
 
+                let _ = write!(
+                    WindowWriter::new(&mut w, 0, y, hud_color),
+                    "GDL90 errors: {} CRC, {} frame",
+                    gdl90.crc_errors(),
+                    gdl90.frame_errors()
+                );
+                y += 16;
+
                 if let Some(ref intersect) = intersect_opt {
                     let _ = write!(
                         WindowWriter::new(&mut w, 0, y, hud_color),
@@ -2420,6 +2944,21 @@ fn main() {
                 }
             }
 
+            {
+                // Failures that will be retried, above the vertical situation display
+                let bottom = if vsd_profile.is_some() { w_h - VSD_PANEL_HEIGHT } else { w_h };
+                let mut y = bottom - 16 * hgt_failed.len() as i32;
+                for (tile, (err, _retry)) in hgt_failed.iter() {
+                    let _ = write!(
+                        WindowWriter::new(&mut w, 0, y, hud_color),
+                        "Failed to load {}: {}",
+                        tile,
+                        err
+                    );
+                    y += 16;
+                }
+            }
+
             w.sync();
 
             drop(timer);
@@ -0,0 +1,138 @@
+use std::io::{self, Write};
+
+use crate::mesh::{Mesh, MeshFrame};
+
+/// glTF component types
+const UNSIGNED_BYTE: u32 = 5121;
+const UNSIGNED_INT: u32 = 5125;
+const FLOAT: u32 = 5126;
+
+/// glTF buffer view targets
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+impl Mesh {
+    /// Write as binary glTF 2.0 (.glb) with normals and vertex colors
+    ///
+    /// ENU meshes are rotated so up is +Y, as glTF expects. ECEF meshes are stored relative to
+    /// their center, which becomes the translation of the node, so 32-bit floats keep centimeter
+    /// precision.
+    pub fn write_gltf<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let (min, max) = self.extent();
+        let offset = match self.frame {
+            MeshFrame::Enu => [0.0; 3],
+            MeshFrame::Ecef if ! self.positions.is_empty() => [
+                (min[0] + max[0]) / 2.0,
+                (min[1] + max[1]) / 2.0,
+                (min[2] + max[2]) / 2.0,
+            ],
+            MeshFrame::Ecef => [0.0; 3],
+        };
+
+        let mut bin = Vec::with_capacity(self.positions.len() * 28 + self.triangles.len() * 12);
+
+        let mut p_min = [f32::MAX; 3];
+        let mut p_max = [f32::MIN; 3];
+        for p in self.positions.iter() {
+            for k in 0..3 {
+                let v = (p[k] - offset[k]) as f32;
+                p_min[k] = p_min[k].min(v);
+                p_max[k] = p_max[k].max(v);
+                bin.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        let normals_start = bin.len();
+        for n in self.vertex_normals().iter() {
+            for v in n.iter() {
+                bin.extend_from_slice(&(*v as f32).to_le_bytes());
+            }
+        }
+        // Colors have an alpha component, as vertex attributes must be aligned to four bytes
+        let colors_start = bin.len();
+        for c in self.colors.iter() {
+            bin.extend_from_slice(&[c.0, c.1, c.2, 255]);
+        }
+        let indices_start = bin.len();
+        for t in self.triangles.iter() {
+            for &v in t.iter() {
+                bin.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+
+        let vertices = self.positions.len();
+        let node = match self.frame {
+            MeshFrame::Enu => "\"rotation\":[-0.70710678,0,0,0.70710678]".to_string(),
+            MeshFrame::Ecef => format!("\"translation\":[{},{},{}]", offset[0], offset[1], offset[2]),
+        };
+        let position_bounds = if vertices > 0 {
+            format!(
+                ",\"min\":[{},{},{}],\"max\":[{},{},{}]",
+                p_min[0], p_min[1], p_min[2], p_max[0], p_max[1], p_max[2]
+            )
+        } else {
+            String::new()
+        };
+
+        let json = format!(
+            concat!(
+                "{{\"asset\":{{\"version\":\"2.0\",\"generator\":\"friar\"}},",
+                "\"scene\":0,\"scenes\":[{{\"nodes\":[0]}}],",
+                "\"nodes\":[{{\"mesh\":0,{node}}}],",
+                "\"meshes\":[{{\"primitives\":[{{\"attributes\":{{\"POSITION\":0,\"NORMAL\":1,\"COLOR_0\":2}},\"indices\":3,\"mode\":4}}]}}],",
+                "\"buffers\":[{{\"byteLength\":{bin_len}}}],",
+                "\"bufferViews\":[",
+                "{{\"buffer\":0,\"byteOffset\":0,\"byteLength\":{normals_start},\"target\":{array}}},",
+                "{{\"buffer\":0,\"byteOffset\":{normals_start},\"byteLength\":{normals_len},\"target\":{array}}},",
+                "{{\"buffer\":0,\"byteOffset\":{colors_start},\"byteLength\":{colors_len},\"target\":{array}}},",
+                "{{\"buffer\":0,\"byteOffset\":{indices_start},\"byteLength\":{indices_len},\"target\":{element}}}",
+                "],",
+                "\"accessors\":[",
+                "{{\"bufferView\":0,\"componentType\":{float},\"count\":{vertices},\"type\":\"VEC3\"{position_bounds}}},",
+                "{{\"bufferView\":1,\"componentType\":{float},\"count\":{vertices},\"type\":\"VEC3\"}},",
+                "{{\"bufferView\":2,\"componentType\":{byte},\"normalized\":true,\"count\":{vertices},\"type\":\"VEC4\"}},",
+                "{{\"bufferView\":3,\"componentType\":{uint},\"count\":{indices},\"type\":\"SCALAR\"}}",
+                "]}}"
+            ),
+            node = node,
+            bin_len = bin.len(),
+            normals_start = normals_start,
+            normals_len = colors_start - normals_start,
+            colors_start = colors_start,
+            colors_len = indices_start - colors_start,
+            indices_start = indices_start,
+            indices_len = bin.len() - indices_start,
+            array = ARRAY_BUFFER,
+            element = ELEMENT_ARRAY_BUFFER,
+            float = FLOAT,
+            byte = UNSIGNED_BYTE,
+            uint = UNSIGNED_INT,
+            vertices = vertices,
+            position_bounds = position_bounds,
+            indices = self.triangles.len() * 3,
+        );
+
+        // Chunks are padded to four bytes, JSON with spaces and binary with zeros
+        let mut json = json.into_bytes();
+        while json.len() % 4 != 0 {
+            json.push(b' ');
+        }
+        while bin.len() % 4 != 0 {
+            bin.push(0);
+        }
+
+        let length = 12 + 8 + json.len() + 8 + bin.len();
+        w.write_all(b"glTF")?;
+        w.write_all(&2u32.to_le_bytes())?;
+        w.write_all(&(length as u32).to_le_bytes())?;
+
+        w.write_all(&(json.len() as u32).to_le_bytes())?;
+        w.write_all(b"JSON")?;
+        w.write_all(&json)?;
+
+        w.write_all(&(bin.len() as u32).to_le_bytes())?;
+        w.write_all(b"BIN\0")?;
+        w.write_all(&bin)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,283 @@
+use std::f64;
+
+use crate::color_ramp::ColorRamp;
+use crate::coordinate::Coordinate;
+use crate::hgt::HgtSampler;
+use crate::spheroid::Spheroid;
+
+pub use self::rtin::Rtin;
+
+mod gltf;
+mod obj;
+mod rtin;
+mod stl;
+
+/// Coordinate frame of the vertices of a Mesh
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MeshFrame {
+    /// Local east, north, up in meters, with the origin at the center of the bounds at sea level
+    Enu,
+    /// Earth-centered, Earth-fixed in meters
+    Ecef,
+}
+
+/// Settings for building a Mesh from elevation samples
+#[derive(Clone, Debug)]
+pub struct MeshOptions {
+    /// Area to export as (south, west, north, east) in degrees
+    pub bounds: (f64, f64, f64, f64),
+    /// Level of detail, where each level skips every other sample of the previous level
+    pub lod: u32,
+    /// Factor applied to elevations before converting them to positions
+    pub exaggeration: f64,
+    pub frame: MeshFrame,
+    /// Colors of vertices by their elevation in meters
+    pub colors: ColorRamp,
+    /// Depth in meters below the lowest sample of a flat base, closing the mesh into a solid
+    /// suitable for 3D printing
+    pub base: Option<f64>,
+}
+
+impl MeshOptions {
+    /// Create options for exporting an area at full detail in the local ENU frame
+    pub fn new(bounds: (f64, f64, f64, f64)) -> Self {
+        Self {
+            bounds,
+            lod: 0,
+            exaggeration: 1.0,
+            frame: MeshFrame::Enu,
+            colors: ColorRamp::hypsometric(),
+            base: None,
+        }
+    }
+
+    /// Color of a vertex at an elevation
+    pub fn color(&self, elevation: f64) -> (u8, u8, u8) {
+        self.colors.color(elevation)
+    }
+}
+
+/// An indexed triangle mesh of terrain, which can be written as OBJ, STL or glTF
+///
+/// Triangles are wound counter-clockwise when seen from outside the surface.
+pub struct Mesh {
+    pub frame: MeshFrame,
+    /// Vertex positions in meters
+    pub positions: Vec<[f64; 3]>,
+    /// Vertex colors
+    pub colors: Vec<(u8, u8, u8)>,
+    /// Indices of the vertices of each triangle
+    pub triangles: Vec<[u32; 3]>,
+}
+
+impl Mesh {
+    /// Build a mesh from the samples of any HgtSampler inside the bounds of the options. Cells
+    /// touching missing samples are left out.
+    pub fn new<S: HgtSampler + ?Sized, R: Spheroid>(sampler: &S, reference: &R, options: &MeshOptions) -> Self {
+        let (south, west, north, east) = options.bounds;
+        let (origin_lat, origin_lon) = sampler.origin();
+        let (dlat, dlon) = sampler.spacing();
+        let step = 1i64 << options.lod.min(16);
+
+        // Rows and columns of samples inside the bounds, always including the last one
+        let indexes = |min: f64, max: f64, origin: f64, spacing: f64| -> Vec<i64> {
+            let first = ((min - origin) / spacing - 1e-6).ceil() as i64;
+            let last = ((max - origin) / spacing + 1e-6).floor() as i64;
+            let mut indexes: Vec<i64> = (0..).map(|i| first + i * step).take_while(|i| *i <= last).collect();
+            if indexes.last().is_some_and(|i| *i != last) {
+                indexes.push(last);
+            }
+            indexes
+        };
+        let rows = indexes(south, north, origin_lat, dlat);
+        let cols = indexes(west, east, origin_lon, dlon);
+
+        let center = reference.coordinate((south + north) / 2.0, (west + east) / 2.0, 0.0);
+        let convert = Converter::new(options.frame, &center);
+        // Height is already exaggerated
+        let vertex = |latitude: f64, longitude: f64, height: f64| -> [f64; 3] {
+            let position = reference.coordinate(latitude, longitude, height).position();
+            convert.convert([position.x, position.y, position.z])
+        };
+
+        let mut mesh = Self {
+            frame: options.frame,
+            positions: Vec::new(),
+            colors: Vec::new(),
+            triangles: Vec::new(),
+        };
+
+        let mut grid = vec![None; rows.len() * cols.len()];
+        let mut lowest = f64::MAX;
+        for (i, row) in rows.iter().enumerate() {
+            for (j, col) in cols.iter().enumerate() {
+                if let Some(elevation) = sampler.height(*row, *col) {
+                    let latitude = origin_lat + (*row as f64) * dlat;
+                    let longitude = origin_lon + (*col as f64) * dlon;
+                    grid[i * cols.len() + j] = Some(mesh.positions.len() as u32);
+                    mesh.positions.push(vertex(latitude, longitude, elevation * options.exaggeration));
+                    mesh.colors.push(options.color(elevation));
+                    lowest = lowest.min(elevation);
+                }
+            }
+        }
+
+        // Rows increase northward and columns eastward, so this is counter-clockwise from above
+        for i in 1..rows.len() {
+            for j in 1..cols.len() {
+                let a = grid[(i - 1) * cols.len() + j - 1];
+                let b = grid[(i - 1) * cols.len() + j];
+                let c = grid[i * cols.len() + j - 1];
+                let d = grid[i * cols.len() + j];
+                if let (Some(a), Some(b), Some(c), Some(d)) = (a, b, c, d) {
+                    mesh.triangles.push([a, b, d]);
+                    mesh.triangles.push([a, d, c]);
+                }
+            }
+        }
+
+        if let Some(depth) = options.base {
+            if rows.len() > 1 && cols.len() > 1 && lowest < f64::MAX {
+                let base = lowest * options.exaggeration - depth;
+
+                // Perimeter counter-clockwise from above, starting at the southwest corner
+                let mut perimeter = Vec::new();
+                let (last_i, last_j) = (rows.len() - 1, cols.len() - 1);
+                perimeter.extend((0..last_j).map(|j| (0, j)));
+                perimeter.extend((0..last_i).map(|i| (i, last_j)));
+                perimeter.extend((1..=last_j).rev().map(|j| (last_i, j)));
+                perimeter.extend((1..=last_i).rev().map(|i| (i, 0)));
+
+                let color = options.color(lowest);
+                let bottom: Vec<u32> = perimeter.iter().map(|&(i, j)| {
+                    let latitude = origin_lat + (rows[i] as f64) * dlat;
+                    let longitude = origin_lon + (cols[j] as f64) * dlon;
+                    mesh.positions.push(vertex(latitude, longitude, base));
+                    mesh.colors.push(color);
+                    (mesh.positions.len() - 1) as u32
+                }).collect();
+
+                let bottom_center = mesh.positions.len() as u32;
+                mesh.positions.push(vertex(
+                    origin_lat + ((rows[0] + rows[last_i]) as f64) * dlat / 2.0,
+                    origin_lon + ((cols[0] + cols[last_j]) as f64) * dlon / 2.0,
+                    base
+                ));
+                mesh.colors.push(color);
+
+                for k in 0..perimeter.len() {
+                    let next = (k + 1) % perimeter.len();
+                    let (i0, j0) = perimeter[k];
+                    let (i1, j1) = perimeter[next];
+
+                    // Walls face outward, which is to the right of a counter-clockwise perimeter
+                    let top0 = grid[i0 * cols.len() + j0];
+                    let top1 = grid[i1 * cols.len() + j1];
+                    if let (Some(t0), Some(t1)) = (top0, top1) {
+                        mesh.triangles.push([bottom[k], bottom[next], t1]);
+                        mesh.triangles.push([bottom[k], t1, t0]);
+                    }
+
+                    // Base faces down, so it is clockwise from above
+                    mesh.triangles.push([bottom_center, bottom[next], bottom[k]]);
+                }
+            }
+        }
+
+        mesh
+    }
+
+    /// Unit normal of each triangle
+    pub fn face_normals(&self) -> Vec<[f64; 3]> {
+        self.triangles.iter().map(|t| {
+            normalize(self.face_cross(t))
+        }).collect()
+    }
+
+    /// Unit normal of each vertex, averaged from the triangles using it weighted by their area
+    pub fn vertex_normals(&self) -> Vec<[f64; 3]> {
+        let mut normals = vec![[0.0; 3]; self.positions.len()];
+        for t in self.triangles.iter() {
+            let n = self.face_cross(t);
+            for &v in t.iter() {
+                for k in 0..3 {
+                    normals[v as usize][k] += n[k];
+                }
+            }
+        }
+        normals.into_iter().map(normalize).collect()
+    }
+
+    fn face_cross(&self, t: &[u32; 3]) -> [f64; 3] {
+        let a = self.positions[t[0] as usize];
+        let b = self.positions[t[1] as usize];
+        let c = self.positions[t[2] as usize];
+        let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+        [
+            u[1] * v[2] - u[2] * v[1],
+            u[2] * v[0] - u[0] * v[2],
+            u[0] * v[1] - u[1] * v[0],
+        ]
+    }
+
+    /// Minimum and maximum of each axis of the positions
+    pub fn extent(&self) -> ([f64; 3], [f64; 3]) {
+        let mut min = [f64::MAX; 3];
+        let mut max = [f64::MIN; 3];
+        for p in self.positions.iter() {
+            for k in 0..3 {
+                min[k] = min[k].min(p[k]);
+                max[k] = max[k].max(p[k]);
+            }
+        }
+        (min, max)
+    }
+}
+
+fn normalize(v: [f64; 3]) -> [f64; 3] {
+    let l = (v[0].powi(2) + v[1].powi(2) + v[2].powi(2)).sqrt();
+    if l > 0.0 {
+        [v[0] / l, v[1] / l, v[2] / l]
+    } else {
+        [0.0, 0.0, 1.0]
+    }
+}
+
+/// Converts ECEF positions to the frame of a Mesh
+struct Converter {
+    frame: MeshFrame,
+    origin: [f64; 3],
+    east: [f64; 3],
+    north: [f64; 3],
+    up: [f64; 3],
+}
+
+impl Converter {
+    /// Use a coordinate as the origin of the ENU frame
+    ///
+    /// Adapted from https://en.wikipedia.org/wiki/Geographic_coordinate_conversion#From_ECEF_to_ENU
+    fn new<R: Spheroid>(frame: MeshFrame, center: &Coordinate<R>) -> Self {
+        let position = center.position();
+        let f = center.latitude.to_radians();
+        let l = center.longitude.to_radians();
+        Self {
+            frame,
+            origin: [position.x, position.y, position.z],
+            east: [-l.sin(), l.cos(), 0.0],
+            north: [-f.sin() * l.cos(), -f.sin() * l.sin(), f.cos()],
+            up: [f.cos() * l.cos(), f.cos() * l.sin(), f.sin()],
+        }
+    }
+
+    fn convert(&self, ecef: [f64; 3]) -> [f64; 3] {
+        match self.frame {
+            MeshFrame::Ecef => ecef,
+            MeshFrame::Enu => {
+                let d = [ecef[0] - self.origin[0], ecef[1] - self.origin[1], ecef[2] - self.origin[2]];
+                let dot = |v: [f64; 3]| v[0] * d[0] + v[1] * d[1] + v[2] * d[2];
+                [dot(self.east), dot(self.north), dot(self.up)]
+            }
+        }
+    }
+}
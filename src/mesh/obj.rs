@@ -0,0 +1,30 @@
+use std::io::{self, Write};
+
+use crate::mesh::Mesh;
+
+impl Mesh {
+    /// Write as Wavefront OBJ, with vertex colors as extra components of each vertex
+    pub fn write_obj<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "# friar terrain mesh, {:?} frame, meters", self.frame)?;
+        for (p, c) in self.positions.iter().zip(self.colors.iter()) {
+            writeln!(
+                w,
+                "v {} {} {} {:.4} {:.4} {:.4}",
+                p[0], p[1], p[2],
+                c.0 as f64 / 255.0, c.1 as f64 / 255.0, c.2 as f64 / 255.0
+            )?;
+        }
+        for n in self.vertex_normals().iter() {
+            writeln!(w, "vn {:.6} {:.6} {:.6}", n[0], n[1], n[2])?;
+        }
+        // Indices start at one
+        for t in self.triangles.iter() {
+            writeln!(
+                w,
+                "f {0}//{0} {1}//{1} {2}//{2}",
+                t[0] + 1, t[1] + 1, t[2] + 1
+            )?;
+        }
+        Ok(())
+    }
+}
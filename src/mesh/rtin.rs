@@ -0,0 +1,134 @@
+use std::f64;
+
+use crate::{Error, Result};
+
+/// Right-triangulated irregular network of a square grid of heights, which splits triangles only
+/// where they would differ too much from the samples
+///
+/// Flat areas are covered by a few large triangles, while detail is spent on ridges and valleys.
+///
+/// Adapted from https://github.com/mapbox/martini
+pub struct Rtin {
+    size: usize,
+    heights: Vec<Option<f64>>,
+    /// Largest error in meters of the triangles with their hypotenuse centered on each sample
+    errors: Vec<f64>,
+}
+
+impl Rtin {
+    /// Compute the errors of a grid of size by size heights, where size is a power of two plus
+    /// one and at least 3, with the height of each row (counted northward) and column (counted
+    /// eastward)
+    ///
+    /// Triangles touching missing heights are split down to single cells, then left out.
+    pub fn new<F: Fn(usize, usize) -> Option<f64>>(size: usize, height: F) -> Result<Self> {
+        if size < 3 || ! (size - 1).is_power_of_two() {
+            return Err(Error::OutOfRange(
+                format!("Rtin: size {} is not at least 3 and a power of two plus one", size)
+            ));
+        }
+
+        let mut heights = Vec::with_capacity(size * size);
+        for row in 0..size {
+            for col in 0..size {
+                heights.push(height(row, col));
+            }
+        }
+
+        let mut rtin = Self {
+            size,
+            heights,
+            errors: vec![0.0; size * size],
+        };
+        rtin.compute_errors();
+        Ok(rtin)
+    }
+
+    /// Number of samples along each side
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Largest error in meters of the simplest mesh, which is two triangles
+    pub fn max_error(&self) -> f64 {
+        let center = self.size / 2;
+        self.errors[center * self.size + center]
+    }
+
+    /// Corners of triangles covering the grid as (row, column), each no further than max_error
+    /// meters from the samples it covers, and counter-clockwise when seen from above
+    pub fn triangles(&self, max_error: f64) -> Vec<[(usize, usize); 3]> {
+        let max = self.size - 1;
+        let mut triangles = Vec::new();
+        self.split((0, 0), (max, max), (0, max), max_error, &mut triangles);
+        self.split((max, max), (0, 0), (max, 0), max_error, &mut triangles);
+        triangles
+    }
+
+    /// Corners of a triangle by its index in the full binary tree of triangles, with (row,
+    /// column) corners a and b on the hypotenuse and c at the right angle
+    fn corners(&self, index: usize) -> ((usize, usize), (usize, usize), (usize, usize)) {
+        let max = self.size - 1;
+        let mut id = index + 2;
+        let (mut a, mut b, mut c) = if id & 1 == 1 {
+            ((0, 0), (max, max), (0, max))
+        } else {
+            ((max, max), (0, 0), (max, 0))
+        };
+        loop {
+            id >>= 1;
+            if id <= 1 {
+                break;
+            }
+            let m = ((a.0 + b.0) / 2, (a.1 + b.1) / 2);
+            if id & 1 == 1 {
+                b = a;
+                a = c;
+            } else {
+                a = b;
+                b = c;
+            }
+            c = m;
+        }
+        (a, b, c)
+    }
+
+    fn compute_errors(&mut self) {
+        let size = self.size;
+        let tile = size - 1;
+        let count = tile * tile * 2 - 2;
+        let parents = count - tile * tile;
+
+        // Children are visited before their parents, so errors propagate up the tree
+        for index in (0..count).rev() {
+            let (a, b, c) = self.corners(index);
+            let m = ((a.0 + b.0) / 2, (a.1 + b.1) / 2);
+            let middle = m.0 * size + m.1;
+
+            let error = match (self.heights[a.0 * size + a.1], self.heights[b.0 * size + b.1], self.heights[middle]) {
+                (Some(ha), Some(hb), Some(hm)) => ((ha + hb) / 2.0 - hm).abs(),
+                _ => f64::INFINITY,
+            };
+            let mut error = self.errors[middle].max(error);
+
+            if index < parents {
+                let left = ((a.0 + c.0) / 2) * size + (a.1 + c.1) / 2;
+                let right = ((b.0 + c.0) / 2) * size + (b.1 + c.1) / 2;
+                error = error.max(self.errors[left]).max(self.errors[right]);
+            }
+            self.errors[middle] = error;
+        }
+    }
+
+    fn split(&self, a: (usize, usize), b: (usize, usize), c: (usize, usize), max_error: f64, triangles: &mut Vec<[(usize, usize); 3]>) {
+        let m = ((a.0 + b.0) / 2, (a.1 + b.1) / 2);
+        let single_cell = (a.0 as i64 - c.0 as i64).abs() + (a.1 as i64 - c.1 as i64).abs() <= 1;
+        if ! single_cell && self.errors[m.0 * self.size + m.1] > max_error {
+            self.split(c, a, m, max_error, triangles);
+            self.split(b, c, m, max_error, triangles);
+        } else if [a, b, c].iter().all(|&(row, col)| self.heights[row * self.size + col].is_some()) {
+            // Corners a, b, c are clockwise from above
+            triangles.push([a, c, b]);
+        }
+    }
+}
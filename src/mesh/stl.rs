@@ -0,0 +1,31 @@
+use std::io::{self, Write};
+
+use crate::mesh::Mesh;
+
+impl Mesh {
+    /// Write as binary STL. STL has no vertex colors, and positions are rounded to 32-bit floats.
+    pub fn write_stl<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let mut header = [0u8; 80];
+        let name = format!("friar terrain mesh, {:?} frame, meters", self.frame);
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        w.write_all(&header)?;
+        w.write_all(&(self.triangles.len() as u32).to_le_bytes())?;
+
+        let mut record = Vec::with_capacity(50);
+        for (t, n) in self.triangles.iter().zip(self.face_normals().iter()) {
+            record.clear();
+            for v in n.iter() {
+                record.extend_from_slice(&(*v as f32).to_le_bytes());
+            }
+            for &v in t.iter() {
+                for p in self.positions[v as usize].iter() {
+                    record.extend_from_slice(&(*p as f32).to_le_bytes());
+                }
+            }
+            // Attribute byte count
+            record.extend_from_slice(&0u16.to_le_bytes());
+            w.write_all(&record)?;
+        }
+        Ok(())
+    }
+}
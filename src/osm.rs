@@ -1,8 +1,10 @@
-use osmpbfreader::{Error, OsmPbfReader, OsmObj, Node, NodeId, Result, Way, WayId};
+use osmpbfreader::{OsmPbfReader, OsmObj, Node, NodeId, Way, WayId};
 use std::collections::HashMap;
 use std::fs::File;
 use std::path::Path;
 
+use crate::Result;
+
 pub struct Osm {
     pub nodes: HashMap<NodeId, Node>,
     pub ways: HashMap<WayId, Way>,
@@ -13,7 +15,7 @@ impl Osm {
         let mut nodes = HashMap::new();
         let mut ways = HashMap::new();
 
-        let file = File::open(p).map_err(|err| Error::Io(err))?;
+        let file = File::open(p)?;
         for obj_res in OsmPbfReader::new(file).iter() {
             match obj_res? {
                 OsmObj::Node(node) => {
@@ -1,7 +1,6 @@
 use reqwest;
-use std::io;
 
-use crate::reqwest_err;
+use crate::Result;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Airport {
@@ -27,9 +26,8 @@ pub struct Airport {
 }
 
 impl Airport {
-    pub fn all() -> io::Result<Vec<Self>> {
-        let response = reqwest::get("https://davidmegginson.github.io/ourairports-data/airports.csv")
-            .map_err(reqwest_err)?;
+    pub fn all() -> Result<Vec<Self>> {
+        let response = reqwest::get("https://davidmegginson.github.io/ourairports-data/airports.csv")?;
 
         let mut entries = Vec::new();
 
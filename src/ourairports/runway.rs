@@ -1,7 +1,6 @@
 use reqwest;
-use std::io;
 
-use crate::reqwest_err;
+use crate::Result;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Runway {
@@ -30,9 +29,8 @@ pub struct Runway {
 }
 
 impl Runway {
-    pub fn all() -> io::Result<Vec<Self>> {
-        let response = reqwest::get("https://davidmegginson.github.io/ourairports-data/runways.csv")
-            .map_err(reqwest_err)?;
+    pub fn all() -> Result<Vec<Self>> {
+        let response = reqwest::get("https://davidmegginson.github.io/ourairports-data/runways.csv")?;
 
         let mut entries = Vec::new();
 
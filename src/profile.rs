@@ -0,0 +1,105 @@
+use crate::coordinate::Coordinate;
+use crate::hgt::{HgtInterpolation, HgtSampler};
+use crate::spheroid::Spheroid;
+
+/// A point sampled along a path
+pub struct ProfilePoint<'r, R: Spheroid + 'r> {
+    /// Distance from the start of the path in meters
+    pub distance: f64,
+    /// Position on the path, with the planned altitude interpolated between the path coordinates
+    pub coordinate: Coordinate<'r, R>,
+    /// Height of terrain in meters, if known
+    pub elevation: Option<f64>,
+}
+
+impl<'r, R: Spheroid> ProfilePoint<'r, R> {
+    /// Height in meters of the planned altitude above terrain
+    pub fn clearance(&self) -> Option<f64> {
+        self.elevation.map(|elevation| self.coordinate.elevation - elevation)
+    }
+}
+
+/// Terrain along a path, such as a planned route or the line ahead of the aircraft
+pub struct Profile<'r, R: Spheroid + 'r> {
+    pub points: Vec<ProfilePoint<'r, R>>,
+}
+
+impl<'r, R: Spheroid> Profile<'r, R> {
+    /// Sample terrain along great circles between the coordinates of a path, with no more than
+    /// spacing meters between points. Every coordinate of the path is included, and the elevation
+    /// of each is the planned altitude there.
+    pub fn new<S: HgtSampler + ?Sized>(sampler: &S, path: &[Coordinate<'r, R>], spacing: f64) -> Self {
+        let mut points = Vec::new();
+        let mut start_distance = 0.0;
+        for (i, a) in path.iter().enumerate() {
+            let b = match path.get(i + 1) {
+                Some(b) => b,
+                None => {
+                    points.push(Self::point(sampler, start_distance, a.duplicate()));
+                    break;
+                }
+            };
+
+            let length = a.distance(b);
+            let heading = a.heading(b);
+            let steps = if spacing > 0.0 { (length / spacing).ceil().max(1.0) as usize } else { 1 };
+            for step in 0..steps {
+                let t = step as f64 / steps as f64;
+                let mut coordinate = a.offset(length * t, heading, 0.0);
+                coordinate.elevation = a.elevation + (b.elevation - a.elevation) * t;
+                points.push(Self::point(sampler, start_distance + length * t, coordinate));
+            }
+            start_distance += length;
+        }
+
+        Self {
+            points,
+        }
+    }
+
+    fn point<S: HgtSampler + ?Sized>(sampler: &S, distance: f64, coordinate: Coordinate<'r, R>) -> ProfilePoint<'r, R> {
+        let elevation = sampler.elevation_at(coordinate.latitude, coordinate.longitude, HgtInterpolation::Bilinear);
+        ProfilePoint {
+            distance,
+            coordinate,
+            elevation,
+        }
+    }
+
+    /// Length of the path in meters
+    pub fn length(&self) -> f64 {
+        self.points.last().map_or(0.0, |point| point.distance)
+    }
+
+    /// Pairs of distance and terrain elevation in meters, where terrain is known
+    pub fn elevations<'a>(&'a self) -> impl Iterator<Item = (f64, f64)> + 'a {
+        self.points.iter().filter_map(|point| point.elevation.map(|elevation| (point.distance, elevation)))
+    }
+
+    /// Point with the lowest terrain
+    pub fn lowest(&self) -> Option<&ProfilePoint<'r, R>> {
+        self.min_by(|point| point.elevation)
+    }
+
+    /// Point with the highest terrain
+    pub fn highest(&self) -> Option<&ProfilePoint<'r, R>> {
+        self.min_by(|point| point.elevation.map(|elevation| -elevation))
+    }
+
+    /// Point where the planned altitude is closest to, or furthest below, terrain
+    pub fn min_clearance(&self) -> Option<&ProfilePoint<'r, R>> {
+        self.min_by(|point| point.clearance())
+    }
+
+    fn min_by<F: Fn(&ProfilePoint<'r, R>) -> Option<f64>>(&self, f: F) -> Option<&ProfilePoint<'r, R>> {
+        let mut min: Option<(f64, &ProfilePoint<'r, R>)> = None;
+        for point in self.points.iter() {
+            if let Some(value) = f(point) {
+                if min.is_none_or(|(min_value, _)| value < min_value) {
+                    min = Some((value, point));
+                }
+            }
+        }
+        min.map(|(_, point)| point)
+    }
+}
@@ -0,0 +1,199 @@
+use std::f64;
+
+use crate::gdl90::Gdl90Traffic;
+use crate::hgt::{HgtInterpolation, HgtSampler, MEAN_RADIUS};
+use crate::x_plane::XPlanePosition;
+
+const FEET: f64 = 0.3048;
+const KNOTS: f64 = 1852.0 / 3600.0;
+const FEET_PER_MINUTE: f64 = FEET / 60.0;
+
+/// Severity of a terrain alert, ordered from least to most severe
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum TawsLevel {
+    Clear,
+    /// Terrain ahead, shown in yellow
+    Caution,
+    /// Terrain ahead with little time to react, shown in red
+    Warning,
+}
+
+/// Position and motion of the aircraft being protected
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TawsOwnship {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Altitude above mean sea level in meters
+    pub altitude: f64,
+    /// Ground speed in meters per second
+    pub ground_speed: f64,
+    /// Direction of motion over the ground in degrees clockwise from true north
+    pub track: f64,
+    /// Rate of climb in meters per second, negative when descending
+    pub vertical_speed: f64,
+}
+
+impl TawsOwnship {
+    /// Use a position sent by X-Plane, with velocity in meters per second east, up and south
+    pub fn from_x_plane(position: &XPlanePosition) -> Self {
+        let (east, up, south) = (position.speed_east as f64, position.speed_up as f64, position.speed_south as f64);
+        Self {
+            latitude: position.latitude,
+            longitude: position.longitude,
+            altitude: position.elevation,
+            ground_speed: (east.powi(2) + south.powi(2)).sqrt(),
+            track: east.atan2(-south).to_degrees().rem_euclid(360.0),
+            vertical_speed: up,
+        }
+    }
+
+    /// Use a GDL90 ownship report, with the geometric altitude in feet from the ownship geometric
+    /// altitude message if known, as it is closer to the height of terrain than pressure altitude
+    ///
+    /// Missing velocities are treated as zero.
+    pub fn from_gdl90(ownship: &Gdl90Traffic, geo_altitude: Option<f64>) -> Self {
+        Self {
+            latitude: ownship.latitude(),
+            longitude: ownship.longitude(),
+            altitude: geo_altitude.unwrap_or_else(|| ownship.altitude()) * FEET,
+            ground_speed: ownship.h_velocity().unwrap_or(0.0) * KNOTS,
+            track: ownship.heading(),
+            vertical_speed: ownship.v_velocity().unwrap_or(0.0) * FEET_PER_MINUTE,
+        }
+    }
+
+    /// Position after a number of seconds at the current velocity, offset to the right of the track
+    /// by a distance in meters, as (latitude, longitude, altitude)
+    pub fn project(&self, time: f64, right: f64) -> (f64, f64, f64) {
+        let forward = self.ground_speed * time;
+        let distance = (forward.powi(2) + right.powi(2)).sqrt();
+        let bearing = (self.track.to_radians() + right.atan2(forward)).rem_euclid(2.0 * f64::consts::PI);
+
+        // Destination along a great circle
+        //
+        // From https://www.movable-type.co.uk/scripts/latlong.html
+        let d = distance / MEAN_RADIUS;
+        let f1 = self.latitude.to_radians();
+        let l1 = self.longitude.to_radians();
+        let f2 = (f1.sin() * d.cos() + f1.cos() * d.sin() * bearing.cos()).asin();
+        let l2 = l1 + (bearing.sin() * d.sin() * f1.cos()).atan2(d.cos() - f1.sin() * f2.sin());
+
+        (
+            f2.to_degrees(),
+            (l2.to_degrees() + 180.0).rem_euclid(360.0) - 180.0,
+            self.altitude + self.vertical_speed * time,
+        )
+    }
+}
+
+/// Result of projecting the flight path against terrain
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TawsAlert {
+    pub level: TawsLevel,
+    /// Seconds until the path first lacks clearance
+    pub time: Option<f64>,
+    /// Smallest height in meters of the path above terrain, where terrain is known
+    pub clearance: Option<f64>,
+    /// Terrain where the path first lacks clearance, as (latitude, longitude, elevation)
+    pub conflict: Option<(f64, f64, f64)>,
+}
+
+impl TawsAlert {
+    /// No alert and no known terrain
+    pub fn clear() -> Self {
+        Self {
+            level: TawsLevel::Clear,
+            time: None,
+            clearance: None,
+            conflict: None,
+        }
+    }
+}
+
+/// Forward-looking terrain alerting, which projects the flight path of the ownship and compares it
+/// with the terrain along a corridor around it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Taws {
+    /// Seconds ahead to look for terrain causing a caution
+    pub caution_time: f64,
+    /// Seconds ahead to look for terrain causing a warning
+    pub warning_time: f64,
+    /// Height in meters the path must keep above terrain
+    pub clearance: f64,
+    /// Terrain within this many meters below the ownship, or above it, is shown as a warning
+    pub warning_margin: f64,
+    /// Distance in meters to each side of the track that is checked
+    pub corridor: f64,
+    /// Seconds between checked points
+    pub step: f64,
+    /// Ground speed in meters per second below which no alerts are raised, such as when taxiing
+    pub min_speed: f64,
+}
+
+impl Default for Taws {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Taws {
+    /// Create with a required clearance of 500 feet, a caution 60 seconds and a warning 30 seconds
+    /// before losing it
+    pub fn new() -> Self {
+        Self {
+            caution_time: 60.0,
+            warning_time: 30.0,
+            clearance: 500.0 * FEET,
+            warning_margin: 100.0 * FEET,
+            corridor: 150.0,
+            step: 1.0,
+            min_speed: 15.0,
+        }
+    }
+
+    /// Project the path of the ownship against terrain
+    pub fn check<S: HgtSampler + ?Sized>(&self, sampler: &S, ownship: &TawsOwnship) -> TawsAlert {
+        let mut alert = TawsAlert::clear();
+        if ownship.ground_speed < self.min_speed {
+            return alert;
+        }
+
+        let steps = (self.caution_time / self.step).ceil().max(0.0) as u32;
+        for i in 0..=steps {
+            let time = (i as f64 * self.step).min(self.caution_time);
+            for &right in [0.0, -self.corridor, self.corridor].iter() {
+                let (latitude, longitude, altitude) = ownship.project(time, right);
+                let elevation = match sampler.elevation_at(latitude, longitude, HgtInterpolation::Bilinear) {
+                    Some(elevation) => elevation,
+                    None => continue,
+                };
+
+                let clearance = altitude - elevation;
+                alert.clearance = Some(alert.clearance.map_or(clearance, |c| c.min(clearance)));
+                if clearance < self.clearance && alert.time.is_none() {
+                    alert.time = Some(time);
+                    alert.conflict = Some((latitude, longitude, elevation));
+                    alert.level = if time <= self.warning_time {
+                        TawsLevel::Warning
+                    } else {
+                        TawsLevel::Caution
+                    };
+                }
+            }
+        }
+
+        alert
+    }
+
+    /// Level used to color terrain at an elevation relative to the altitude of the ownship, both
+    /// in meters
+    pub fn terrain_level(&self, altitude: f64, elevation: f64) -> TawsLevel {
+        if elevation > altitude - self.warning_margin {
+            TawsLevel::Warning
+        } else if elevation > altitude - self.clearance {
+            TawsLevel::Caution
+        } else {
+            TawsLevel::Clear
+        }
+    }
+}
@@ -2,6 +2,8 @@ use plain::{self, Plain};
 use std::io;
 use std::net::UdpSocket;
 
+use crate::{Error, Result};
+
 #[derive(Clone, Copy, Debug, Default)]
 #[repr(packed)]
 pub struct XPlanePosition {
@@ -28,16 +30,16 @@ pub struct XPlane {
 }
 
 impl XPlane {
-    pub fn new(remote: &str, rate: u8) -> io::Result<Self> {
+    pub fn new(remote: &str, rate: u8) -> Result<Self> {
         let socket = UdpSocket::bind(("0.0.0.0", 0))?;
 
         let request = format!("RPOS\0{}\0", rate).into_bytes();
         let sent = socket.send_to(&request, (remote, 49000))?;
         if sent != request.len() {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::WriteZero,
                 format!("sent {} bytes instead of {}", sent, request.len())
-            ));
+            )));
         }
 
         socket.set_nonblocking(true)?;
@@ -49,7 +51,7 @@ impl XPlane {
         })
     }
 
-    pub fn position(&mut self) -> io::Result<Option<XPlanePosition>> {
+    pub fn position(&mut self) -> Result<Option<XPlanePosition>> {
         let mut rpos = XPlanePosition::default();
 
         {
@@ -59,13 +61,12 @@ impl XPlane {
                 Err(err) => if err.kind() == io::ErrorKind::WouldBlock {
                     return Ok(None);
                 } else {
-                    return Err(err);
+                    return Err(err.into());
                 }
             };
 
             if received != response.len() {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
+                return Err(Error::Decode(
                     format!("received {} bytes instead of {}", received, response.len())
                 ));
             }
@@ -73,8 +74,7 @@ impl XPlane {
 
         let signature = b"RPOS4";
         if &rpos.signature != signature {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
+            return Err(Error::Decode(
                 format!("received signature {:?} instead of {:?}", rpos.signature, signature)
             ));
         }
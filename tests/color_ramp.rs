@@ -0,0 +1,42 @@
+extern crate friar;
+
+use friar::color_ramp::ColorRamp;
+
+#[test]
+fn interpolate_stops() {
+    let ramp = ColorRamp::grayscale();
+    assert_eq!(ramp.color(-50.0), (0, 0, 0));
+    assert_eq!(ramp.color(2250.0), (0x80, 0x80, 0x80));
+    assert_eq!(ramp.color(9000.0), (0xFF, 0xFF, 0xFF));
+
+    // Stops are sorted
+    let ramp = ColorRamp::new("test", vec![(100.0, (200, 0, 0)), (0.0, (0, 0, 100))]);
+    assert_eq!(ramp.stops[0].0, 0.0);
+    assert_eq!(ramp.color(50.0), (100, 0, 50));
+
+    for ramp in ColorRamp::builtin() {
+        assert!(ramp.stops.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+}
+
+#[test]
+fn parse_gradient() {
+    let ramp = ColorRamp::parse("relief", "\
+        # gdaldem color-relief\n\
+        nv 0 0 0 0\n\
+        0 10 20 30\n\
+        \n\
+        1000,40,50,60,255\n\
+        500\t25 35 45\n\
+    ").unwrap();
+    assert_eq!(ramp.name, "relief");
+    assert_eq!(ramp.stops, vec![
+        (0.0, (10, 20, 30)),
+        (500.0, (25, 35, 45)),
+        (1000.0, (40, 50, 60)),
+    ]);
+
+    assert!(ColorRamp::parse("empty", "# nothing\n").is_err());
+    assert!(ColorRamp::parse("short", "0 1 2\n").is_err());
+    assert!(ColorRamp::parse("range", "0 1 2 300\n").is_err());
+}
@@ -0,0 +1,89 @@
+extern crate friar;
+
+use friar::contour::Contour;
+use friar::earth::Earth;
+use friar::hgt::HgtSampler;
+
+/// Grid of one sample per 0.01 degrees, with heights from a function of row and column
+struct Grid<F: Fn(i64, i64) -> f64>(F);
+
+impl<F: Fn(i64, i64) -> f64> HgtSampler for Grid<F> {
+    fn origin(&self) -> (f64, f64) {
+        (0.0, 0.0)
+    }
+
+    fn spacing(&self) -> (f64, f64) {
+        (0.01, 0.01)
+    }
+
+    fn height(&self, row: i64, col: i64) -> Option<f64> {
+        if (0..=100).contains(&row) && (0..=100).contains(&col) {
+            Some((self.0)(row, col))
+        } else {
+            None
+        }
+    }
+}
+
+#[test]
+fn hill_loops() {
+    // Cone peaking at 1000 meters in the middle, with integer heights
+    let hill = Grid(|row, col| {
+        let d = (((row - 50).pow(2) + (col - 50).pow(2)) as f64).sqrt();
+        (1000.0 - 25.0 * d).max(0.0).round()
+    });
+
+    let contours = Contour::generate(&hill, &Earth, (0.0, 0.0, 1.0, 1.0), 100.0);
+    // Levels are just above the samples on them, so 0 is the foot of the cone and there is no
+    // line at exactly the peak
+    let levels: Vec<f64> = contours.iter().map(|c| c.elevation).collect();
+    assert_eq!(levels, vec![0.0, 100.0, 200.0, 300.0, 400.0, 500.0, 600.0, 700.0, 800.0, 900.0]);
+
+    for contour in contours.iter().skip(1) {
+        assert!(contour.closed);
+
+        // Counter-clockwise, so the hill is on the left
+        let mut area = 0.0;
+        for i in 0..contour.points.len() {
+            let a = &contour.points[i];
+            let b = &contour.points[(i + 1) % contour.points.len()];
+            area += a.longitude * b.latitude - b.longitude * a.latitude;
+        }
+        assert!(area > 0.0);
+
+        // Radius matches the cone
+        let radius = (1000.0 - contour.elevation) / 25.0 * 0.01;
+        for point in contour.points.iter() {
+            let d = ((point.latitude - 0.5).powi(2) + (point.longitude - 0.5).powi(2)).sqrt();
+            assert!((d - radius).abs() < 0.01);
+            assert_eq!(point.elevation, contour.elevation);
+        }
+    }
+}
+
+#[test]
+fn ramp_lines() {
+    // Rises 10 meters per column toward the east
+    let ramp = Grid(|_row, col| col as f64 * 10.0);
+
+    let contours = Contour::generate(&ramp, &Earth, (0.2, 0.2, 0.4, 0.4), 50.0);
+    // 200 through 350 meters, as 400 meters is only reached on the edge
+    assert_eq!(contours.len(), 4);
+    for contour in contours.iter() {
+        assert!(!contour.closed);
+        assert_eq!(contour.points.len(), 21);
+
+        // Higher ground to the east is on the left, so lines run south
+        let first = &contour.points[0];
+        let last = &contour.points[contour.points.len() - 1];
+        assert!(first.latitude > last.latitude);
+        assert!((first.longitude - contour.elevation / 1000.0).abs() < 1e-6);
+    }
+
+    let mut geojson = Vec::new();
+    Contour::write_geojson(&contours, &mut geojson).unwrap();
+    let geojson = String::from_utf8(geojson).unwrap();
+    assert!(geojson.starts_with("{\"type\":\"FeatureCollection\""));
+    assert_eq!(geojson.matches("\"LineString\"").count(), 4);
+    assert!(geojson.contains("\"elevation\":300"));
+}
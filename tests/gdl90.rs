@@ -0,0 +1,580 @@
+extern crate friar;
+
+use std::time::{Duration, Instant};
+
+use friar::Error;
+use friar::gdl90::{self, dlac_decode, FisbApdu, FisbGeometry, FisbProduct, FisbReassembler, FisbRecord, FisbSegment, FisbTime, Gdl90Emergency, Gdl90EmitterCategory, Gdl90ForeFlightAhrs, Gdl90Framer, Gdl90GeoAltitude, Gdl90Heartbeat, Gdl90InfoFrame, Gdl90Kind, Gdl90Message, Gdl90Nacp, Gdl90Nic, Gdl90TrackType, Gdl90Traffic, Gdl90UatReport, Gdl90Uplink};
+
+/// Heartbeat example from the GDL90 ICD
+const HEARTBEAT: [u8; 11] = [0x7E, 0x00, 0x81, 0x41, 0xDB, 0xD0, 0x08, 0x02, 0xB3, 0x8B, 0x7E];
+
+#[test]
+fn frame_check() {
+    assert_eq!(gdl90::crc(&HEARTBEAT[1..8]), 0x8BB3);
+
+    let msg = Gdl90Message::new(&HEARTBEAT).unwrap();
+    assert_eq!(msg.id(), 0x00);
+    assert_eq!(msg.data(), &HEARTBEAT[2..8]);
+    match msg.kind() {
+        Some(Gdl90Kind::Heartbeat(heartbeat)) => {
+            assert!(heartbeat.gps_position_valid());
+            assert!(! heartbeat.maintenance_required());
+            assert!(heartbeat.uat_initialized());
+            assert!(heartbeat.csa_requested());
+            assert!(heartbeat.utc_ok());
+            assert_eq!(heartbeat.seconds(), 0xD0DB);
+            assert_eq!(heartbeat.uplink_count(), 1);
+            assert_eq!(heartbeat.basic_long_count(), 2);
+        },
+        other => panic!("expected heartbeat, got {:?}", other),
+    }
+
+    let mut corrupt = HEARTBEAT;
+    corrupt[4] ^= 0x01;
+    match Gdl90Message::new(&corrupt) {
+        Err(Error::Checksum(_)) => (),
+        other => panic!("expected checksum error, got {:?}", other.err()),
+    }
+
+    match Gdl90Message::new(&HEARTBEAT[1..]) {
+        Err(Error::Decode(_)) => (),
+        other => panic!("expected decode error, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn byte_stuffing() {
+    // Data containing both the flag and escape bytes
+    let msg = [0x65, 0x7E, 0x01, 0x7D, 0x02];
+    let fcs = gdl90::crc(&msg);
+
+    let mut frame = vec![0x7E];
+    for &byte in msg.iter().chain([fcs as u8, (fcs >> 8) as u8].iter()) {
+        if byte == 0x7E || byte == 0x7D {
+            frame.push(0x7D);
+            frame.push(byte ^ 0x20);
+        } else {
+            frame.push(byte);
+        }
+    }
+    frame.push(0x7E);
+    assert_eq!(&frame[1..8], &[0x65, 0x7D, 0x5E, 0x01, 0x7D, 0x5D, 0x02]);
+
+    let decoded = Gdl90Message::new(&frame).unwrap();
+    assert_eq!(decoded.id(), 0x65);
+    assert_eq!(decoded.data(), &msg[1..]);
+}
+
+#[test]
+fn framer() {
+    // Starts partway through a frame, then has frames with their own flags, a shared flag, a
+    // corrupted frame and a truncated frame
+    let mut stream = vec![0x41, 0x02, 0x7E];
+    stream.extend_from_slice(&HEARTBEAT);
+    stream.extend_from_slice(&HEARTBEAT[..HEARTBEAT.len() - 1]);
+    stream.extend_from_slice(&HEARTBEAT);
+    let mut corrupt = HEARTBEAT;
+    corrupt[4] ^= 0x01;
+    stream.extend_from_slice(&corrupt);
+    stream.extend_from_slice(&[0x7E, 0x00, 0x7E]);
+    stream.extend_from_slice(&HEARTBEAT);
+
+    // Split across reads of every size
+    for size in 1..stream.len() {
+        let mut framer = Gdl90Framer::new();
+        let mut count = 0;
+        for chunk in stream.chunks(size) {
+            framer.push(chunk);
+            for msg in &mut framer {
+                assert_eq!(msg.data(), &HEARTBEAT[2..8]);
+                count += 1;
+            }
+        }
+        assert_eq!(count, 4);
+        assert_eq!(framer.crc_errors(), 1);
+        assert_eq!(framer.frame_errors(), 1);
+    }
+
+    // From a reader
+    let mut framer = Gdl90Framer::new();
+    let mut reader = &stream[..];
+    while framer.read(&mut reader).unwrap() > 0 {}
+    assert_eq!(framer.count(), 4);
+
+    // A long stream in small reads, taking messages as they arrive
+    let mut framer = Gdl90Framer::default();
+    let long: Vec<u8> = HEARTBEAT.iter().cycle().take(HEARTBEAT.len() * 1000).cloned().collect();
+    let mut count = 0;
+    for chunk in long.chunks(7) {
+        framer.push(chunk);
+        count += (&mut framer).count();
+    }
+    assert_eq!(count, 1000);
+}
+
+#[test]
+fn encode() {
+    let heartbeat = Gdl90Heartbeat {
+        status: [0x81, 0x41],
+        timestamp: 0xD0DB,
+        counts: [0x08, 0x02],
+    };
+    assert_eq!(Gdl90Kind::Heartbeat(heartbeat).encode(), HEARTBEAT);
+
+    // Seconds after 18:12:16 UTC need the highest bit in the status
+    let mut heartbeat = Gdl90Heartbeat { status: [0x81, 0x41], timestamp: 0, counts: [0, 0] };
+    heartbeat.set_seconds(86399);
+    assert_eq!(heartbeat.status[1], 0xC1);
+    let msg = Gdl90Message::new(&Gdl90Kind::Heartbeat(heartbeat).encode()).unwrap();
+    match msg.kind() {
+        Some(Gdl90Kind::Heartbeat(heartbeat)) => assert_eq!(heartbeat.seconds(), 86399),
+        other => panic!("expected heartbeat, got {:?}", other),
+    }
+
+    let mut traffic = Gdl90Traffic {
+        address: 0xA00001,
+        ..Default::default()
+    };
+    traffic.set_latitude(39.64);
+    traffic.set_longitude(-104.85);
+    traffic.set_altitude(9500.0);
+    traffic.set_h_velocity(120.0);
+    traffic.set_v_velocity(-500.0);
+    traffic.set_heading(270.0);
+    traffic.set_callsign("N12345");
+
+    // Data that need escaping are stuffed
+    traffic.category = 0x7E;
+    let frame = Gdl90Kind::Traffic(traffic).encode();
+    assert!(! frame[1..frame.len() - 1].contains(&0x7E));
+
+    let mut framer = Gdl90Framer::new();
+    framer.push(&frame);
+    let msg = framer.next().unwrap();
+    assert_eq!(msg.id(), 20);
+    let decoded = match msg.kind() {
+        Some(Gdl90Kind::Traffic(traffic)) => traffic,
+        other => panic!("expected traffic, got {:?}", other),
+    };
+    assert_eq!(decoded.address, 0xA00001);
+    assert!((decoded.latitude() - 39.64).abs() < 1e-4);
+    assert!((decoded.longitude() + 104.85).abs() < 1e-4);
+    assert_eq!(decoded.altitude(), 9500.0);
+    assert_eq!(decoded.h_velocity(), Some(120.0));
+    assert_eq!(decoded.v_velocity(), Some(-512.0));
+    assert_eq!(decoded.heading(), 270.0);
+    assert_eq!(decoded.category, 0x7E);
+    assert_eq!(&decoded.callsign, b"N12345  ");
+    assert_eq!(decoded.callsign(), "N12345");
+
+    // Traffic example from the GDL90 ICD
+    let decoded = Gdl90Traffic::new(&[
+        0x00, 0xAB, 0x45, 0x49, 0x1F, 0xEF, 0x15, 0xA8, 0x89, 0x78, 0x0F, 0x09, 0xA9, 0x07, 0xB0, 0x01,
+        0x20, 0x01, 0x4E, 0x38, 0x32, 0x35, 0x56, 0x20, 0x20, 0x20, 0x00,
+    ]).unwrap();
+    assert_eq!(decoded.altitude(), 5000.0);
+    assert_eq!(decoded.track_type(), Gdl90TrackType::TrueTrack);
+    assert!(! decoded.extrapolated());
+    assert!(decoded.airborne());
+    assert_eq!(decoded.nic(), Gdl90Nic::Rc25M);
+    assert_eq!(decoded.nic().radius(), Some(25.0));
+    assert_eq!(decoded.nacp(), Gdl90Nacp::Epu30M);
+    assert_eq!(decoded.h_velocity(), Some(123.0));
+    assert_eq!(decoded.v_velocity(), Some(64.0));
+    assert_eq!(decoded.heading(), 45.0);
+    assert_eq!(decoded.emitter_category(), Gdl90EmitterCategory::Light);
+    assert_eq!(decoded.callsign(), "N825V");
+    assert_eq!(decoded.emergency(), Gdl90Emergency::None);
+
+    // Unavailable velocities, and a callsign that is not text
+    let traffic = Gdl90Traffic {
+        h_velocity: 0xFFF,
+        v_velocity: 0x800,
+        priority: 6,
+        callsign: [0xFF; 8],
+        ..Default::default()
+    };
+    assert_eq!(traffic.h_velocity(), None);
+    assert_eq!(traffic.v_velocity(), None);
+    assert_eq!(traffic.emergency(), Gdl90Emergency::Downed);
+    assert_eq!(traffic.callsign(), "");
+
+    let ahrs = Gdl90ForeFlightAhrs {
+        roll: -150,
+        pitch: 25,
+        heading: 0x8000 | 900,
+        indicated_airspeed: 110,
+        true_airspeed: 0xFFFF,
+    };
+    let msg = Gdl90Message::new(&Gdl90Kind::ForeFlightAhrs(ahrs).encode()).unwrap();
+    let decoded = match msg.kind() {
+        Some(Gdl90Kind::ForeFlightAhrs(ahrs)) => ahrs,
+        other => panic!("expected AHRS, got {:?}", other),
+    };
+    assert_eq!(decoded.roll(), Some(-15.0));
+    assert_eq!(decoded.magnetic_heading(), Some(90.0));
+    assert_eq!(decoded.true_airspeed(), None);
+
+    let msg = Gdl90Message::new(&Gdl90Kind::GeoAltitude(Gdl90GeoAltitude { altitude: -20, metrics: 10 }).encode()).unwrap();
+    match msg.kind() {
+        Some(Gdl90Kind::GeoAltitude(altitude)) => assert_eq!(altitude.altitude(), -100.0),
+        other => panic!("expected geometric altitude, got {:?}", other),
+    }
+}
+
+/// Encode text in DLAC, padded with ETX
+fn dlac(text: &str) -> Vec<u8> {
+    const DLAC: &[u8] = b"\x03ABCDEFGHIJKLMNOPQRSTUVWXYZ\x1A\t\x1E\n| !\"#$%&'()*+,-./0123456789:;<=>?";
+    let codes: Vec<u8> = text.bytes().map(|c| DLAC.iter().position(|&d| d == c).unwrap() as u8).collect();
+    let mut data = Vec::new();
+    for chunk in codes.chunks(4) {
+        let mut bits = 0u32;
+        for i in 0..4 {
+            bits = (bits << 6) | chunk.get(i).cloned().unwrap_or(0) as u32;
+        }
+        data.extend_from_slice(&bits.to_be_bytes()[1..]);
+    }
+    data
+}
+
+/// Header of an APDU with hours and minutes
+fn apdu(product_id: u16, hours: u8, minutes: u8, payload: &[u8]) -> Gdl90InfoFrame {
+    let mut data = vec![
+        (product_id >> 6) as u8,
+        (product_id << 2) as u8,
+        (hours << 2) | (minutes >> 4),
+        minutes << 4,
+    ];
+    data.extend_from_slice(payload);
+    Gdl90InfoFrame {
+        kind: 0,
+        data,
+    }
+}
+
+/// Header of a segment of a product file, with the segmentation fields packed after the time
+fn segment(product_id: u16, minutes: u8, (file_id, file_length, number): (u16, u16, u16), payload: &[u8]) -> Gdl90InfoFrame {
+    let mut frame = apdu(product_id, 0, minutes, &[]);
+    frame.data[1] |= 0x02;
+    frame.data[3] |= (file_id >> 6) as u8;
+    frame.data.extend_from_slice(&[
+        ((file_id << 2) as u8) | ((file_length >> 7) as u8),
+        ((file_length << 1) as u8) | ((number >> 8) as u8),
+        number as u8,
+    ]);
+    frame.data.extend_from_slice(payload);
+    frame
+}
+
+#[test]
+fn uplink() {
+    assert_eq!(dlac_decode(&dlac("KDEN\tA")), "KDEN \x03\x03");
+
+    // Generic text
+    let text = apdu(413, 18, 53, &dlac(
+        "METAR KDEN 121853Z 27010KT 10SM CLR 22/M04 A3012\x1eTAF KBJC 121720Z 1218/1318 VRB05KT P6SM SKC\x1e"
+    ));
+
+    // Regional NEXRAD, with one block of bins and a set of empty blocks
+    let block = 73 * 450 + 200;
+    let mut bins = vec![0x80 | (block >> 16) as u8, (block >> 8) as u8, block as u8];
+    bins.extend_from_slice(&[(31 << 3) | 5, (31 << 3) | 5, 31 << 3, 31 << 3]);
+    let rle = apdu(63, 18, 50, &bins);
+    let empty = apdu(63, 18, 50, &[(block >> 16) as u8, (block >> 8) as u8, block as u8 + 1, 0x10 | 2, 0x01]);
+
+    // NOTAM with a TFR polygon, active until the end of the month
+    let vertex = |latitude: f64, longitude: f64, altitude: u32| -> Vec<u8> {
+        let lat = ((latitude.rem_euclid(180.0) * 524288.0 / 360.0).round() as u32) & 0x7FFFF;
+        let lon = ((longitude.rem_euclid(360.0) * 524288.0 / 360.0).round() as u32) & 0x7FFFF;
+        let alt = altitude / 100;
+        let bits = ((lon as u64) << 29) | ((lat as u64) << 10) | alt as u64;
+        bits.to_be_bytes()[2..].to_vec()
+    };
+    let (report_number, year) = (1234u16, 26u8);
+    let mut record = vec![
+        0,
+        0,
+        (report_number >> 6) as u8,
+        ((report_number << 2) as u8) | (year >> 5),
+        (year << 3) | 0x04,
+        0,
+        0,
+        0,
+        0,
+        42,
+        0,
+        0x11,
+        (2 << 6) | 3,
+        3,
+        10, 31, 23, 59,
+    ];
+    record.extend(vertex(39.5, -105.0, 0));
+    record.extend(vertex(39.6, -105.0, 18000));
+    record.extend(vertex(39.6, -104.9, 18000));
+    record.extend(vertex(39.5, -104.9, 0));
+    let length = record.len();
+    record[0] = (length >> 8) as u8;
+    record[1] = length as u8;
+    let mut twgo = vec![0x21, 0x10];
+    twgo.extend(dlac("KZDV"));
+    twgo.push(0);
+    twgo.extend(record);
+    let notam = apdu(8, 12, 0, &twgo);
+
+    let uplink = Gdl90Uplink {
+        time_of_reception: Some(1000),
+        position: Some((39.75, -104.5)),
+        utc_coupled: true,
+        slot_id: 7,
+        tisb_site_id: 3,
+        frames: vec![text, rle, empty, notam],
+    };
+    let msg = Gdl90Message::new(&Gdl90Kind::Uplink(uplink.clone()).encode()).unwrap();
+    let decoded = match msg.kind() {
+        Some(Gdl90Kind::Uplink(uplink)) => uplink,
+        other => panic!("expected uplink, got {:?}", other),
+    };
+    let (latitude, longitude) = decoded.position.unwrap();
+    assert!((latitude - 39.75).abs() < 1e-4 && (longitude + 104.5).abs() < 1e-4);
+    assert_eq!(decoded.frames, uplink.frames);
+    assert_eq!(decoded.time_of_reception, Some(1000));
+    assert_eq!(decoded.slot_id, 7);
+
+    let apdus = decoded.apdus();
+    assert_eq!(apdus.len(), 4);
+
+    assert_eq!(apdus[0].product_id, 413);
+    assert_eq!((apdus[0].time.hours, apdus[0].time.minutes), (18, 53));
+    match apdus[0].product {
+        FisbProduct::Text(ref reports) => {
+            assert_eq!(reports.len(), 2);
+            assert_eq!(reports[0].kind, "METAR");
+            assert_eq!(reports[0].location, "KDEN");
+            assert_eq!(reports[0].time, "121853Z");
+            assert_eq!(reports[0].text, "27010KT 10SM CLR 22/M04 A3012");
+            assert_eq!(reports[1].kind, "TAF");
+            assert_eq!(reports[1].location, "KBJC");
+        },
+        ref other => panic!("expected text, got {:?}", other),
+    }
+
+    match apdus[1].product {
+        FisbProduct::Nexrad(ref blocks) => {
+            assert_eq!(blocks.len(), 1);
+            assert_eq!(blocks[0].block, block);
+            assert_eq!(blocks[0].bin(0, 0), 5);
+            assert_eq!(blocks[0].bin(1, 31), 5);
+            assert_eq!(blocks[0].bin(2, 0), 0);
+            let (north, west, height, width) = blocks[0].bounds();
+            assert!((north - 74.0 / 15.0).abs() < 1e-9);
+            assert!((west - 160.0).abs() < 1e-9);
+            assert!((height - 1.0 / 15.0).abs() < 1e-9 && (width - 0.8).abs() < 1e-9);
+        },
+        ref other => panic!("expected NEXRAD, got {:?}", other),
+    }
+
+    match apdus[2].product {
+        FisbProduct::Nexrad(ref blocks) => {
+            let numbers: Vec<u32> = blocks.iter().map(|block| block.block).collect();
+            assert_eq!(numbers, vec![block + 1, block + 2, block + 6]);
+            assert!(blocks.iter().all(|block| block.bins.iter().all(|&bin| bin == 0)));
+        },
+        ref other => panic!("expected NEXRAD, got {:?}", other),
+    }
+
+    match apdus[3].product {
+        FisbProduct::Records(ref records) => match records[..] {
+            [FisbRecord::Graphic(ref graphic)] => {
+                assert_eq!(graphic.report_number, report_number);
+                assert_eq!(graphic.report_year, year);
+                assert!(graphic.active);
+                assert_eq!(graphic.overlay, 1);
+                assert_eq!(graphic.label, "42");
+                assert_eq!(graphic.start, None);
+                let end = graphic.end.unwrap();
+                assert_eq!((end.month, end.day, end.hours, end.minutes), (Some(10), Some(31), 23, 59));
+                match graphic.geometry {
+                    FisbGeometry::PolygonMsl(ref vertices) => {
+                        assert_eq!(vertices.len(), 4);
+                        assert!((vertices[2].latitude - 39.6).abs() < 1e-3);
+                        assert!((vertices[2].longitude + 104.9).abs() < 1e-3);
+                        assert_eq!(vertices[2].altitude, 18000.0);
+                    },
+                    ref other => panic!("expected polygon, got {:?}", other),
+                }
+            },
+            ref other => panic!("expected one graphic, got {:?}", other),
+        },
+        ref other => panic!("expected records, got {:?}", other),
+    }
+
+    // The same product split into segments, which arrive out of order
+    let first = segment(8, 1, (300, 2, 1), &twgo[..20]).apdu().unwrap();
+    let second = segment(8, 2, (300, 2, 2), &twgo[20..]).apdu().unwrap();
+    assert_eq!(second.product, FisbProduct::Segment(FisbSegment {
+        file_id: 300,
+        file_length: 2,
+        number: 2,
+        data: twgo[20..].to_vec(),
+    }));
+    let mut reassembler = FisbReassembler::new();
+    assert_eq!(reassembler.push(apdus[0].clone()).as_ref(), Some(&apdus[0]));
+    assert_eq!(reassembler.push(second), None);
+    assert_eq!(reassembler.pending(), 1);
+    assert_eq!(reassembler.push(segment(8, 3, (300, 2, 3), &[]).apdu().unwrap()), None);
+    let whole = reassembler.push(first).unwrap();
+    assert_eq!(whole.time.minutes, 1);
+    assert_eq!(whole.product, apdus[3].product);
+    assert_eq!(reassembler.pending(), 0);
+
+    // Files with a segment that never arrives are dropped after a while
+    assert_eq!(reassembler.push(segment(8, 4, (301, 2, 1), &twgo[..20]).apdu().unwrap()), None);
+    reassembler.expire(Instant::now());
+    assert_eq!(reassembler.pending(), 1);
+    reassembler.expire(Instant::now() + Duration::from_secs(600));
+    assert_eq!(reassembler.pending(), 0);
+
+    // Geographic location fields are not decoded
+    let mut geographic = apdu(8, 12, 0, &twgo);
+    geographic.data[0] |= 0x40;
+    let geographic = geographic.apdu().unwrap();
+    assert!(geographic.g_flag);
+    assert_eq!(geographic.product, FisbProduct::Other(twgo.clone()));
+
+    // Empty blocks wrap around their ring of latitude, which has 225 blocks from 60 degrees
+    let empty_blocks = |block: u32, bitmap: u8| -> Vec<u32> {
+        let frame = apdu(63, 18, 50, &[(block >> 16) as u8, (block >> 8) as u8, block as u8, bitmap]);
+        match frame.apdu().unwrap().product {
+            FisbProduct::Nexrad(blocks) => blocks.iter().map(|block| block.block).collect(),
+            other => panic!("expected NEXRAD, got {:?}", other),
+        }
+    };
+    assert_eq!(empty_blocks(73 * 450 + 448, 0x20 | 1), vec![73 * 450 + 448, 73 * 450]);
+    assert_eq!(empty_blocks(405000 + 225 + 224, 0x10 | 1), vec![405000 + 225 + 224, 405000 + 225]);
+}
+
+/// Header of an APDU with each time option, from fields of (value, bits) after the time option
+fn timed_apdu(time_option: u8, fields: &[(u8, usize)]) -> FisbApdu {
+    let mut bits = vec![(0u32, 3), (413, 11), (0, 1), (time_option as u32, 2)];
+    bits.extend(fields.iter().map(|&(value, len)| (value as u32, len)));
+    let mut data = Vec::new();
+    let mut used = 0;
+    for (value, len) in bits {
+        for i in (0..len).rev() {
+            if used % 8 == 0 {
+                data.push(0);
+            }
+            *data.last_mut().unwrap() |= (((value >> i) & 1) as u8) << (7 - used % 8);
+            used += 1;
+        }
+    }
+    Gdl90InfoFrame {
+        kind: 0,
+        data,
+    }.apdu().unwrap()
+}
+
+#[test]
+fn apdu_time() {
+    let time = timed_apdu(1, &[(18, 5), (53, 6), (47, 6)]).time;
+    assert_eq!(time, FisbTime { month: None, day: None, hours: 18, minutes: 53, seconds: Some(47) });
+
+    let time = timed_apdu(2, &[(10, 4), (31, 5), (18, 5), (53, 6)]).time;
+    assert_eq!(time, FisbTime { month: Some(10), day: Some(31), hours: 18, minutes: 53, seconds: None });
+
+    let time = timed_apdu(3, &[(10, 4), (31, 5), (18, 5), (53, 6), (59, 6)]).time;
+    assert_eq!(time, FisbTime { month: Some(10), day: Some(31), hours: 18, minutes: 53, seconds: Some(59) });
+    let time = timed_apdu(3, &[(12, 4), (1, 5), (23, 5), (0, 6), (32, 6)]).time;
+    assert_eq!(time, FisbTime { month: Some(12), day: Some(1), hours: 23, minutes: 0, seconds: Some(32) });
+}
+
+/// Encode a UAT ADS-B header and state vector, with altitude in feet and the raw velocity fields,
+/// where the air/ground state is in bits 12 and 13 of the first
+fn state_vector(payload_type: u8, (latitude, longitude): (f64, f64), altitude: f64, geometric: bool, (a, b, vvel): (u16, u16, u16)) -> Vec<u8> {
+    let raw_lat = (latitude.rem_euclid(180.0) * 16777216.0 / 360.0).round() as u32;
+    let raw_lon = (longitude.rem_euclid(360.0) * 16777216.0 / 360.0).round() as u32;
+    let raw_alt = ((altitude + 1000.0) / 25.0) as u16 + 1;
+    let mut p = vec![0; 18];
+    p[0] = payload_type << 3;
+    p[1..4].copy_from_slice(&[0xA1, 0x23, 0x45]);
+    p[4] = (raw_lat >> 15) as u8;
+    p[5] = (raw_lat >> 7) as u8;
+    p[6] = ((raw_lat << 1) as u8) | ((raw_lon >> 23) as u8 & 0x01);
+    p[7] = (raw_lon >> 15) as u8;
+    p[8] = (raw_lon >> 7) as u8;
+    p[9] = ((raw_lon << 1) as u8) | geometric as u8;
+    p[10] = (raw_alt >> 4) as u8;
+    // NIC of 8
+    p[11] = ((raw_alt << 4) as u8) | 8;
+    p[12] = (a >> 6) as u8;
+    p[13] = ((a << 2) as u8) | ((b >> 9) & 0x03) as u8;
+    p[14] = (b >> 1) as u8;
+    p[15] = ((b << 7) as u8) | ((vvel >> 4) & 0x7F) as u8;
+    p[16] = (vvel << 4) as u8;
+    p
+}
+
+#[test]
+fn uat_report() {
+    // Airborne, flying west at 120 knots and descending at 512 feet per minute
+    let mut payload = state_vector(1, (39.64, -104.85), 9600.0, true, (1, 0x400 | 121, 0x200 | 9));
+    payload.resize(34, 0);
+    // Light aircraft N12345, in base 40
+    let triplets = [1600 + 23 * 40 + 1, 2 * 1600 + 3 * 40 + 4, 5 * 1600 + 36 * 40 + 36];
+    for (i, triplet) in triplets.iter().enumerate() {
+        payload[17 + 2 * i] = (triplet >> 8) as u8;
+        payload[18 + 2 * i] = *triplet as u8;
+    }
+    // NACp of 9
+    payload[25] = 0x90;
+    // Pressure altitude in the auxiliary state vector
+    let aux_alt: u16 = (9500 + 1000) / 25 + 1;
+    payload[29] = (aux_alt >> 4) as u8;
+    payload[30] = (aux_alt << 4) as u8;
+
+    let report = Gdl90UatReport { time_of_reception: None, payload };
+    let msg = Gdl90Message::new(&Gdl90Kind::LongReport(report).encode()).unwrap();
+    assert_eq!(msg.id(), 31);
+    let report = match msg.kind() {
+        Some(Gdl90Kind::LongReport(report)) => report,
+        other => panic!("expected long report, got {:?}", other),
+    };
+    assert_eq!(report.time_of_reception, None);
+    let traffic = report.traffic().unwrap();
+    assert_eq!(traffic.address, 0xA12345);
+    assert_eq!(traffic.kind, 0);
+    assert!((traffic.latitude() - 39.64).abs() < 1e-4);
+    assert!((traffic.longitude() + 104.85).abs() < 1e-4);
+    assert_eq!(traffic.altitude(), 9500.0);
+    assert_eq!(traffic.track_type(), Gdl90TrackType::TrueTrack);
+    assert!(traffic.airborne());
+    assert_eq!(traffic.nic(), Gdl90Nic::Rc0_1Nm);
+    assert_eq!(traffic.nacp(), Gdl90Nacp::Epu30M);
+    assert_eq!(traffic.h_velocity(), Some(120.0));
+    assert_eq!(traffic.v_velocity(), Some(-512.0));
+    assert_eq!(traffic.heading(), 270.0);
+    assert_eq!(traffic.emitter_category(), Gdl90EmitterCategory::Light);
+    assert_eq!(&traffic.callsign, b"N12345  ");
+
+    // On the ground at 10 knots, with a true heading of 90 degrees, and no mode status
+    let payload = state_vector(0, (39.57, -104.85), 5800.0, false, (0x2000 | 11, 0x600 | 128, 0));
+    let mut data = vec![0x10, 0x20, 0x30];
+    data.extend_from_slice(&payload[..]);
+    let msg = Gdl90Message::new(&gdl90::frame(30, &data)).unwrap();
+    let report = match msg.kind() {
+        Some(Gdl90Kind::BasicReport(report)) => report,
+        other => panic!("expected basic report, got {:?}", other),
+    };
+    assert_eq!(report.time_of_reception, Some(0x302010));
+    let traffic = report.traffic().unwrap();
+    assert_eq!(traffic.altitude(), 5800.0);
+    assert_eq!(traffic.track_type(), Gdl90TrackType::TrueHeading);
+    assert!(! traffic.airborne());
+    assert_eq!(traffic.h_velocity(), Some(10.0));
+    assert_eq!(traffic.v_velocity(), None);
+    assert_eq!(traffic.heading(), 90.0);
+    assert_eq!(&traffic.callsign, b"        ");
+
+    // Reports of the wrong length are not decoded
+    assert!(Gdl90Message::new(&gdl90::frame(30, &data[..20])).unwrap().kind().is_none());
+}
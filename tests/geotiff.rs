@@ -0,0 +1,170 @@
+extern crate flate2;
+extern crate friar;
+
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use friar::geotiff::GeoTiff;
+use friar::hgt::{HgtInterpolation, HgtSampler};
+use std::io::Write;
+
+enum Value {
+    Short(Vec<u16>),
+    Long(Vec<u32>),
+    Double(Vec<f64>),
+}
+
+/// Write a little endian TIFF with the given tags and blocks, fixing up the block offsets tag
+fn tiff(mut tags: Vec<(u16, Value)>, offsets_tag: u16, blocks: &[Vec<u8>]) -> Vec<u8> {
+    let mut out = b"II*\0".to_vec();
+    out.extend_from_slice(&8u32.to_le_bytes());
+
+    tags.push((offsets_tag, Value::Long(vec![0; blocks.len()])));
+    tags.sort_by_key(|tag| tag.0);
+
+    let ifd_len = 2 + tags.len() * 12 + 4;
+    let mut extra = Vec::new();
+    let extra_start = 8 + ifd_len;
+    let blocks_start = extra_start + tags.iter().map(|(_, value)| match value {
+        Value::Short(v) => if v.len() > 2 { v.len() * 2 } else { 0 },
+        Value::Long(v) => if v.len() > 1 { v.len() * 4 } else { 0 },
+        Value::Double(v) => v.len() * 8,
+    }).sum::<usize>();
+
+    let mut block_offsets = Vec::new();
+    let mut offset = blocks_start;
+    for block in blocks {
+        block_offsets.push(offset as u32);
+        offset += block.len();
+    }
+
+    out.extend_from_slice(&(tags.len() as u16).to_le_bytes());
+    for (tag, value) in tags.iter() {
+        let value = if *tag == offsets_tag { Value::Long(block_offsets.clone()) } else {
+            match value {
+                Value::Short(v) => Value::Short(v.clone()),
+                Value::Long(v) => Value::Long(v.clone()),
+                Value::Double(v) => Value::Double(v.clone()),
+            }
+        };
+        let (kind, count, bytes): (u16, usize, Vec<u8>) = match value {
+            Value::Short(v) => (3, v.len(), v.iter().flat_map(|x| x.to_le_bytes().to_vec()).collect()),
+            Value::Long(v) => (4, v.len(), v.iter().flat_map(|x| x.to_le_bytes().to_vec()).collect()),
+            Value::Double(v) => (12, v.len(), v.iter().flat_map(|x| x.to_le_bytes().to_vec()).collect()),
+        };
+        out.extend_from_slice(&tag.to_le_bytes());
+        out.extend_from_slice(&kind.to_le_bytes());
+        out.extend_from_slice(&(count as u32).to_le_bytes());
+        if bytes.len() <= 4 {
+            let mut inline = bytes.clone();
+            inline.resize(4, 0);
+            out.extend_from_slice(&inline);
+        } else {
+            out.extend_from_slice(&((extra_start + extra.len()) as u32).to_le_bytes());
+            extra.extend_from_slice(&bytes);
+        }
+    }
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&extra);
+    for block in blocks {
+        out.extend_from_slice(block);
+    }
+    out
+}
+
+#[test]
+fn int16_strips() {
+    // 3x2 image, one strip per row, rows from north to south
+    let rows: [[i16; 3]; 2] = [[10, 20, 30], [40, 50, -32768]];
+    let blocks: Vec<Vec<u8>> = rows.iter().map(|row| {
+        row.iter().flat_map(|x| x.to_le_bytes().to_vec()).collect()
+    }).collect();
+
+    let data = tiff(vec![
+        (256, Value::Short(vec![3])),
+        (257, Value::Short(vec![2])),
+        (258, Value::Short(vec![16])),
+        (259, Value::Short(vec![1])),
+        (278, Value::Short(vec![1])),
+        (279, Value::Long(vec![6, 6])),
+        (339, Value::Short(vec![2])),
+        (33550, Value::Double(vec![0.5, 0.5, 0.0])),
+        (33922, Value::Double(vec![0.0, 0.0, 0.0, -105.25, 40.25, 0.0])),
+    ], 273, &blocks);
+
+    let geotiff = GeoTiff::new(&data).unwrap();
+    assert_eq!((geotiff.rows, geotiff.cols), (2, 3));
+    assert_eq!(geotiff.longitude, -105.0);
+    assert_eq!(geotiff.latitude, 39.5);
+
+    // Southwest sample is the first sample of the last row
+    assert_eq!(geotiff.get(0, 0), Some(40.0));
+    assert_eq!(geotiff.get(1, 2), Some(30.0));
+    assert_eq!(geotiff.get(0, 2), None);
+    assert_eq!(geotiff.elevation_at(39.75, -104.75, HgtInterpolation::Bilinear), Some(30.0));
+
+    // Images without rows are rejected
+    let data = tiff(vec![
+        (256, Value::Short(vec![3])),
+        (257, Value::Short(vec![0])),
+        (258, Value::Short(vec![16])),
+        (279, Value::Long(vec![0])),
+        (33550, Value::Double(vec![0.5, 0.5, 0.0])),
+        (33922, Value::Double(vec![0.0, 0.0, 0.0, -105.25, 40.25, 0.0])),
+    ], 273, &[Vec::new()]);
+    assert!(GeoTiff::new(&data).is_err());
+}
+
+#[test]
+fn float32_deflate_tiles() {
+    // 20x20 image of values row * 100 + col in one 16x16 tile grid, with the floating point
+    // predictor used by Copernicus
+    let (width, height, tile) = (20usize, 20usize, 16usize);
+    let mut blocks = Vec::new();
+    for tile_row in 0..2 {
+        for tile_col in 0..2 {
+            let mut raw = Vec::new();
+            for r in 0..tile {
+                let mut planes = vec![0u8; tile * 4];
+                for c in 0..tile {
+                    let value = ((tile_row * tile + r) * 100 + tile_col * tile + c) as f32;
+                    for (k, b) in value.to_be_bytes().iter().enumerate() {
+                        planes[k * tile + c] = *b;
+                    }
+                }
+                for i in (1..planes.len()).rev() {
+                    planes[i] = planes[i].wrapping_sub(planes[i - 1]);
+                }
+                raw.extend_from_slice(&planes);
+            }
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&raw).unwrap();
+            blocks.push(encoder.finish().unwrap());
+        }
+    }
+
+    let counts = blocks.iter().map(|b| b.len() as u32).collect();
+    let data = tiff(vec![
+        (256, Value::Short(vec![width as u16])),
+        (257, Value::Short(vec![height as u16])),
+        (258, Value::Short(vec![32])),
+        (259, Value::Short(vec![8])),
+        (317, Value::Short(vec![3])),
+        (322, Value::Short(vec![tile as u16])),
+        (323, Value::Short(vec![tile as u16])),
+        (325, Value::Long(counts)),
+        (339, Value::Short(vec![3])),
+        (33550, Value::Double(vec![1.0, 1.0, 0.0])),
+        (33922, Value::Double(vec![0.0, 0.0, 0.0, 0.0, 19.0, 0.0])),
+        // PixelIsPoint
+        (34735, Value::Short(vec![1, 1, 0, 1, 1025, 0, 1, 2])),
+    ], 324, &blocks);
+
+    let geotiff = GeoTiff::new(&data).unwrap();
+    assert_eq!(geotiff.latitude, 0.0);
+    assert_eq!(geotiff.longitude, 0.0);
+
+    // Top row is row 0 of the image
+    assert_eq!(geotiff.get(19, 0), Some(0.0));
+    assert_eq!(geotiff.get(0, 19), Some(1919.0));
+    assert_eq!(geotiff.get(2, 17), Some(1717.0));
+}
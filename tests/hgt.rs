@@ -0,0 +1,529 @@
+extern crate friar;
+
+use friar::Error;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use friar::hgt::{HgtCache, HgtCacheCompression, HgtChunk, HgtFile, HgtInterpolation, HgtLoader, HgtLoaderEvent, HgtMosaic, HgtResolution, HgtSampler, HgtSrtm, HgtTileId, HgtWaterMask};
+
+#[test]
+fn mosaic_edges() {
+    let res = HgtResolution::Three;
+    let mut mosaic = HgtMosaic::new(res);
+    mosaic.insert(HgtFile::from_value(39.0, -105.0, res, 100)).unwrap();
+    mosaic.insert(HgtFile::from_value(39.0, -104.0, res, 200)).unwrap();
+
+    // Inside each tile
+    assert_eq!(mosaic.elevation(39.5, -104.5), Some(100.0));
+    assert_eq!(mosaic.elevation(39.5, -103.5), Some(200.0));
+
+    // Corners and edges with no neighbouring tile loaded
+    assert_eq!(mosaic.elevation(39.0, -105.0), Some(100.0));
+    assert_eq!(mosaic.elevation(40.0, -105.0), Some(100.0));
+    assert_eq!(mosaic.elevation(40.0, -103.0), Some(200.0));
+
+    // Halfway between the last sample of one tile and the first sample of the next
+    let half = 0.5 * res.degrees();
+    let between = mosaic.elevation(39.5, -104.0 - half).unwrap();
+    assert!((between - 150.0).abs() < 1e-6);
+
+    // No tile loaded
+    assert_eq!(mosaic.elevation(38.5, -104.5), None);
+}
+
+#[test]
+fn fill_voids() {
+    let res = HgtResolution::Three;
+    let samples = res.samples() as usize;
+
+    let mut file = HgtFile::from_value(39.0, -105.0, res, 100);
+    // Void in the middle of the file
+    let index = (samples / 2) * samples + samples / 2;
+    file.data[index * 2] = 0x80;
+    file.data[index * 2 + 1] = 0x00;
+    assert_eq!(file.voids(), 1);
+    assert_eq!(file.sample(600, 600), None);
+
+    assert_eq!(file.fill_voids(8, None), 1);
+    assert_eq!(file.voids(), 0);
+    assert_eq!(file.sample(600, 600), Some(100));
+    assert!(file.is_filled(600, 600));
+    assert!(!file.is_filled(600, 601));
+
+    // Fallback takes precedence over interpolation
+    file.data[index * 2] = 0x80;
+    file.data[index * 2 + 1] = 0x00;
+    let mut fallback = HgtMosaic::new(res);
+    fallback.insert(HgtFile::from_value(39.0, -105.0, res, 50)).unwrap();
+    assert_eq!(file.fill_voids(8, Some(&fallback)), 1);
+    assert_eq!(file.sample(600, 600), Some(50));
+}
+
+#[test]
+fn sampler_plane() {
+    let res = HgtResolution::Three;
+    let samples = res.samples() as usize;
+
+    // Rises one meter per column toward the east
+    let mut data = Vec::with_capacity(samples * samples * 2);
+    for _row in 0..samples {
+        for col in 0..samples {
+            data.push((col >> 8) as u8);
+            data.push(col as u8);
+        }
+    }
+    let file = HgtFile::new(0.0, 0.0, res, data.into_boxed_slice()).unwrap();
+
+    let latitude = 0.5;
+    let longitude = 100.5 * res.degrees();
+    for &interpolation in &[HgtInterpolation::Bilinear, HgtInterpolation::Bicubic] {
+        let h = file.elevation_at(latitude, longitude, interpolation).unwrap();
+        assert!((h - 100.5).abs() < 1e-6);
+
+        let (gn, ge) = file.gradient(latitude, longitude, interpolation).unwrap();
+        assert!(gn.abs() < 1e-9);
+        assert!((ge - 1.0 / 92.6).abs() < 1e-3);
+
+        let aspect = file.aspect(latitude, longitude, interpolation).unwrap();
+        assert!((aspect - 270.0).abs() < 1e-6);
+
+        let curvature = file.curvature(latitude, longitude, interpolation).unwrap();
+        assert!(curvature.abs() < 1e-9);
+
+        let (e, n, u) = file.normal(latitude, longitude, interpolation).unwrap();
+        assert!(e < 0.0 && n.abs() < 1e-9 && u > 0.99);
+    }
+
+    assert_eq!(file.elevation_at(latitude, 100.4 * res.degrees(), HgtInterpolation::Nearest), Some(100.0));
+    assert_eq!(file.elevation_at(-0.5, 0.0, HgtInterpolation::Bilinear), None);
+}
+
+#[test]
+fn srtm_offline() {
+    let res = HgtResolution::Three;
+    let dir = std::env::temp_dir().join(format!("friar-srtm-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("SRTM3")).unwrap();
+
+    let file = HgtFile::from_value(39.0, -105.0, res, 1234);
+    std::fs::write(dir.join("SRTM3").join("N39W105.hgt"), &file.data).unwrap();
+
+    let mut srtm = HgtSrtm::new(vec![
+        "https://example.invalid/{root}/{region}/{name}.hgt.zip".to_string(),
+        format!("file://{}/{{root}}/{{name}}.hgt", dir.display()),
+    ]);
+    srtm.set_offline(true);
+
+    let loaded = srtm.get(39.0, -105.0, res).unwrap();
+    assert_eq!(loaded.sample(10, 10), Some(1234));
+    match srtm.get(40.0, -105.0, res) {
+        Err(Error::NotFound(_)) => (),
+        other => panic!("expected missing tile, got {:?}", other.err()),
+    }
+
+    // A truncated file is corrupt rather than missing
+    std::fs::write(dir.join("SRTM3").join("N40W105.hgt"), &file.data[..1000]).unwrap();
+    match srtm.get(40.0, -105.0, res) {
+        Err(Error::Format(_)) => (),
+        other => panic!("expected invalid tile, got {:?}", other.err()),
+    }
+
+    // Sources after one that fails are still tried
+    std::fs::create_dir_all(dir.join("mirror")).unwrap();
+    std::fs::write(dir.join("mirror").join("N40W105.hgt"), &file.data).unwrap();
+    let mut srtm = HgtSrtm::new(vec![
+        format!("file://{}/{{root}}/{{name}}.hgt", dir.display()),
+        format!("{}/mirror/{{name}}.hgt", dir.display()),
+    ]);
+    srtm.set_offline(true);
+    assert_eq!(srtm.get(40.0, -105.0, res).unwrap().sample(10, 10), Some(1234));
+
+    // Servers on this machine are used while offline
+    let mut srtm = HgtSrtm::new(vec!["http://127.0.0.1:1/{name}.hgt".to_string()]);
+    srtm.set_offline(true);
+    match srtm.get(40.0, -105.0, res) {
+        Err(Error::Network(_)) => (),
+        other => panic!("expected network error, got {:?}", other.err()),
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn file_bounds() {
+    let file = HgtFile::from_value(39.0, -105.0, HgtResolution::Three, 7);
+    assert_eq!(file.get(1, 1).unwrap(), Some(7));
+    match file.get(1201, 1) {
+        Err(Error::OutOfRange(_)) => (),
+        other => panic!("expected out of range, got {:?}", other),
+    }
+}
+
+#[test]
+fn cache_validation_and_eviction() {
+    let res = HgtResolution::Three;
+    let dir = std::env::temp_dir().join(format!("friar-cache-{}", std::process::id()));
+    let source = dir.join("source");
+    std::fs::create_dir_all(&source).unwrap();
+    for &(lat, name) in &[(39.0, "N39W105"), (40.0, "N40W105")] {
+        let file = HgtFile::from_value(lat, -105.0, res, 1000);
+        std::fs::write(source.join(format!("{}.hgt", name)), &file.data).unwrap();
+    }
+
+    let mut srtm = HgtSrtm::new(vec![format!("{}/{{name}}.hgt", source.display())]);
+    srtm.set_offline(true);
+
+    let mut cache = HgtCache::new(dir.join("cache"));
+    cache.set_srtm(srtm);
+    cache.set_compression(HgtCacheCompression::Zstd);
+
+    assert_eq!(cache.get(39.0, -105.0, res).unwrap().sample(1, 1), Some(1000));
+    let entries = cache.list().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].name, "N39W105");
+    assert_eq!(entries[0].compression, HgtCacheCompression::Zstd);
+
+    // A corrupt file is replaced by a new download
+    std::fs::write(&entries[0].path, b"corrupt").unwrap();
+    assert_eq!(cache.get(39.0, -105.0, res).unwrap().sample(1, 1), Some(1000));
+
+    // A file without its checksum is also replaced
+    std::fs::remove_file(entries[0].path.with_file_name("N39W105.hgt.zst.crc32")).unwrap();
+    assert_eq!(cache.get(39.0, -105.0, res).unwrap().sample(1, 1), Some(1000));
+    assert!(entries[0].path.with_file_name("N39W105.hgt.zst.crc32").exists());
+
+    // Only the most recent file fits
+    let size = entries[0].size;
+    cache.set_max_bytes(Some(size)).unwrap();
+    cache.get(40.0, -105.0, res).unwrap();
+    let entries = cache.list().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].name, "N40W105");
+
+    // Lowering the limit shrinks the cache right away
+    cache.set_max_bytes(Some(size - 1)).unwrap();
+    assert!(cache.list().unwrap().is_empty());
+    cache.get(39.0, -105.0, res).unwrap();
+    cache.set_max_bytes(None).unwrap();
+
+    assert!(cache.purge().unwrap() > 0);
+    assert!(cache.list().unwrap().is_empty());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Wait for the next event from a loader
+fn loader_event<T: Send + 'static>(loader: &HgtLoader<T>) -> HgtLoaderEvent<T> {
+    let started = Instant::now();
+    loop {
+        if let Some(event) = loader.poll() {
+            return event;
+        }
+        assert!(started.elapsed() < Duration::from_secs(10), "timed out waiting for loader");
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// Wait for a tile to be loaded, returning its sample
+fn loader_loaded(loader: &HgtLoader<Option<i16>>) -> (HgtTileId, Option<i16>) {
+    match loader_event(loader) {
+        HgtLoaderEvent::Loaded(tile, sample) => (tile, sample),
+        HgtLoaderEvent::Failed(tile, err) => panic!("failed to load {}: {}", tile, err),
+    }
+}
+
+#[test]
+fn loader() {
+    let res = HgtResolution::Three;
+    let dir = std::env::temp_dir().join(format!("friar-loader-{}", std::process::id()));
+    let source = dir.join("source");
+    std::fs::create_dir_all(&source).unwrap();
+
+    let first = HgtTileId::new(39, -105);
+    let near = HgtTileId::new(39, -104);
+    let far = HgtTileId::new(45, -111);
+    let cancelled = HgtTileId::new(40, -105);
+    let panics = HgtTileId::new(41, -105);
+    let missing = HgtTileId::new(50, -100);
+    for &(tile, value) in &[(first, 100), (near, 200), (far, 300), (cancelled, 400), (panics, 500)] {
+        let file = HgtFile::from_value(tile.south(), tile.west(), res, value);
+        std::fs::write(source.join(format!("{}.hgt", tile.name())), &file.data).unwrap();
+    }
+
+    let mut srtm = HgtSrtm::new(vec![format!("file://{}/{{name}}.hgt", source.display())]);
+    srtm.set_offline(true);
+    let mut cache = HgtCache::new(dir.join("cache"));
+    cache.set_srtm(srtm);
+
+    // Workers report each tile they start, then wait for the gate
+    let gate = Arc::new(Mutex::new(()));
+    let (started_sender, started) = mpsc::channel();
+    let loader = {
+        let gate = gate.clone();
+        let started_sender = Mutex::new(started_sender);
+        HgtLoader::new(Arc::new(cache), res, 1, move |file| {
+            started_sender.lock().unwrap().send(file.tile()).unwrap();
+            if file.tile() == panics {
+                panic!("processing {}", file.tile());
+            }
+            let _gate = gate.lock().unwrap();
+            file.sample(1, 1)
+        })
+    };
+    loader.set_viewer(39.5, -104.5);
+
+    // Duplicate requests are loaded once
+    let closed = gate.lock().unwrap();
+    loader.request(first);
+    loader.request(first);
+    assert!(loader.is_loading(first));
+    assert_eq!(started.recv_timeout(Duration::from_secs(10)).unwrap(), first);
+
+    // While the worker is busy, the closest pending tile is loaded first and cancelled tiles are
+    // not loaded at all
+    loader.request(far);
+    loader.request(near);
+    loader.request(cancelled);
+    loader.request(near);
+    loader.retain(|tile| tile != cancelled);
+    assert!(! loader.is_loading(cancelled));
+    assert!(loader.is_loading(far));
+    drop(closed);
+
+    assert_eq!(loader_loaded(&loader), (first, Some(100)));
+    assert_eq!(loader_loaded(&loader), (near, Some(200)));
+    assert_eq!(loader_loaded(&loader), (far, Some(300)));
+    assert_eq!(started.try_iter().collect::<Vec<_>>(), vec![near, far]);
+    assert!(! loader.is_loading(far));
+
+    // Missing tiles fail with the error from the cache
+    loader.request(missing);
+    match loader_event(&loader) {
+        HgtLoaderEvent::Failed(tile, Error::NotFound(_)) => assert_eq!(tile, missing),
+        _ => panic!("expected {} to be missing", missing),
+    }
+
+    // A panic while processing fails the tile, which can be requested again, and the worker keeps
+    // loading other tiles
+    for _ in 0..2 {
+        loader.request(panics);
+        match loader_event(&loader) {
+            HgtLoaderEvent::Failed(tile, Error::Io(err)) => {
+                assert_eq!(tile, panics);
+                assert!(err.to_string().contains(&format!("processing {}", panics)));
+            },
+            HgtLoaderEvent::Failed(tile, err) => panic!("expected {} to panic, got {}", tile, err),
+            HgtLoaderEvent::Loaded(tile, _) => panic!("expected {} to fail", tile),
+        }
+        assert!(! loader.is_loading(panics));
+    }
+    loader.request(cancelled);
+    assert_eq!(loader_loaded(&loader), (cancelled, Some(400)));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn chunk_error_and_split() {
+    let res = HgtResolution::Three;
+
+    // Flat files have no error, and all voids are treated as sea level
+    let flat = HgtChunk::root(&HgtFile::from_value(39.0, -105.0, res, 100), 32);
+    assert_eq!((flat.error, flat.low, flat.high), (0.0, 100.0, 100.0));
+    let void = HgtChunk::root(&HgtFile::from_fn(39.0, -105.0, res, |_, _| None), 32);
+    assert_eq!((void.error, void.low, void.high), (0.0, 0.0, 0.0));
+
+    // The root mesh uses every 37th or 38th sample, so a spike in the middle of its first cell is
+    // error, while the spike itself is not part of the mesh
+    let mut file = HgtFile::from_value(39.0, -105.0, res, 100);
+    file.set(19, 19, Some(150));
+    let root = HgtChunk::root(&file, 32);
+    let (rows, cols) = root.indexes();
+    assert_eq!(rows.len(), 33);
+    assert_eq!(&rows[..3], &[0, 38, 75]);
+    assert_eq!(rows, cols);
+    assert_eq!((root.error, root.low, root.high), (50.0, 100.0, 100.0));
+
+    let children = root.children(&file);
+    let bounds: Vec<_> = children.iter().map(|c| (c.row0, c.row1, c.col0, c.col1)).collect();
+    assert_eq!(bounds, [(0, 600, 0, 600), (0, 600, 600, 1200), (600, 1200, 0, 600), (600, 1200, 600, 1200)]);
+    assert!(children[0].error > 0.0);
+    assert_eq!(children[3].error, 0.0);
+
+    // Error of 50 meters, plus 10 meters of simplification, doubled by exaggeration
+    let screen_error = root.screen_error(10.0, 2.0, 1000.0, 12_000.0);
+    assert_eq!(screen_error, 10.0);
+    assert!(root.split(screen_error, 2.0));
+    assert!(! root.split(screen_error, 20.0));
+
+    // Chunks with every sample in their mesh are never split
+    let leaf = HgtChunk::new(&file, 32, 0, 32, 0, 32);
+    assert!(leaf.is_leaf());
+    assert_eq!(leaf.indexes().0, (0..=32).collect::<Vec<u16>>());
+    assert_eq!(leaf.error, 0.0);
+    assert!(! leaf.split(f64::MAX, 2.0));
+}
+
+#[test]
+fn tile_id_names() {
+    assert_eq!(HgtTileId::from_degrees(39.7, -104.2).name(), "N39W105");
+    assert_eq!(HgtTileId::from_degrees(-0.5, -0.5).name(), "S01W001");
+    assert_eq!(HgtTileId::from_degrees(0.0, 0.0).name(), "N00E000");
+    assert_eq!(HgtTileId::from_degrees(90.0, 180.5).name(), "N89W180");
+
+    assert_eq!("N39W105".parse::<HgtTileId>().unwrap(), HgtTileId::new(39, -105));
+    assert_eq!("s01e001.hgt.zip".parse::<HgtTileId>().unwrap(), HgtTileId::new(-1, 1));
+    assert!("N39W10".parse::<HgtTileId>().is_err());
+    assert!("X39W105".parse::<HgtTileId>().is_err());
+    assert!("N+9W105".parse::<HgtTileId>().is_err());
+    assert!("N90E000".parse::<HgtTileId>().is_err());
+}
+
+#[test]
+fn tile_id_areas() {
+    let tiles = HgtTileId::covering(39.5, -105.5, 40.5, -104.0);
+    assert_eq!(tiles, vec![
+        HgtTileId::new(39, -106),
+        HgtTileId::new(39, -105),
+        HgtTileId::new(40, -106),
+        HgtTileId::new(40, -105),
+    ]);
+
+    // Crossing the antimeridian
+    let tiles = HgtTileId::covering(0.5, 179.5, 0.5, -179.5);
+    assert_eq!(tiles, vec![HgtTileId::new(0, 179), HgtTileId::new(0, -180)]);
+
+    // About 7 km from the corner at 40N 105W, so only the four tiles sharing it are reached
+    let tiles = HgtTileId::within_radius(40.05, -104.95, 10_000.0);
+    assert_eq!(tiles.len(), 4);
+    assert!(tiles.contains(&HgtTileId::new(39, -106)));
+    assert_eq!(HgtTileId::within_radius(40.5, -104.5, 10_000.0), vec![HgtTileId::new(40, -105)]);
+}
+
+#[test]
+fn write_and_transform() {
+    // Rises one meter per arc-second toward the east, with a void in the southwest corner
+    let file = HgtFile::from_fn(-1.0, 10.0, HgtResolution::Three, |latitude, longitude| {
+        if latitude < -0.9 && longitude < 10.1 {
+            None
+        } else {
+            Some((longitude - 10.0) * 3600.0)
+        }
+    });
+    assert_eq!(file.tile().name(), "S01E010");
+    assert_eq!(file.sample(0, 0), None);
+    assert_eq!(file.sample(1200, 1200), Some(3600));
+
+    let dir = std::env::temp_dir().join(format!("friar-write-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    file.save(&dir).unwrap();
+    // Saving again replaces the file
+    file.save(dir.join("S01E010.hgt")).unwrap();
+    let names: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+    assert_eq!(names, ["S01E010.hgt"]);
+    let read = HgtFile::from_path(-1.0, 10.0, HgtResolution::Three, dir.join("S01E010.hgt")).unwrap();
+    assert!(read.data == file.data);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // Every third sample is shared, and others are interpolated on the way back
+    let one = file.resample(HgtResolution::One, HgtInterpolation::Bilinear);
+    assert_eq!(one.sample(3600, 1), Some(1));
+    assert_eq!(one.sample(3600, 3600), Some(3600));
+    let three = one.resample(HgtResolution::Three, HgtInterpolation::Bilinear);
+    assert_eq!(three.sample(1200, 601), Some(1803));
+
+    let cropped = file.crop((-0.5, 10.5, 0.0, 11.0));
+    assert_eq!(cropped.sample(599, 700), None);
+    assert_eq!(cropped.sample(600, 599), None);
+    assert_eq!(cropped.sample(600, 600), Some(1800));
+
+    let mut merged = cropped;
+    assert_eq!(merged.merge(&HgtFile::from_value(-1.0, 10.0, HgtResolution::Three, 7)).unwrap(), 1201 * 1201 - 601 * 601);
+    assert_eq!(merged.sample(0, 0), Some(7));
+    assert_eq!(merged.sample(1200, 1200), Some(3600));
+    assert!(merged.merge(&HgtFile::from_value(-1.0, 11.0, HgtResolution::Three, 7)).is_err());
+}
+
+/// Build a shapefile with a single polygon record of (longitude, latitude) rings
+fn shapefile(rings: &[&[(f64, f64)]]) -> Vec<u8> {
+    let points: usize = rings.iter().map(|ring| ring.len()).sum();
+    let mut content = Vec::new();
+    content.extend_from_slice(&5i32.to_le_bytes());
+    content.extend_from_slice(&[0; 32]);
+    content.extend_from_slice(&(rings.len() as i32).to_le_bytes());
+    content.extend_from_slice(&(points as i32).to_le_bytes());
+    let mut start = 0;
+    for ring in rings.iter() {
+        content.extend_from_slice(&(start as i32).to_le_bytes());
+        start += ring.len();
+    }
+    for ring in rings.iter() {
+        for &(x, y) in ring.iter() {
+            content.extend_from_slice(&x.to_le_bytes());
+            content.extend_from_slice(&y.to_le_bytes());
+        }
+    }
+
+    let mut data = vec![0; 100];
+    data[0..4].copy_from_slice(&9994i32.to_be_bytes());
+    data[24..28].copy_from_slice(&(((100 + 8 + content.len()) / 2) as i32).to_be_bytes());
+    data[28..32].copy_from_slice(&1000i32.to_le_bytes());
+    data[32..36].copy_from_slice(&5i32.to_le_bytes());
+    data.extend_from_slice(&1i32.to_be_bytes());
+    data.extend_from_slice(&((content.len() / 2) as i32).to_be_bytes());
+    data.extend_from_slice(&content);
+    data
+}
+
+#[test]
+fn water_mask() {
+    let res = HgtResolution::Three;
+    let tile = HgtTileId::new(39, -105);
+
+    // A lake with an island, with edges between samples
+    let data = shapefile(&[
+        &[(-104.7504, 39.2496), (-104.7504, 39.7496), (-104.2504, 39.7496), (-104.2504, 39.2496), (-104.7504, 39.2496)],
+        &[(-104.6004, 39.3996), (-104.4004, 39.3996), (-104.4004, 39.5996), (-104.6004, 39.5996), (-104.6004, 39.3996)],
+    ]);
+    let mask = HgtWaterMask::from_shapefile(tile, res, &data).unwrap();
+    assert!(mask.water_at(39.3, -104.5));
+    assert!(mask.water_at(39.5, -104.7));
+    assert!(! mask.water_at(39.5, -104.5));
+    assert!(! mask.water_at(39.1, -104.5));
+    assert!(! mask.water_at(39.5, -105.5));
+    assert_eq!(mask.count(), 600 * 600 - 240 * 240);
+
+    assert!(HgtWaterMask::from_shapefile(tile, res, &[0; 100]).is_err());
+
+    // Negative or huge counts of parts and points are rejected, rather than read out of bounds
+    for &(offset, value) in [(104, (-1i32).to_be_bytes()), (144, (-1i32).to_le_bytes()), (148, (-1i32).to_le_bytes()), (148, i32::MAX.to_le_bytes())].iter() {
+        let mut corrupt = data.clone();
+        corrupt[offset..offset + 4].copy_from_slice(&value);
+        assert!(matches!(HgtWaterMask::from_shapefile(tile, res, &corrupt), Err(Error::Format(_))));
+    }
+
+    // Points that are not finite are rejected, rather than sorted
+    let nan = shapefile(&[&[(f64::NAN, 39.0), (-104.0, 39.0), (-104.0, 40.0), (f64::NAN, 39.0)]]);
+    assert!(matches!(HgtWaterMask::from_shapefile(tile, res, &nan), Err(Error::Format(_))));
+
+    // Noisy water is leveled to its lowest sample, leaving land alone
+    let mut file = HgtFile::from_fn(39.0, -105.0, res, |latitude, longitude| {
+        Some(1500.0 + ((latitude * 1000.0).sin() * 5.0).round() + (longitude + 105.0) * 10.0)
+    });
+    let before = file.sample(0, 0);
+    assert!(mask.flatten(&mut file).unwrap() > 0);
+    assert_eq!(file.sample(0, 0), before);
+    let level = file.sample(360, 360);
+    assert!(level.unwrap() <= 1500 - 4 + 3);
+    assert_eq!(file.sample(840, 840), level);
+    assert!(mask.flatten(&mut HgtFile::from_value(40.0, -105.0, res, 0)).is_err());
+
+    // Found by the name of the SWBD file
+    let dir = std::env::temp_dir().join(format!("friar-water-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    assert!(HgtWaterMask::find(tile, res, &dir).unwrap().is_none());
+    std::fs::write(dir.join("w105n39n.shp"), &data).unwrap();
+    let found = HgtWaterMask::find(tile, res, &dir).unwrap().unwrap();
+    assert_eq!(found.count(), mask.count());
+    assert!(HgtWaterMask::find(HgtTileId::new(39, -104), res, &dir).unwrap().is_none());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,157 @@
+extern crate friar;
+
+use friar::earth::Earth;
+use friar::hgt::{HgtFile, HgtResolution};
+use friar::mesh::{Mesh, MeshFrame, MeshOptions, Rtin};
+use std::collections::HashMap;
+
+fn file() -> HgtFile {
+    HgtFile::from_value(39.0, -105.0, HgtResolution::Three, 1000)
+}
+
+#[test]
+fn enu_grid() {
+    let res = HgtResolution::Three;
+    // 11 by 11 samples, centered on the origin
+    let d = 10.0 * res.degrees();
+    let mut options = MeshOptions::new((39.5, -104.5, 39.5 + d, -104.5 + d));
+    options.exaggeration = 2.0;
+
+    let mesh = Mesh::new(&file(), &Earth, &options);
+    assert_eq!(mesh.positions.len(), 121);
+    assert_eq!(mesh.triangles.len(), 200);
+
+    // 30 arc-seconds across, and raised by twice the elevation
+    let (min, max) = mesh.extent();
+    assert!((max[0] - min[0] - 715.5).abs() < 5.0);
+    assert!((max[1] - min[1] - 926.6).abs() < 5.0);
+    assert!((max[2] - 2000.0).abs() < 1.0);
+
+    // Every triangle faces up
+    for n in mesh.face_normals() {
+        assert!(n[2] > 0.99);
+    }
+
+    // Every other sample, keeping the last
+    options.lod = 2;
+    let mesh = Mesh::new(&file(), &Earth, &options);
+    assert_eq!(mesh.positions.len(), 16);
+
+    options.frame = MeshFrame::Ecef;
+    let mesh = Mesh::new(&file(), &Earth, &options);
+    let p = mesh.positions[0];
+    let r = (p[0].powi(2) + p[1].powi(2) + p[2].powi(2)).sqrt();
+    assert!(r > 6_360_000.0 && r < 6_372_000.0);
+}
+
+#[test]
+fn closed_solid() {
+    let res = HgtResolution::Three;
+    let d = 4.0 * res.degrees();
+    let mut options = MeshOptions::new((39.5, -104.5, 39.5 + d, -104.5 + d));
+    options.base = Some(10.0);
+
+    let mesh = Mesh::new(&file(), &Earth, &options);
+
+    // Each directed edge is used once, and in the opposite direction by another triangle
+    let mut edges = HashMap::new();
+    for t in mesh.triangles.iter() {
+        for k in 0..3 {
+            *edges.entry((t[k], t[(k + 1) % 3])).or_insert(0) += 1;
+        }
+    }
+    for (&(a, b), &count) in edges.iter() {
+        assert_eq!(count, 1);
+        assert_eq!(edges.get(&(b, a)), Some(&1));
+    }
+
+    let (min, _max) = mesh.extent();
+    assert!((min[2] - 990.0).abs() < 1.0);
+}
+
+#[test]
+fn formats() {
+    let res = HgtResolution::Three;
+    let d = 2.0 * res.degrees();
+    let mesh = Mesh::new(&file(), &Earth, &MeshOptions::new((39.5, -104.5, 39.5 + d, -104.5 + d)));
+    assert_eq!(mesh.positions.len(), 9);
+    assert_eq!(mesh.triangles.len(), 8);
+
+    let mut obj = Vec::new();
+    mesh.write_obj(&mut obj).unwrap();
+    let obj = String::from_utf8(obj).unwrap();
+    assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 9);
+    assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), 8);
+
+    let mut stl = Vec::new();
+    mesh.write_stl(&mut stl).unwrap();
+    assert_eq!(stl.len(), 84 + 8 * 50);
+    assert_eq!(&stl[80..84], &8u32.to_le_bytes());
+
+    let mut glb = Vec::new();
+    mesh.write_gltf(&mut glb).unwrap();
+    assert_eq!(&glb[0..4], b"glTF");
+    assert_eq!(u32::from_le_bytes([glb[8], glb[9], glb[10], glb[11]]) as usize, glb.len());
+    let json_len = u32::from_le_bytes([glb[12], glb[13], glb[14], glb[15]]) as usize;
+    assert_eq!(&glb[16..20], b"JSON");
+    let json = std::str::from_utf8(&glb[20..20 + json_len]).unwrap();
+    assert!(json.contains("\"COLOR_0\":2"));
+    // Positions, normals, colors and indices
+    let bin_len = u32::from_le_bytes([glb[20 + json_len], glb[21 + json_len], glb[22 + json_len], glb[23 + json_len]]) as usize;
+    assert_eq!(bin_len, 9 * 12 + 9 * 12 + 9 * 4 + 8 * 12);
+}
+
+#[test]
+fn rtin_adaptive() {
+    // Doubled area of a triangle of (row, column) corners, positive if counter-clockwise
+    let area = |t: &[(usize, usize); 3]| {
+        let (a, b, c) = (t[0], t[1], t[2]);
+        (b.1 as i64 - a.1 as i64) * (c.0 as i64 - a.0 as i64) -
+        (b.0 as i64 - a.0 as i64) * (c.1 as i64 - a.1 as i64)
+    };
+
+    assert!(Rtin::new(16, |_, _| Some(0.0)).is_err());
+    assert!(Rtin::new(2, |_, _| Some(0.0)).is_err());
+
+    // A plane is exactly two triangles
+    let plane = Rtin::new(17, |row, col| Some(row as f64 * 3.0 + col as f64)).unwrap();
+    assert_eq!(plane.max_error(), 0.0);
+    assert_eq!(plane.triangles(0.0).len(), 2);
+
+    // A ridge along the middle column keeps detail only near it
+    let ridge = |_row: usize, col: usize| Some(100.0 - (col as f64 - 16.0).abs().powi(2));
+    let rtin = Rtin::new(33, ridge).unwrap();
+    let full = rtin.triangles(-1.0);
+    assert_eq!(full.len(), 32 * 32 * 2);
+    let simple = rtin.triangles(1.0);
+    assert!(simple.len() < full.len() / 4);
+    for triangles in [&full, &simple].iter() {
+        assert!(triangles.iter().all(|t| area(t) > 0));
+        assert_eq!(triangles.iter().map(area).sum::<i64>(), 32 * 32 * 2);
+    }
+
+    // Every sample is within the error of the triangle covering it
+    for t in simple.iter() {
+        let (a, b, c) = (t[0], t[1], t[2]);
+        let total = area(t) as f64;
+        for row in 0..33 {
+            for col in 0..33 {
+                let p = (row, col);
+                let weight = |u: (usize, usize), v: (usize, usize)| area(&[u, v, p]) as f64 / total;
+                let (wa, wb, wc) = (weight(b, c), weight(c, a), weight(a, b));
+                if wa < 0.0 || wb < 0.0 || wc < 0.0 {
+                    continue;
+                }
+                let h = |(row, col): (usize, usize)| ridge(row, col).unwrap();
+                let estimate = wa * h(a) + wb * h(b) + wc * h(c);
+                assert!((estimate - h(p)).abs() <= 1.0 + 1e-9);
+            }
+        }
+    }
+
+    // Voids are left out, with full detail around them
+    let void = Rtin::new(9, |row, col| if (row, col) == (4, 4) { None } else { Some(0.0) }).unwrap();
+    let triangles = void.triangles(10.0);
+    assert!(triangles.iter().all(|t| ! t.contains(&(4, 4))));
+    assert_eq!(triangles.iter().map(area).sum::<i64>(), 8 * 8 * 2 - 8);
+}
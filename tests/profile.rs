@@ -0,0 +1,65 @@
+extern crate friar;
+
+use friar::coordinate::Coordinate;
+use friar::earth::Earth;
+use friar::hgt::{HgtFile, HgtMosaic, HgtResolution};
+use friar::profile::Profile;
+
+/// Plains at 1000 meters with a 2000 meter plateau east of 104.5 west
+fn terrain() -> HgtFile {
+    HgtFile::from_fn(39.0, -105.0, HgtResolution::Three, |_latitude, longitude| {
+        Some(if longitude >= -104.5 { 2000.0 } else { 1000.0 })
+    })
+}
+
+#[test]
+fn path() {
+    let file = terrain();
+    let path = [
+        Coordinate::new(&Earth, 39.5, -104.6, 1500.0),
+        Coordinate::new(&Earth, 39.5, -104.4, 2500.0),
+    ];
+    let length = path[0].distance(&path[1]);
+
+    let profile = Profile::new(&file, &path, 100.0);
+    assert_eq!(profile.points.len(), (length / 100.0).ceil() as usize + 1);
+    assert!((profile.length() - length).abs() < 1e-6);
+    for pair in profile.points.windows(2) {
+        assert!(pair[1].distance - pair[0].distance <= 100.0 + 1e-6);
+    }
+
+    // Planned altitude is interpolated between the coordinates
+    let middle = &profile.points[profile.points.len() / 2];
+    let expected = 1500.0 + 1000.0 * middle.distance / length;
+    assert!((middle.coordinate.elevation - expected).abs() < 1e-6);
+
+    assert!((profile.lowest().unwrap().elevation.unwrap() - 1000.0).abs() < 1e-6);
+    assert!((profile.highest().unwrap().elevation.unwrap() - 2000.0).abs() < 1e-6);
+    assert_eq!(profile.elevations().count(), profile.points.len());
+
+    // Closest just after reaching the plateau, at about 2000 meters planned
+    let closest = profile.min_clearance().unwrap();
+    assert!(closest.coordinate.longitude >= -104.5);
+    assert!(closest.clearance().unwrap() < 50.0);
+}
+
+#[test]
+fn mosaic() {
+    // The path crosses from this tile into the tile to the east, which is only known to the mosaic
+    let path = [
+        Coordinate::new(&Earth, 39.5, -104.1, 2500.0),
+        Coordinate::new(&Earth, 39.5, -103.9, 2500.0),
+    ];
+    let file = terrain();
+    let known = Profile::new(&file, &path, 500.0).elevations().count();
+
+    let mut mosaic = HgtMosaic::new(HgtResolution::Three);
+    mosaic.insert(terrain()).unwrap();
+    mosaic.insert(HgtFile::from_value(39.0, -104.0, HgtResolution::Three, 1200)).unwrap();
+
+    let profile = Profile::new(&mosaic, &path, 500.0);
+    assert!(known > 0 && known < profile.points.len());
+    assert_eq!(profile.elevations().count(), profile.points.len());
+    assert!((profile.lowest().unwrap().elevation.unwrap() - 1200.0).abs() < 1e-6);
+    assert!((profile.min_clearance().unwrap().clearance().unwrap() - 500.0).abs() < 1e-6);
+}
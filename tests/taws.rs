@@ -0,0 +1,104 @@
+extern crate friar;
+
+use friar::gdl90::Gdl90Traffic;
+use friar::hgt::{HgtFile, HgtResolution};
+use friar::taws::{Taws, TawsLevel, TawsOwnship};
+use friar::x_plane::XPlanePosition;
+
+/// Plains at 1000 meters with a 2000 meter plateau east of 104.5 west
+fn terrain() -> HgtFile {
+    HgtFile::from_fn(39.0, -105.0, HgtResolution::Three, |_latitude, longitude| {
+        Some(if longitude >= -104.5 { 2000.0 } else { 1000.0 })
+    })
+}
+
+fn ownship(longitude: f64, altitude: f64, ground_speed: f64) -> TawsOwnship {
+    TawsOwnship {
+        latitude: 39.5,
+        longitude,
+        altitude,
+        ground_speed,
+        track: 90.0,
+        vertical_speed: 0.0,
+    }
+}
+
+#[test]
+fn flight_path() {
+    let taws = Taws::new();
+    let file = terrain();
+
+    // About 100 seconds from the plateau
+    let alert = taws.check(&file, &ownship(-104.6, 1600.0, 86.0));
+    assert_eq!(alert.level, TawsLevel::Clear);
+    assert!((alert.clearance.unwrap() - 600.0).abs() < 1.0);
+
+    // About 50 seconds
+    let alert = taws.check(&file, &ownship(-104.55, 1600.0, 86.0));
+    assert_eq!(alert.level, TawsLevel::Caution);
+    let time = alert.time.unwrap();
+    assert!(time > 45.0 && time < 55.0);
+    let (_, longitude, elevation) = alert.conflict.unwrap();
+    assert!(longitude >= -104.5 && elevation == 2000.0);
+
+    // About 20 seconds
+    let warning = taws.check(&file, &ownship(-104.52, 1600.0, 86.0));
+    assert_eq!(warning.level, TawsLevel::Warning);
+
+    // High enough, too slow, or climbing over it
+    assert_eq!(taws.check(&file, &ownship(-104.52, 2500.0, 86.0)).level, TawsLevel::Clear);
+    assert_eq!(taws.check(&file, &ownship(-104.52, 1600.0, 5.0)).level, TawsLevel::Clear);
+    let mut climbing = ownship(-104.55, 1600.0, 86.0);
+    climbing.vertical_speed = 20.0;
+    assert_eq!(taws.check(&file, &climbing).level, TawsLevel::Clear);
+
+    assert_eq!(taws.terrain_level(1000.0, 1010.0), TawsLevel::Warning);
+    assert_eq!(taws.terrain_level(1000.0, 900.0), TawsLevel::Caution);
+    assert_eq!(taws.terrain_level(1000.0, 800.0), TawsLevel::Clear);
+}
+
+#[test]
+fn ownship_sources() {
+    let position = XPlanePosition {
+        latitude: 39.5,
+        longitude: -104.5,
+        elevation: 2000.0,
+        speed_east: 30.0,
+        speed_south: -40.0,
+        speed_up: -5.0,
+        ..Default::default()
+    };
+    let ownship = TawsOwnship::from_x_plane(&position);
+    assert!((ownship.ground_speed - 50.0).abs() < 1e-9);
+    assert!((ownship.track - 36.87).abs() < 0.01);
+    assert_eq!(ownship.vertical_speed, -5.0);
+
+    // 120 knots, descending 640 feet per minute, track 90 degrees, 5000 feet pressure altitude
+    let traffic = Gdl90Traffic {
+        status: 0,
+        kind: 0,
+        address: 0,
+        latitude: 0,
+        longitude: 0,
+        altitude: (5000 + 1000) / 25,
+        misc: 0,
+        integrity: 0,
+        accuracy: 0,
+        h_velocity: 120,
+        v_velocity: 0xFF6,
+        heading: 64,
+        category: 0,
+        callsign: [b' '; 8],
+        priority: 0,
+        spare: 0,
+    };
+
+    let ownship = TawsOwnship::from_gdl90(&traffic, None);
+    assert!((ownship.ground_speed - 120.0 * 1852.0 / 3600.0).abs() < 1e-9);
+    assert!((ownship.vertical_speed + 640.0 * 0.3048 / 60.0).abs() < 1e-9);
+    assert_eq!(ownship.track, 90.0);
+    assert!((ownship.altitude - 5000.0 * 0.3048).abs() < 1e-9);
+
+    let ownship = TawsOwnship::from_gdl90(&traffic, Some(5500.0));
+    assert!((ownship.altitude - 5500.0 * 0.3048).abs() < 1e-9);
+}